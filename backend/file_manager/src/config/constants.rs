@@ -1,6 +1,219 @@
+use crate::enums::app_enums::HttpMethod;
+use crate::library::access_log::AccessLogFormat;
+use crate::library::tp::QueuePolicy;
+use crate::library::vfs::MountKind;
+
 pub const HOST_IP_ADDRESS:        &str        = "127.0.0.1";
 pub const HOST_DEFAULT_PORT:      &str        = "7000";
-pub const TOTAL_ACTIVE_THREADS:   usize       = 10;
+pub const STORAGE_ROOT:           &str        = "./storage";
+pub const TRASH_ROOT:             &str        = "./trash";
+pub const MAX_REQUEST_BODY_SIZE:  usize       = 64 * 1024 * 1024;
+
+// THREAD POOL
+pub const THREAD_POOL_MIN_THREADS:      usize       = 4;
+pub const THREAD_POOL_MAX_THREADS:      usize       = 32;
+pub const THREAD_POOL_KEEP_ALIVE_SECS:  u64         = 60;
+pub const THREAD_POOL_QUEUE_DEPTH:      usize       = 256;
+pub const THREAD_POOL_QUEUE_POLICY:     QueuePolicy = QueuePolicy::Reject;
+pub const THREAD_POOL_RETRY_AFTER:      u32         = 1;
+
+// SCHEDULING, CLASS LIMITS ARE ORDERED INTERACTIVE, NORMAL, BULK
+pub const THREAD_POOL_CLASS_LIMITS:     [usize; 3]  = [32, 24, 8];
+pub const THREAD_POOL_AGING_MILLIS:     u64         = 500;
+
+pub const BULK_ROUTING_TABLE: &'static [(HttpMethod, &str)] = &[
+  (HttpMethod::DELETE, "/files"),
+  (HttpMethod::GET, "/search"),
+  (HttpMethod::GET, "/archive"),
+  (HttpMethod::GET, "/archive/list"),
+  (HttpMethod::GET, "/files/checksum"),
+  (HttpMethod::GET, "/files/manifest"),
+  (HttpMethod::POST, "/files/manifest/verify"),
+  (HttpMethod::POST, "/files/duplicates/resolve"),
+];
+
+// ACCESS LOG, "-" WRITES TO STDOUT
+pub const ACCESS_LOG_PATH:              &str            = "./logs/access.log";
+pub const ACCESS_LOG_FORMAT:            AccessLogFormat = AccessLogFormat::Combined;
+
+// READINESS
+pub const READINESS_MIN_FREE_BYTES:     u64         = 512 * 1024 * 1024;
+pub const READINESS_MAX_QUEUE_PERCENT:  usize       = 90;
+
+// METRICS
+pub const METRICS_LATENCY_BUCKETS: &'static [f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+// COMPRESSION
+pub const COMPRESSION_MIN_LENGTH:       usize       = 1024;
+pub const COMPRESSION_BROTLI_QUALITY:   u32         = 5;
+pub const COMPRESSION_BROTLI_WINDOW:    u32         = 22;
+pub const COMPRESSION_BUFFER_SIZE:      usize       = 8192;
+
+pub const INCOMPRESSIBLE_MIME_TYPES: &'static [&str] = &[
+  "image/",
+  "video/",
+  "audio/",
+  "font/woff",
+  "application/zip",
+  "application/gzip",
+  "application/x-gzip",
+  "application/x-bzip2",
+  "application/x-xz",
+  "application/x-7z-compressed",
+  "application/x-rar-compressed",
+  "application/zstd",
+  "application/pdf",
+  // STREAMED LINE BY LINE, AN ENCODER WOULD BUFFER RESULTS THE CLIENT SHOULD SEE RIGHT AWAY
+  "application/x-ndjson",
+];
+
+// STATIC ASSETS
+pub const STATIC_ASSETS_ROOT:           &str        = "../../tools/json_formatter/static/dist";
+pub const STATIC_ASSETS_PREFIX:         &str        = "/ui";
+pub const STATIC_ASSETS_SPA_FALLBACK:   bool        = true;
+pub const STATIC_ASSETS_MAX_AGE:        u32         = 3600;
+
+pub const STATIC_INDEX_FILES: &'static [&str] = &[
+  "index.html",
+  "index.htm",
+];
+
+// CORS
+pub const CORS_ALLOW_CREDENTIALS:       bool        = false;
+pub const CORS_MAX_AGE:                 u32         = 600;
+
+pub const CORS_ALLOWED_ORIGINS: &'static [&str] = &[
+  "http://localhost:7001",
+  "http://127.0.0.1:7001",
+];
+
+pub const CORS_ALLOWED_METHODS: &'static [&str] = &[
+  "GET",
+  "POST",
+  "DELETE",
+  "OPTIONS",
+];
+
+pub const CORS_ALLOWED_HEADERS: &'static [&str] = &[
+  "Content-Type",
+  "Authorization",
+  "If-Match",
+  "If-None-Match",
+  "If-Modified-Since",
+  "If-Unmodified-Since",
+];
+
+pub const CORS_EXPOSED_HEADERS: &'static [&str] = &[
+  "ETag",
+  "Last-Modified",
+  "Content-Encoding",
+  "Retry-After",
+  "RateLimit-Limit",
+  "RateLimit-Remaining",
+  "RateLimit-Reset",
+  "RateLimit-Policy",
+];
+
+// RATE LIMITING, BUCKETS REFILL CONTINUOUSLY AT CAPACITY PER WINDOW
+pub const RATE_LIMIT_CAPACITY:            u32     = 120;
+pub const RATE_LIMIT_WINDOW:              u32     = 60;
+pub const RATE_LIMIT_EXPENSIVE_CAPACITY:  u32     = 10;
+pub const RATE_LIMIT_EXPENSIVE_WINDOW:    u32     = 60;
+pub const RATE_LIMIT_MAX_CLIENTS:         usize   = 10_000;
+pub const RATE_LIMIT_SWEEP_SECS:          u64     = 60;
+
+pub const EXPENSIVE_ROUTING_TABLE: &'static [(HttpMethod, &str)] = &[
+  (HttpMethod::POST, "/files"),
+  (HttpMethod::GET, "/search"),
+  (HttpMethod::GET, "/search/content"),
+  (HttpMethod::GET, "/archive"),
+  (HttpMethod::POST, "/archive/extract"),
+  (HttpMethod::GET, "/files/checksum"),
+  (HttpMethod::GET, "/files/manifest"),
+  (HttpMethod::POST, "/files/manifest/verify"),
+  (HttpMethod::GET, "/files/usage"),
+  (HttpMethod::GET, "/files/duplicates"),
+  (HttpMethod::POST, "/files/duplicates/resolve"),
+  (HttpMethod::POST, "/chunks/gc"),
+];
+
+// SEARCH
+pub const SEARCH_MAX_DEPTH:               usize   = 64;
+pub const SEARCH_DEFAULT_LIMIT:           usize   = 1000;
+pub const SEARCH_MAX_LIMIT:               usize   = 100_000;
+pub const SEARCH_TIMEOUT_SECS:            u64     = 30;
+
+pub const SEARCH_IGNORE_FILES: &'static [&str] = &[
+  ".gitignore",
+  ".fmignore",
+];
+
+// CONTENT INDEX, UPLOADS AND DELETES UPDATE IT RIGHT AWAY, THE RESCAN CATCHES EVERYTHING ELSE
+pub const INDEX_PATH:                     &str    = "./index/content.idx";
+pub const INDEX_RESCAN_SECS:              u64     = 300;
+pub const INDEX_MAX_FILE_SIZE:            u64     = 8 * 1024 * 1024;
+pub const INDEX_MAX_TERM_LENGTH:          usize   = 64;
+pub const INDEX_SNIPPET_LENGTH:           usize   = 160;
+pub const INDEX_DEFAULT_LIMIT:            usize   = 100;
+pub const INDEX_MAX_LIMIT:                usize   = 1000;
+
+// ARCHIVES
+pub const ARCHIVE_COMPRESSION_LEVEL:      u32     = 6;
+pub const ARCHIVE_MAX_SELECTION:          usize   = 1000;
+
+// EXTRACTION, THE RATIO IS ONLY ENFORCED ONCE THE GRACE AMOUNT HAS BEEN WRITTEN
+pub const ARCHIVE_MAX_MEMBERS:            usize   = 100_000;
+pub const ARCHIVE_MAX_EXTRACT_SIZE:       u64     = 4 * 1024 * 1024 * 1024;
+pub const ARCHIVE_MAX_RATIO:              u64     = 100;
+pub const ARCHIVE_RATIO_GRACE:            u64     = 16 * 1024 * 1024;
+pub const ARCHIVE_MAX_JOBS:               usize   = 4;
+pub const ARCHIVE_JOB_HISTORY:            usize   = 100;
+
+// BACKGROUND TASKS
+pub const TASK_MAX_RUNNING:               usize   = 4;
+pub const TASK_HISTORY:                   usize   = 100;
+
+// CHECKSUMS, LARGER FILES ARE HASHED AS A BACKGROUND TASK
+pub const CHECKSUM_INLINE_LIMIT:          u64     = 64 * 1024 * 1024;
+pub const CHECKSUM_BUFFER_SIZE:           usize   = 256 * 1024;
+
+// DISK USAGE, A SCAN KEEPS AT MOST USAGE_MAX_TOP CHILDREN PER DIRECTORY
+pub const USAGE_DEFAULT_TOP:              usize   = 20;
+pub const USAGE_MAX_TOP:                  usize   = 1000;
+pub const USAGE_DEFAULT_DEPTH:            usize   = 3;
+pub const USAGE_MAX_DEPTH:                usize   = 32;
+pub const USAGE_CACHE_ENTRIES:            usize   = 32;
+// EVENTS ARE ALSO APPLIED THIS OFTEN WHEN NOBODY READS THE CACHE, SO THEY DO NOT PILE UP
+pub const USAGE_DRAIN_SECS:               u64     = 5;
+
+// DUPLICATES, FILES OF THE SAME SIZE ARE COMPARED BY THEIR FIRST AND LAST BLOCK BEFORE A FULL HASH
+pub const DUPLICATE_BLOCK_SIZE:           u64     = 4096;
+pub const DUPLICATE_MAX_SETS:             usize   = 10_000;
+pub const DUPLICATE_MAX_RESOLVE:          usize   = 1000;
+
+// VERSIONS ARE OPT-IN, OVERWRITES AND DELETES UNDER THESE ROOTS KEEP THE OLD CONTENT. "/" VERSIONS ALL OF STORAGE
+pub const VERSION_STORE:                  &str    = "./versions";
+pub const VERSION_KEEP_COUNT:             usize   = 50;
+pub const VERSION_KEEP_DAYS:              u64     = 90;
+pub const VERSION_MAX_FILE_SIZE:          u64     = 1024 * 1024 * 1024;
+
+pub const VERSION_ROOTS: &'static [&str] = &[];
+
+// CHUNKED STORAGE, FILES UNDER THESE ROOTS ARE KEPT AS MANIFESTS OF CONTENT-DEFINED CHUNKS, ABOUT 8 KiB EACH
+pub const CHUNK_STORE:                    &str    = "./chunks";
+pub const CHUNK_MIN_SIZE:                 usize   = 2 * 1024;
+pub const CHUNK_AVERAGE_BITS:             u32     = 13;
+pub const CHUNK_MAX_SIZE:                 usize   = 64 * 1024;
+pub const CHUNK_GC_GRACE_SECS:            u64     = 60 * 60;
+
+pub const CHUNKED_ROOTS: &'static [&str] = &[
+  "/backups",
+];
+
+// MOUNTS, A PATH IS SERVED BY THE BACKEND WITH THE LONGEST MATCHING PREFIX
+pub const MOUNT_TABLE: &'static [(&str, MountKind)] = &[
+  ("/", MountKind::Local(STORAGE_ROOT)),
+];
 
 // TODO: TEMPORARY
 pub const ASYNC_ROUTING_TABLE: &'static [&str] = &[
@@ -1,5 +1,140 @@
+use std::{collections::HashMap, time::SystemTime};
+
+use time::{format_description::well_known::Rfc3339, macros::format_description, Date, OffsetDateTime, PrimitiveDateTime};
+
 use crate::config::constants::{HOST_DEFAULT_PORT, HOST_IP_ADDRESS};
 
 pub fn construct_app_url() -> String {
     format!("{}:{}", HOST_IP_ADDRESS, HOST_DEFAULT_PORT)
 }
+
+// PERCENT-DECODING ONLY, A '+' IN A PATH IS A LITERAL PLUS
+pub fn decode_uri_component(value: &str) -> String {
+  let bytes = value.as_bytes();
+  let mut decoded = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+  while i < bytes.len() {
+    match bytes[i] {
+      b'%' if i + 2 < bytes.len() => {
+        let hex = (char::from(bytes[i + 1]).to_digit(16), char::from(bytes[i + 2]).to_digit(16));
+        match hex {
+          (Some(high), Some(low)) => { decoded.push((high * 16 + low) as u8); i += 2; },
+          _ => decoded.push(b'%'),
+        }
+      },
+      b => decoded.push(b),
+    }
+    i += 1;
+  }
+
+  String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// FORM ENCODING, A '+' IN A QUERY STRING STANDS FOR A SPACE
+pub fn decode_query_component(value: &str) -> String {
+  decode_uri_component(&value.replace('+', " "))
+}
+
+// STANDARD ALPHABET WITH OPTIONAL PADDING, NONE ON ANY OTHER CHARACTER
+pub fn decode_base64(value: &str) -> Option<Vec<u8>> {
+  let mut decoded = Vec::with_capacity(value.len() * 3 / 4);
+  let (mut buffer, mut bits) = (0u32, 0);
+  for c in value.trim_end_matches('=').bytes() {
+    let digit = match c {
+      b'A'..=b'Z' => c - b'A',
+      b'a'..=b'z' => c - b'a' + 26,
+      b'0'..=b'9' => c - b'0' + 52,
+      b'+'        => 62,
+      b'/'        => 63,
+      _           => return None,
+    };
+    buffer = (buffer << 6) | digit as u32;
+    bits += 6;
+    if bits >= 8 {
+      bits -= 8;
+      decoded.push((buffer >> bits) as u8);
+      buffer &= (1 << bits) - 1;
+    }
+  }
+  Some(decoded)
+}
+
+pub fn parse_query(query: &str) -> HashMap<String, String> {
+  parse_query_pairs(query).into_iter().collect()
+}
+
+// KEEPS REPEATED KEYS, `?path=/a&path=/b` YIELDS BOTH PAIRS IN ORDER
+pub fn parse_query_pairs(query: &str) -> Vec<(String, String)> {
+  query
+    .split('&')
+    .filter(|pair| !pair.is_empty())
+    .map(|pair| match pair.split_once('=') {
+      Some((k, v)) => (decode_query_component(k), decode_query_component(v)),
+      None => (decode_query_component(pair), String::new()),
+    })
+    .collect()
+}
+
+pub fn escape_json(value: &str) -> String {
+  let mut escaped = String::with_capacity(value.len());
+  for c in value.chars() {
+    match c {
+      '"'                   => escaped.push_str("\\\""),
+      '\\'                  => escaped.push_str("\\\\"),
+      '\n'                  => escaped.push_str("\\n"),
+      '\r'                  => escaped.push_str("\\r"),
+      '\t'                  => escaped.push_str("\\t"),
+      c if c < ' '          => escaped.push_str(format!("\\u{:04x}", c as u32).as_str()),
+      c                     => escaped.push(c),
+    }
+  }
+
+  escaped
+}
+
+pub fn format_http_date(dt: SystemTime) -> String {
+  let format = format_description!("[weekday repr:short], [day] [month repr:short] [year] [hour]:[minute]:[second] GMT");
+  let dt: OffsetDateTime = dt.into();
+  dt.format(&format).unwrap_or_default()
+}
+
+pub fn parse_http_date(value: &str) -> Option<SystemTime> {
+  let format = format_description!("[weekday repr:short], [day] [month repr:short] [year] [hour]:[minute]:[second] GMT");
+  PrimitiveDateTime::parse(value.trim(), &format).ok().map(|dt| dt.assume_utc().into())
+}
+
+// ACCEPTS UNIX SECONDS, RFC 3339 OR A PLAIN `YYYY-MM-DD` DATE AT MIDNIGHT UTC
+pub fn parse_timestamp(value: &str) -> Option<SystemTime> {
+  let value = value.trim();
+  if let Ok(seconds) = value.parse::<u64>() {
+    return Some(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(seconds));
+  }
+
+  if let Ok(dt) = OffsetDateTime::parse(value, &Rfc3339) {
+    return Some(dt.into());
+  }
+
+  Date::parse(value, &format_description!("[year]-[month]-[day]"))
+    .ok()
+    .map(|date| date.midnight().assume_utc().into())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{decode_base64, decode_uri_component, parse_query_pairs};
+
+  #[test]
+  fn decode_path_and_query_test() {
+    assert_eq!(decode_uri_component("/c++/a%20b+c.txt"), "/c++/a b+c.txt");
+    assert_eq!(decode_uri_component("/100%"), "/100%");
+    assert_eq!(parse_query_pairs("path=/a+b%2Bc&flag"), vec![(String::from("path"), String::from("/a b+c")), (String::from("flag"), String::new())]);
+  }
+
+  #[test]
+  fn decode_base64_test() {
+    assert_eq!(decode_base64("YWxpY2U6c2VjcmV0").unwrap(), b"alice:secret");
+    assert_eq!(decode_base64("YQ==").unwrap(), b"a");
+    assert_eq!(decode_base64("YWI").unwrap(), b"ab");
+    assert!(decode_base64("YW*=").is_none());
+  }
+}
@@ -1,6 +1,6 @@
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum HttpMethod {
-  DELETE, GET, NONE, POST, UPDATE,
+  DELETE, GET, NONE, OPTIONS, POST, UPDATE,
 }
 
 impl HttpMethod {
@@ -10,6 +10,7 @@ impl HttpMethod {
       "POST"    => HttpMethod::POST,
       "UPDATE"  => HttpMethod::UPDATE,
       "DELETE"  => HttpMethod::DELETE,
+      "OPTIONS" => HttpMethod::OPTIONS,
       _         => HttpMethod::NONE,
     }
   }
@@ -22,6 +23,7 @@ impl HttpMethod {
       HttpMethod::POST     => String::from("POST"),
       HttpMethod::UPDATE   => String::from("UPDATE"),
       HttpMethod::DELETE   => String::from("DELETE"),
+      HttpMethod::OPTIONS  => String::from("OPTIONS"),
       HttpMethod::NONE     => String::from("NONE"),
     }
   }
@@ -31,4 +33,32 @@ impl std::fmt::Display for HttpMethod {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
       f.write_fmt(format_args!("HttpRequest-Method {{ {} }}", self.as_string()))
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContentEncoding {
+  BROTLI, DEFLATE, GZIP, IDENTITY,
+}
+
+impl ContentEncoding {
+  pub fn from(token: &str) -> Option<ContentEncoding> {
+    match token.to_ascii_lowercase().as_str() {
+      "br"                  => Some(ContentEncoding::BROTLI),
+      "deflate"             => Some(ContentEncoding::DEFLATE),
+      "gzip" | "x-gzip"     => Some(ContentEncoding::GZIP),
+      "identity"            => Some(ContentEncoding::IDENTITY),
+      _                     => None,
+    }
+  }
+}
+
+impl ContentEncoding {
+  pub fn as_string(&self) -> String {
+    match self {
+      ContentEncoding::BROTLI     => String::from("br"),
+      ContentEncoding::DEFLATE    => String::from("deflate"),
+      ContentEncoding::GZIP       => String::from("gzip"),
+      ContentEncoding::IDENTITY   => String::from("identity"),
+    }
+  }
+}
@@ -0,0 +1,102 @@
+use std::{fmt::Display, io};
+
+use json_builder::{Json, JsonBuilder};
+
+use crate::config::{constants::THREAD_POOL_RETRY_AFTER, utility::escape_json};
+use crate::parser::http_response::HttpResponse;
+
+#[derive(Debug)]
+pub enum AppError {
+  BadRequest(String),
+  Forbidden(String),
+  NotFound(String),
+  MethodNotAllowed(String),
+  Conflict(String),
+  PreconditionFailed(String),
+  PayloadTooLarge(String),
+  TooManyRequests(String),
+  Internal(String),
+  ServiceUnavailable(String),
+  Io(io::Error),
+}
+
+impl AppError {
+  pub fn status(&self) -> (&'static str, &'static str) {
+    match self {
+      AppError::BadRequest(_)             => ("400", "Bad Request"),
+      AppError::Forbidden(_)              => ("403", "Forbidden"),
+      AppError::NotFound(_)               => ("404", "Not Found"),
+      AppError::MethodNotAllowed(_)       => ("405", "Method Not Allowed"),
+      AppError::Conflict(_)               => ("409", "Conflict"),
+      AppError::PreconditionFailed(_)     => ("412", "Precondition Failed"),
+      AppError::PayloadTooLarge(_)        => ("413", "Payload Too Large"),
+      AppError::TooManyRequests(_)        => ("429", "Too Many Requests"),
+      AppError::Internal(_)               => ("500", "Internal Server Error"),
+      AppError::ServiceUnavailable(_)     => ("503", "Service Unavailable"),
+      AppError::Io(e) => match e.kind() {
+        io::ErrorKind::NotFound           => ("404", "Not Found"),
+        io::ErrorKind::PermissionDenied   => ("403", "Forbidden"),
+        io::ErrorKind::AlreadyExists      => ("409", "Conflict"),
+        io::ErrorKind::NotADirectory      => ("409", "Conflict"),
+        io::ErrorKind::IsADirectory       => ("409", "Conflict"),
+        _                                 => ("500", "Internal Server Error"),
+      },
+    }
+  }
+
+  pub fn code(&self) -> &'static str {
+    match self.status().0 {
+      "400" => "BAD_REQUEST",
+      "403" => "FORBIDDEN",
+      "404" => "NOT_FOUND",
+      "405" => "METHOD_NOT_ALLOWED",
+      "409" => "CONFLICT",
+      "412" => "PRECONDITION_FAILED",
+      "413" => "PAYLOAD_TOO_LARGE",
+      "429" => "TOO_MANY_REQUESTS",
+      "503" => "SERVICE_UNAVAILABLE",
+      _     => "INTERNAL_ERROR",
+    }
+  }
+
+  pub fn message(&self) -> String {
+    match self {
+      // I/O DETAILS CAN LEAK SERVER PATHS, ONLY THE KIND IS REPORTED TO THE CLIENT
+      AppError::Io(e) => format!("I/O error: {}", e.kind()),
+      AppError::BadRequest(m) | AppError::Forbidden(m) | AppError::NotFound(m) | AppError::MethodNotAllowed(m)
+        | AppError::Conflict(m) | AppError::PreconditionFailed(m) | AppError::PayloadTooLarge(m) | AppError::TooManyRequests(m)
+        | AppError::Internal(m) | AppError::ServiceUnavailable(m) => m.clone(),
+    }
+  }
+
+  pub fn into_response(self, request_id: &str) -> HttpResponse {
+    let (status, message) = self.status();
+    let mut json_object = Json::object();
+    json_object.insert("status", status.parse::<u16>().unwrap_or(500));
+    json_object.insert("code", self.code());
+    json_object.insert("message", escape_json(&self.message()));
+    json_object.insert("request_id", escape_json(request_id));
+
+    let mut http_response = HttpResponse::new(status, message, Json::build(json_object));
+    if let AppError::ServiceUnavailable(_) = self {
+      http_response.header("Retry-After", THREAD_POOL_RETRY_AFTER.to_string());
+    }
+
+    http_response
+  }
+}
+
+impl From<io::Error> for AppError {
+  fn from(e: io::Error) -> Self {
+    AppError::Io(e)
+  }
+}
+
+impl Display for AppError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      AppError::Io(e) => f.write_fmt(format_args!("AppError {{ {}: {} }}", self.code(), e)),
+      _ => f.write_fmt(format_args!("AppError {{ {}: {} }}", self.code(), self.message())),
+    }
+  }
+}
@@ -1 +1,2 @@
-pub mod app_enums;
\ No newline at end of file
+pub mod app_enums;
+pub mod app_error;
\ No newline at end of file
@@ -1,24 +1,26 @@
-use std::{io::{BufRead, BufReader, Write}, net::{TcpListener, TcpStream}};
+use std::{io::{BufRead, BufReader, Read}, net::{TcpListener, TcpStream}, sync::Arc};
 
-use crate::{config::{constants::{ASYNC_ROUTING_TABLE, TOTAL_ACTIVE_THREADS}, utility::construct_app_url}, router::router_handler::RouterHandler};
+use crate::{config::{constants::{ASYNC_ROUTING_TABLE, MAX_REQUEST_BODY_SIZE}, utility::construct_app_url}, router::router_handler::RouterHandler};
+use crate::enums::app_error::AppError;
+use crate::router::middleware::RequestId;
 use logger_main::Logger;
 
-use crate::library::tp::ThreadPool;
-use crate::parser::http_request::HttpRequest;
+use crate::library::{access_log::{AccessLog, AccessRecord}, metrics::{CountingWriter, Metrics}, tp::{PoolConfig, ThreadPool}};
+use crate::parser::{http_request::HttpRequest, http_response::HttpResponse};
 
 pub struct TcpHandler {
   pub url: String,
   pub pool: ThreadPool,
   pub listener: TcpListener,
   
-  router_handler: RouterHandler
+  router_handler: Arc<RouterHandler>
 }
 
 impl TcpHandler {
   pub fn new() -> Self {
     TcpHandler {
       url: construct_app_url(),
-      pool: ThreadPool::new(TOTAL_ACTIVE_THREADS),
+      pool: ThreadPool::new(PoolConfig::default()),
       listener: match TcpListener::bind(construct_app_url()) {
         Ok(tcpl) => tcpl,
         Err(e) => {
@@ -27,7 +29,7 @@ impl TcpHandler {
         },
       },
 
-      router_handler: RouterHandler::new(),
+      router_handler: Arc::new(RouterHandler::new()),
     }
   } 
 }
@@ -38,10 +40,17 @@ impl TcpHandler {
 
     for res_stream in self.listener.incoming() {
       match res_stream {
-        Err(e) => Logger::error("Failed to get stream from listener", Some(Box::new(e))),
+        Err(e) => Logger::error(format!("Failed to get stream from listener: {}", e), None),
         Ok(tcp_stream) => {
           // TODO: BETTER TO MAKE IT ASYNC OR MANAGE BY SEPARATE THREAD
-          let http_request = TcpHandler::parse_tcp_stream(&tcp_stream);
+          let http_request = match TcpHandler::parse_tcp_stream(&tcp_stream) {
+            Ok(http_request) => http_request,
+            Err(e) => {
+              Logger::warn(format!("Rejected malformed request: {}", e));
+              self.reject(e, &tcp_stream, AccessRecord::new(tcp_stream.peer_addr().ok().map(|addr| addr.ip())));
+              continue;
+            },
+          };
 
           if let Some(&path) = ASYNC_ROUTING_TABLE.iter().find(|p| **p == http_request.path) {
             // TODO: ASYNC ROUTE UNDER CONSTRUCTION
@@ -51,7 +60,7 @@ impl TcpHandler {
           } 
 
           Logger::debug(format!("Thread handling Http Request, Path: {}", &http_request.path));
-          self.execute(&http_request, &tcp_stream, &http_request.path);
+          self.execute(http_request, &tcp_stream);
         },
       }
     }
@@ -59,36 +68,102 @@ impl TcpHandler {
 }
 
 impl TcpHandler {
-  fn execute(&self, http_request: &HttpRequest, tcp_stream: &TcpStream, path: &str) {
-    let mut stream = tcp_stream.try_clone().expect("Failed to clone mutable TCP stream!");
-    let http_response = self.router_handler.exec(&http_request.method, path).clone();
-    self.pool.execute(move || TcpHandler::reply_to_client(http_response(), &mut stream));
+  fn execute(&self, http_request: HttpRequest, tcp_stream: &TcpStream) {
+    let Some(mut stream) = TcpHandler::clone_stream(tcp_stream) else {
+      return;
+    };
+
+    let router_handler = Arc::clone(&self.router_handler);
+    let priority = router_handler.priority(&http_request);
+    let record = AccessRecord::from(&http_request);
+    let job_record = record.clone();
+    let connection = Metrics::global().connection();
+    Metrics::global().observe_pool(self.pool.stats());
+    let result = self.pool.submit(priority, move || {
+      let _connection = connection;
+      let http_response = router_handler.dispatch(http_request);
+      TcpHandler::reply_to_client(http_response, &mut stream, job_record)
+    });
+
+    // THE QUEUE IS FULL, ANSWER RIGHT AWAY INSTEAD OF LETTING THE CLIENT WAIT
+    if let Err(e) = result {
+      self.reject(e, tcp_stream, record);
+    }
+  }
+
+  fn reject(&self, error: AppError, tcp_stream: &TcpStream, record: AccessRecord) {
+    let Some(mut stream) = TcpHandler::clone_stream(tcp_stream) else {
+      return;
+    };
+
+    let request_id = RequestId::generate();
+    let mut http_response = error.into_response(&request_id);
+    http_response.header("X-Request-Id", request_id);
+    TcpHandler::reply_to_client(http_response, &mut stream, record);
+  }
+
+  fn clone_stream(tcp_stream: &TcpStream) -> Option<TcpStream> {
+    match tcp_stream.try_clone() {
+      Ok(stream) => Some(stream),
+      Err(e) => {
+        Logger::error(format!("Failed to clone mutable TCP stream: {}", e), None);
+        None
+      },
+    }
   }
 }
 
 trait TcpHandlerTrait {
-  fn parse_tcp_stream(stream: &TcpStream) -> HttpRequest;
-  fn reply_to_client(http_response: String, stream: &mut TcpStream);
+  fn parse_tcp_stream(stream: &TcpStream) -> Result<HttpRequest, AppError>;
+  fn reply_to_client(http_response: HttpResponse, stream: &mut TcpStream, record: AccessRecord);
 }
 
 impl TcpHandlerTrait for TcpHandler {
-  fn parse_tcp_stream(stream: &TcpStream) -> HttpRequest {
+  fn parse_tcp_stream(stream: &TcpStream) -> Result<HttpRequest, AppError> {
     Logger::debug("Creating HTTP request from stream");
-    let buf_reader = BufReader::new(stream);
+    let mut buf_reader = BufReader::new(stream);
     let request: Vec<String> = buf_reader
+        .by_ref()
         .lines()
-        .map(|result| result.unwrap())
+        .map_while(Result::ok)
         .take_while(|line| !line.is_empty())
         .collect();
 
-    HttpRequest::construct(request)
+    if request.first().is_none_or(|line| line.split_whitespace().count() != 3) {
+      return Err(AppError::BadRequest(String::from("Malformed request line")));
+    }
+
+    let mut http_request = HttpRequest::construct(request);
+    http_request.remote_addr = stream.peer_addr().ok().map(|addr| addr.ip());
+    let content_length = match http_request.header("content-length") {
+      Some(value) => value.trim().parse::<usize>().map_err(|_| AppError::BadRequest(String::from("Invalid Content-Length")))?,
+      None => 0,
+    };
+
+    if content_length > MAX_REQUEST_BODY_SIZE {
+      return Err(AppError::PayloadTooLarge(format!("Request body exceeds {} bytes", MAX_REQUEST_BODY_SIZE)));
+    }
+
+    if content_length > 0 {
+      http_request.body = vec![0; content_length];
+      buf_reader
+        .read_exact(&mut http_request.body)
+        .map_err(|_| AppError::BadRequest(String::from("Request body is shorter than Content-Length")))?;
+    }
+
+    Ok(http_request)
   }
 
-  fn reply_to_client(http_response: String, stream: &mut TcpStream) {
+  fn reply_to_client(http_response: HttpResponse, stream: &mut TcpStream, record: AccessRecord) {
     Logger::debug("Sending response to client");
-    match stream.write_all(http_response.as_bytes()) {
-        Err(e) => Logger::error("Failed to write response to client", Some(Box::new(e))),
-        _ => {},
+    let record = record.finish(&http_response);
+    let head_length = http_response.construct().len() as u64;
+    let mut writer = CountingWriter::new(stream);
+    if let Err(e) = http_response.write_to(&mut writer) {
+      Logger::error(format!("Failed to write response to client: {}", e), None);
     }
+
+    Metrics::global().observe_bytes_out(writer.written);
+    AccessLog::global().write(&record.sent(writer.written.saturating_sub(head_length)));
   }
 }
@@ -0,0 +1,240 @@
+use std::{fs::{self, OpenOptions}, io::{self, Write}, net::IpAddr, path::Path, sync::{LazyLock, Mutex, PoisonError}, time::{Duration, Instant}};
+
+use json_builder::{Json, JsonBuilder};
+use logger_main::Logger;
+use time::{macros::format_description, OffsetDateTime};
+
+use crate::config::constants::{ACCESS_LOG_FORMAT, ACCESS_LOG_PATH};
+use crate::config::utility::escape_json;
+use crate::parser::{http_request::HttpRequest, http_response::HttpResponse};
+
+static ACCESS_LOG: LazyLock<AccessLog> = LazyLock::new(|| AccessLog::open(ACCESS_LOG_PATH, ACCESS_LOG_FORMAT));
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessLogFormat {
+  Combined,
+  JsonLines,
+}
+
+#[derive(Debug, Clone)]
+pub struct AccessRecord {
+  pub remote_addr: Option<IpAddr>,
+  pub user: Option<String>,
+  pub request_line: Option<String>,
+  pub method: String,
+  pub target: String,
+  pub protocol: String,
+  pub status: String,
+  pub bytes: u64,
+  pub referer: Option<String>,
+  pub user_agent: Option<String>,
+  pub request_id: Option<String>,
+  pub time: OffsetDateTime,
+  pub duration: Duration,
+  started: Instant,
+}
+
+pub struct AccessLog {
+  sink: Mutex<Box<dyn Write + Send>>,
+  format: AccessLogFormat,
+}
+
+impl AccessRecord {
+  pub fn new(remote_addr: Option<IpAddr>) -> Self {
+    AccessRecord {
+      remote_addr,
+      user: None,
+      request_line: None,
+      method: String::from("-"),
+      target: String::from("-"),
+      protocol: String::from("-"),
+      status: String::from("-"),
+      bytes: 0,
+      referer: None,
+      user_agent: None,
+      request_id: None,
+      time: OffsetDateTime::now_utc(),
+      duration: Duration::ZERO,
+      started: Instant::now(),
+    }
+  }
+
+  pub fn from(http_request: &HttpRequest) -> Self {
+    let non_empty = |value: &String| (!value.is_empty()).then(|| value.clone());
+    AccessRecord {
+      request_line: non_empty(&http_request.request_line),
+      method: non_empty(&http_request.method_token).unwrap_or_else(|| String::from("-")),
+      user: http_request.username(),
+      target: http_request.target.clone(),
+      protocol: http_request.http_version.clone(),
+      referer: http_request.header("referer").and_then(non_empty),
+      user_agent: non_empty(&http_request.user_agent),
+      ..AccessRecord::new(http_request.remote_addr)
+    }
+  }
+
+  pub fn finish(mut self, http_response: &HttpResponse) -> Self {
+    self.status = http_response.status.clone();
+    self.request_id = http_response.get_header("X-Request-Id").cloned();
+    self
+  }
+
+  pub fn sent(mut self, bytes: u64) -> Self {
+    self.bytes = bytes;
+    self.duration = self.started.elapsed();
+    self
+  }
+
+  // %h %l %u %t "%r" %>s %b "%{Referer}i" "%{User-Agent}i" %D
+  pub fn combined(&self) -> String {
+    let format = format_description!("[day]/[month repr:short]/[year]:[hour]:[minute]:[second] [offset_hour sign:mandatory][offset_minute]");
+    format!(
+      "{} - {} [{}] \"{}\" {} {} \"{}\" \"{}\" {}",
+      self.remote_addr.map(|addr| addr.to_string()).unwrap_or_else(|| String::from("-")),
+      self.user.as_deref().map(AccessRecord::escape).unwrap_or_else(|| String::from("-")),
+      self.time.format(&format).unwrap_or_default(),
+      self.request_line.as_deref().map(AccessRecord::escape).unwrap_or_else(|| String::from("-")),
+      self.status,
+      if self.bytes == 0 { String::from("-") } else { self.bytes.to_string() },
+      self.referer.as_deref().map(AccessRecord::escape).unwrap_or_else(|| String::from("-")),
+      self.user_agent.as_deref().map(AccessRecord::escape).unwrap_or_else(|| String::from("-")),
+      self.duration.as_micros(),
+    )
+  }
+
+  pub fn json(&self) -> String {
+    let format = format_description!("[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:3]Z");
+    let text = |value: &Option<String>| escape_json(value.as_deref().unwrap_or("-"));
+
+    let mut json_object = Json::object();
+    json_object.insert("time", self.time.format(&format).unwrap_or_default());
+    json_object.insert("remote_addr", self.remote_addr.map(|addr| addr.to_string()).unwrap_or_else(|| String::from("-")));
+    json_object.insert("user", text(&self.user));
+    json_object.insert("method", escape_json(&self.method));
+    json_object.insert("path", escape_json(&self.target));
+    json_object.insert("protocol", escape_json(&self.protocol));
+    json_object.insert("status", self.status.parse::<u16>().unwrap_or(0));
+    json_object.insert("bytes", self.bytes);
+    json_object.insert("referer", text(&self.referer));
+    json_object.insert("user_agent", text(&self.user_agent));
+    json_object.insert("request_id", text(&self.request_id));
+    json_object.insert("duration_ms", self.duration.as_secs_f64() * 1000.0);
+    Json::build(json_object)
+  }
+
+  // APACHE STYLE, QUOTES AND BACKSLASHES ARE ESCAPED AND CONTROL BYTES BECOME \xHH
+  fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+      match c {
+        '"' | '\\' => {
+          escaped.push('\\');
+          escaped.push(c);
+        },
+        c if c.is_control() => escaped.push_str(&format!("\\x{:02x}", c as u32)),
+        c => escaped.push(c),
+      }
+    }
+    escaped
+  }
+}
+
+impl AccessLog {
+  pub fn global() -> &'static AccessLog {
+    &ACCESS_LOG
+  }
+
+  // "-" WRITES TO STDOUT, ANY OTHER VALUE IS A FILE THAT IS APPENDED TO
+  pub fn open(path: &str, format: AccessLogFormat) -> Self {
+    let sink: Box<dyn Write + Send> = match path {
+      "-" => Box::new(io::stdout()),
+      _ => match AccessLog::open_file(path) {
+        Ok(file) => Box::new(file),
+        Err(e) => {
+          Logger::warn(format!("Access Log - Failed to open {}, falling back to stdout: {}", path, e));
+          Box::new(io::stdout())
+        },
+      },
+    };
+
+    AccessLog { sink: Mutex::new(sink), format }
+  }
+
+  pub fn write(&self, record: &AccessRecord) {
+    let line = match self.format {
+      AccessLogFormat::Combined => record.combined(),
+      AccessLogFormat::JsonLines => record.json(),
+    };
+
+    let mut sink = self.sink.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Err(e) = writeln!(sink, "{}", line).and_then(|_| sink.flush()) {
+      Logger::warn(format!("Access Log - Failed to write entry: {}", e));
+    }
+  }
+
+  fn open_file(path: &str) -> io::Result<fs::File> {
+    if let Some(parent) = Path::new(path).parent().filter(|p| !p.as_os_str().is_empty()) {
+      fs::create_dir_all(parent)?;
+    }
+
+    OpenOptions::new().create(true).append(true).open(path)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use time::macros::datetime;
+
+  use crate::parser::http_request::HttpRequest;
+
+  use super::AccessRecord;
+
+  #[test]
+  fn access_log_combined_test() {
+    let mut http_request = HttpRequest::construct(vec![
+      String::from("GET /files?path=%2F HTTP/1.1"),
+      String::from("User-Agent: curl/8.0 \"quoted\""),
+      String::from("Referer: http://localhost:7001/"),
+    ]);
+    http_request.remote_addr = "10.0.0.1".parse().ok();
+
+    let mut record = AccessRecord::from(&http_request);
+    record.status = String::from("200");
+    record.bytes = 512;
+    record.time = datetime!(2000-10-10 13:55:36 UTC);
+    record.duration = Duration::from_micros(1500);
+
+    assert_eq!(
+      record.combined(),
+      "10.0.0.1 - - [10/Oct/2000:13:55:36 +0000] \"GET /files?path=%2F HTTP/1.1\" 200 512 \"http://localhost:7001/\" \"curl/8.0 \\\"quoted\\\"\" 1500"
+    );
+    assert!(record.json().contains("\"duration_ms\":1.5"));
+  }
+
+  #[test]
+  fn access_log_user_test() {
+    let http_request = HttpRequest::construct(vec![
+      String::from("GET /files HTTP/1.1"),
+      String::from("Authorization: Basic YWxpY2U6c2VjcmV0"),
+    ]);
+    let record = AccessRecord::from(&http_request);
+    assert_eq!(record.user.as_deref(), Some("alice"));
+    assert!(record.combined().starts_with("- - alice ["));
+    assert!(record.json().contains("\"user\":\"alice\""));
+
+    let http_request = HttpRequest::construct(vec![String::from("GET /files HTTP/1.1"), String::from("Authorization: Bearer abc")]);
+    assert!(AccessRecord::from(&http_request).combined().starts_with("- - - ["));
+  }
+
+  #[test]
+  fn access_log_method_test() {
+    for line in ["PUT /files?path=/a.txt HTTP/1.1", "HEAD /files HTTP/1.1", "PATCH /files HTTP/1.1", "BREW /pot HTTP/1.1"] {
+      let record = AccessRecord::from(&HttpRequest::construct(vec![String::from(line)]));
+      assert!(record.combined().contains(&format!("\"{}\"", line)), "{}", record.combined());
+      assert_eq!(record.method, line.split(' ').next().unwrap());
+    }
+  }
+}
@@ -0,0 +1,648 @@
+use std::{io::{self, Read}, path::Path, time::UNIX_EPOCH};
+
+use flate2::{read::DeflateEncoder, Compression, Crc};
+use time::OffsetDateTime;
+
+use crate::config::constants::ARCHIVE_COMPRESSION_LEVEL;
+use crate::library::{compression, mime::Mime, vfs::{EntryKind, Stat, Vfs}};
+
+const ZIP64_LIMIT: u64 = 0xFFFF_FFFF;
+const TAR_BLOCK: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+  Zip,
+  TarGz,
+}
+
+pub struct ArchiveEntry {
+  pub path: String,
+  pub name: String,
+  pub stat: Stat,
+}
+
+// WALKS THE SELECTION LAZILY, DIRECTORIES ARE LISTED ONLY WHEN THE STREAM REACHES THEM
+pub struct ArchiveEntries {
+  vfs: Vfs,
+  pending: Vec<(String, String)>,
+}
+
+// COUNTS AND CHECKSUMS WHAT PASSES THROUGH, THE SIZE ON DISK MAY CHANGE WHILE STREAMING
+struct Checksummed {
+  inner: Box<dyn Read + Send>,
+  crc: Crc,
+  bytes: u64,
+}
+
+enum Payload {
+  Stored(Checksummed),
+  Deflated(DeflateEncoder<Checksummed>),
+}
+
+struct CentralRecord {
+  name: String,
+  method: u16,
+  crc: u32,
+  compressed: u64,
+  size: u64,
+  offset: u64,
+  modified: u32,
+  mode: u32,
+  is_dir: bool,
+}
+
+pub struct ZipStream {
+  entries: ArchiveEntries,
+  current: Option<(Payload, CentralRecord)>,
+  central: Vec<CentralRecord>,
+  buffer: Vec<u8>,
+  offset: usize,
+  written: u64,
+  is_finished: bool,
+}
+
+pub struct TarStream {
+  entries: ArchiveEntries,
+  current: Option<(io::Take<Box<dyn Read + Send>>, u64)>,
+  buffer: Vec<u8>,
+  offset: usize,
+  is_finished: bool,
+}
+
+impl ArchiveFormat {
+  pub fn from(value: &str) -> Option<Self> {
+    match value.to_ascii_lowercase().as_str() {
+      "zip" => Some(ArchiveFormat::Zip),
+      "tar.gz" | "tgz" => Some(ArchiveFormat::TarGz),
+      _ => None,
+    }
+  }
+
+  pub fn content_type(&self) -> &'static str {
+    match self {
+      ArchiveFormat::Zip => "application/zip",
+      ArchiveFormat::TarGz => "application/gzip",
+    }
+  }
+
+  pub fn extension(&self) -> &'static str {
+    match self {
+      ArchiveFormat::Zip => "zip",
+      ArchiveFormat::TarGz => "tar.gz",
+    }
+  }
+
+  pub fn stream(&self, entries: ArchiveEntries) -> Box<dyn Read + Send> {
+    match self {
+      ArchiveFormat::Zip => Box::new(ZipStream::new(entries)),
+      ArchiveFormat::TarGz => Box::new(flate2::read::GzEncoder::new(TarStream::new(entries), Compression::new(ARCHIVE_COMPRESSION_LEVEL))),
+    }
+  }
+}
+
+impl ArchiveEntries {
+  // EACH SELECTION IS A PATH IN THE VFS AND THE NAME IT GETS IN THE ARCHIVE, AN EMPTY NAME PUTS ITS CHILDREN AT THE TOP
+  pub fn new(vfs: Vfs, selection: Vec<(String, String)>) -> Self {
+    ArchiveEntries { vfs, pending: selection.into_iter().rev().collect() }
+  }
+
+  pub fn open(&self, entry: &ArchiveEntry) -> io::Result<Box<dyn Read + Send>> {
+    self.vfs.open_read(&entry.path, 0)
+  }
+}
+
+impl Iterator for ArchiveEntries {
+  type Item = ArchiveEntry;
+
+  fn next(&mut self) -> Option<ArchiveEntry> {
+    loop {
+      let (path, name) = self.pending.pop()?;
+      let Ok(stat) = self.vfs.stat(&path) else {
+        continue;
+      };
+
+      if stat.kind == EntryKind::Directory {
+        // LINKS AND SPECIAL FILES ARE LEFT OUT, FOLLOWING A LINK COULD LEAVE THE STORAGE ROOT
+        let children = self.vfs.list(&path).unwrap_or_default().into_iter().filter(|e| e.stat.kind != EntryKind::Other);
+        for child in children.rev() {
+          let child_name = match name.is_empty() {
+            true => child.name.clone(),
+            false => format!("{}/{}", name, child.name),
+          };
+          self.pending.push((Vfs::join(&path, &child.name), child_name));
+        }
+      }
+
+      if name.is_empty() {
+        continue;
+      }
+
+      return Some(ArchiveEntry { path, name, stat });
+    }
+  }
+}
+
+impl ArchiveEntry {
+  pub fn is_dir(&self) -> bool {
+    self.stat.kind == EntryKind::Directory
+  }
+
+  fn modified(&self) -> u64 {
+    self.stat.modified.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+  }
+
+  fn mode(&self) -> u32 {
+    self.stat.mode
+  }
+}
+
+impl Read for Checksummed {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let read = self.inner.read(buf)?;
+    self.crc.update(&buf[..read]);
+    self.bytes += read as u64;
+    Ok(read)
+  }
+}
+
+impl Payload {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    match self {
+      Payload::Stored(reader) => reader.read(buf),
+      Payload::Deflated(reader) => reader.read(buf),
+    }
+  }
+
+  fn source(&self) -> &Checksummed {
+    match self {
+      Payload::Stored(reader) => reader,
+      Payload::Deflated(reader) => reader.get_ref(),
+    }
+  }
+}
+
+impl ZipStream {
+  pub fn new(entries: ArchiveEntries) -> Self {
+    ZipStream { entries, current: None, central: Vec::new(), buffer: Vec::new(), offset: 0, written: 0, is_finished: false }
+  }
+
+  // FILLS THE BUFFER WITH THE NEXT PIECE OF THE ARCHIVE, EMPTY ONLY ONCE EVERYTHING HAS BEEN SENT
+  fn advance(&mut self) -> io::Result<()> {
+    loop {
+      if let Some((payload, _)) = self.current.as_mut() {
+        let mut chunk = vec![0; 64 * 1024];
+        let read = payload.read(&mut chunk)?;
+        if read > 0 {
+          chunk.truncate(read);
+          self.emit(chunk);
+          return Ok(());
+        }
+
+        let (payload, mut record) = self.current.take().unwrap_or_else(|| unreachable!());
+        let source = payload.source();
+        record.crc = source.crc.sum();
+        record.size = source.bytes;
+        record.compressed = self.written - record.offset - ZipStream::local_header_length(&record);
+        self.data_descriptor(&record);
+        self.central.push(record);
+        return Ok(());
+      }
+
+      if self.is_finished {
+        return Ok(());
+      }
+
+      let Some(entry) = self.entries.next() else {
+        self.central_directory();
+        self.is_finished = true;
+        return Ok(());
+      };
+
+      let is_dir = entry.is_dir();
+      let source = match is_dir {
+        true => None,
+        false => match self.entries.open(&entry) {
+          Ok(source) => Some(source),
+          Err(_) => continue,
+        },
+      };
+
+      let is_compressible = match is_dir {
+        true => false,
+        false => self.entries.open(&entry).is_ok_and(|reader| compression::is_compressible(&Mime::detect_from(Path::new(&entry.name), reader))),
+      };
+      let mut record = CentralRecord {
+        name: match is_dir {
+          true => format!("{}/", entry.name),
+          false => entry.name.clone(),
+        },
+        method: if is_compressible { 8 } else { 0 },
+        crc: 0,
+        compressed: 0,
+        size: entry.stat.size,
+        offset: self.written,
+        modified: entry.modified() as u32,
+        mode: entry.mode() | if is_dir { 0o040000 } else { 0o100000 },
+        is_dir,
+      };
+
+      if is_dir {
+        record.method = 0;
+        record.size = 0;
+        self.local_header(&record);
+        self.central.push(record);
+        return Ok(());
+      }
+
+      let source = Checksummed { inner: source.unwrap_or_else(|| Box::new(io::empty())), crc: Crc::new(), bytes: 0 };
+      let payload = match is_compressible {
+        true => Payload::Deflated(DeflateEncoder::new(source, Compression::new(ARCHIVE_COMPRESSION_LEVEL))),
+        false => Payload::Stored(source),
+      };
+      self.local_header(&record);
+      self.current = Some((payload, record));
+      return Ok(());
+    }
+  }
+
+  // THE SIZE ON DISK DECIDES UP FRONT, A DATA DESCRIPTOR CANNOT SWITCH TO ZIP64 AFTER THE FACT
+  fn is_zip64(record: &CentralRecord) -> bool {
+    record.size >= ZIP64_LIMIT - ZIP64_LIMIT / 16
+  }
+
+  fn local_header_length(record: &CentralRecord) -> u64 {
+    30 + record.name.len() as u64 + 9 + if ZipStream::is_zip64(record) { 20 } else { 0 }
+  }
+
+  fn local_header(&mut self, record: &CentralRecord) {
+    let is_zip64 = ZipStream::is_zip64(record);
+    let (time, date) = ZipStream::dos_time(record.modified);
+    let mut header = Vec::with_capacity(ZipStream::local_header_length(record) as usize);
+    header.extend(0x04034b50u32.to_le_bytes());
+    header.extend((if is_zip64 { 45u16 } else { 20u16 }).to_le_bytes());
+    header.extend(ZipStream::flags(record).to_le_bytes());
+    header.extend(record.method.to_le_bytes());
+    header.extend(time.to_le_bytes());
+    header.extend(date.to_le_bytes());
+    header.extend(0u32.to_le_bytes());
+    header.extend((if is_zip64 { 0xFFFF_FFFFu32 } else { 0 }).to_le_bytes());
+    header.extend((if is_zip64 { 0xFFFF_FFFFu32 } else { 0 }).to_le_bytes());
+    header.extend((record.name.len() as u16).to_le_bytes());
+    header.extend((if is_zip64 { 29u16 } else { 9u16 }).to_le_bytes());
+    header.extend(record.name.as_bytes());
+    ZipStream::timestamp_field(&mut header, record.modified);
+    if is_zip64 {
+      header.extend(0x0001u16.to_le_bytes());
+      header.extend(16u16.to_le_bytes());
+      header.extend([0; 16]);
+    }
+    self.emit(header);
+  }
+
+  fn data_descriptor(&mut self, record: &CentralRecord) {
+    let mut descriptor = Vec::with_capacity(24);
+    descriptor.extend(0x08074b50u32.to_le_bytes());
+    descriptor.extend(record.crc.to_le_bytes());
+    match ZipStream::is_zip64(record) {
+      true => {
+        descriptor.extend(record.compressed.to_le_bytes());
+        descriptor.extend(record.size.to_le_bytes());
+      },
+      false => {
+        descriptor.extend((record.compressed as u32).to_le_bytes());
+        descriptor.extend((record.size as u32).to_le_bytes());
+      },
+    }
+    self.emit(descriptor);
+  }
+
+  fn central_directory(&mut self) {
+    let start = self.written;
+    let records = std::mem::take(&mut self.central);
+    for record in records.iter() {
+      let needs_zip64 = record.size >= ZIP64_LIMIT || record.compressed >= ZIP64_LIMIT || record.offset >= ZIP64_LIMIT;
+      let clamp = |value: u64| if needs_zip64 { 0xFFFF_FFFFu32 } else { value as u32 };
+      let (time, date) = ZipStream::dos_time(record.modified);
+
+      let mut header = Vec::new();
+      header.extend(0x02014b50u32.to_le_bytes());
+      header.extend((3u16 << 8 | 45).to_le_bytes());
+      header.extend((if needs_zip64 || ZipStream::is_zip64(record) { 45u16 } else { 20u16 }).to_le_bytes());
+      header.extend(ZipStream::flags(record).to_le_bytes());
+      header.extend(record.method.to_le_bytes());
+      header.extend(time.to_le_bytes());
+      header.extend(date.to_le_bytes());
+      header.extend(record.crc.to_le_bytes());
+      header.extend(clamp(record.compressed).to_le_bytes());
+      header.extend(clamp(record.size).to_le_bytes());
+      header.extend((record.name.len() as u16).to_le_bytes());
+      header.extend((if needs_zip64 { 37u16 } else { 9u16 }).to_le_bytes());
+      header.extend(0u16.to_le_bytes());
+      header.extend(0u16.to_le_bytes());
+      header.extend(0u16.to_le_bytes());
+      header.extend((record.mode << 16 | if record.is_dir { 0x10 } else { 0 }).to_le_bytes());
+      header.extend(clamp(record.offset).to_le_bytes());
+      header.extend(record.name.as_bytes());
+      ZipStream::timestamp_field(&mut header, record.modified);
+      if needs_zip64 {
+        header.extend(0x0001u16.to_le_bytes());
+        header.extend(24u16.to_le_bytes());
+        header.extend(record.size.to_le_bytes());
+        header.extend(record.compressed.to_le_bytes());
+        header.extend(record.offset.to_le_bytes());
+      }
+      self.emit(header);
+    }
+
+    let count = records.len() as u64;
+    let size = self.written - start;
+    let end = self.written;
+    let needs_zip64 = count >= 0xFFFF || size >= ZIP64_LIMIT || start >= ZIP64_LIMIT;
+    let mut trailer = Vec::new();
+    if needs_zip64 {
+      trailer.extend(0x06064b50u32.to_le_bytes());
+      trailer.extend(44u64.to_le_bytes());
+      trailer.extend((3u16 << 8 | 45).to_le_bytes());
+      trailer.extend(45u16.to_le_bytes());
+      trailer.extend(0u32.to_le_bytes());
+      trailer.extend(0u32.to_le_bytes());
+      trailer.extend(count.to_le_bytes());
+      trailer.extend(count.to_le_bytes());
+      trailer.extend(size.to_le_bytes());
+      trailer.extend(start.to_le_bytes());
+
+      trailer.extend(0x07064b50u32.to_le_bytes());
+      trailer.extend(0u32.to_le_bytes());
+      trailer.extend(end.to_le_bytes());
+      trailer.extend(1u32.to_le_bytes());
+    }
+
+    trailer.extend(0x06054b50u32.to_le_bytes());
+    trailer.extend(0u16.to_le_bytes());
+    trailer.extend(0u16.to_le_bytes());
+    trailer.extend((count.min(0xFFFF) as u16).to_le_bytes());
+    trailer.extend((count.min(0xFFFF) as u16).to_le_bytes());
+    trailer.extend((size.min(ZIP64_LIMIT) as u32).to_le_bytes());
+    trailer.extend((start.min(ZIP64_LIMIT) as u32).to_le_bytes());
+    trailer.extend(0u16.to_le_bytes());
+    self.emit(trailer);
+  }
+
+  // BIT 3: SIZES FOLLOW THE DATA, BIT 11: NAMES ARE UTF-8
+  fn flags(record: &CentralRecord) -> u16 {
+    match record.is_dir {
+      true => 1 << 11,
+      false => 1 << 3 | 1 << 11,
+    }
+  }
+
+  // EXTENDED TIMESTAMP, DOS TIME ONLY HAS TWO SECOND PRECISION AND NO TIME ZONE
+  fn timestamp_field(header: &mut Vec<u8>, modified: u32) {
+    header.extend(0x5455u16.to_le_bytes());
+    header.extend(5u16.to_le_bytes());
+    header.push(1);
+    header.extend(modified.to_le_bytes());
+  }
+
+  fn dos_time(modified: u32) -> (u16, u16) {
+    let Ok(time) = OffsetDateTime::from_unix_timestamp(modified as i64) else {
+      return (0, 0x21);
+    };
+
+    if time.year() < 1980 {
+      return (0, 0x21);
+    }
+
+    let dos_time = (time.hour() as u16) << 11 | (time.minute() as u16) << 5 | ((time.second() as u16) / 2);
+    let dos_date = ((time.year() - 1980) as u16) << 9 | (time.month() as u16) << 5 | time.day() as u16;
+    (dos_time, dos_date)
+  }
+
+  fn emit(&mut self, bytes: Vec<u8>) {
+    if self.offset >= self.buffer.len() {
+      self.buffer.clear();
+      self.offset = 0;
+    }
+
+    self.written += bytes.len() as u64;
+    self.buffer.extend(bytes);
+  }
+}
+
+impl Read for ZipStream {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    while self.offset >= self.buffer.len() {
+      if self.is_finished && self.current.is_none() {
+        return Ok(0);
+      }
+      self.advance()?;
+    }
+
+    let count = buf.len().min(self.buffer.len() - self.offset);
+    buf[..count].copy_from_slice(&self.buffer[self.offset..self.offset + count]);
+    self.offset += count;
+    Ok(count)
+  }
+}
+
+impl TarStream {
+  pub fn new(entries: ArchiveEntries) -> Self {
+    TarStream { entries, current: None, buffer: Vec::new(), offset: 0, is_finished: false }
+  }
+
+  fn advance(&mut self) -> io::Result<()> {
+    if let Some((reader, remaining)) = self.current.as_mut() {
+      let mut chunk = vec![0; 64 * 1024];
+      let read = reader.read(&mut chunk)?;
+      *remaining -= read as u64;
+
+      // A FILE THAT SHRANK WHILE STREAMING IS PADDED, THE HEADER ALREADY PROMISED ITS SIZE
+      let read = match (read, *remaining) {
+        (0, 0) => {
+          self.current = None;
+          return Ok(());
+        },
+        (0, left) => {
+          let padding = left.min(chunk.len() as u64) as usize;
+          chunk[..padding].fill(0);
+          *remaining -= padding as u64;
+          padding
+        },
+        (read, _) => read,
+      };
+
+      chunk.truncate(read);
+      self.buffer = chunk;
+      self.offset = 0;
+      return Ok(());
+    }
+
+    let Some(entry) = self.entries.next() else {
+      self.buffer = vec![0; TAR_BLOCK * 2];
+      self.offset = 0;
+      self.is_finished = true;
+      return Ok(());
+    };
+
+    let (kind, size) = match entry.is_dir() {
+      true => (b'5', 0),
+      false => (b'0', entry.stat.size),
+    };
+
+    let source = match kind {
+      b'0' => match self.entries.open(&entry) {
+        Ok(reader) => Some(reader),
+        Err(_) => return Ok(()),
+      },
+      _ => None,
+    };
+
+    let name = match entry.is_dir() {
+      true => format!("{}/", entry.name),
+      false => entry.name.clone(),
+    };
+
+    self.buffer = TarStream::header(&name, kind, size, entry.mode(), entry.modified());
+    self.offset = 0;
+    if let Some(file) = source {
+      let padded = size.div_ceil(TAR_BLOCK as u64) * TAR_BLOCK as u64;
+      let reader: Box<dyn Read + Send> = Box::new(file.take(size).chain(io::repeat(0).take(padded - size)));
+      self.current = Some((reader.take(padded), padded));
+    }
+    Ok(())
+  }
+
+  // USTAR, WITH A PAX RECORD IN FRONT FOR ANYTHING THAT DOES NOT FIT ITS FIELDS
+  fn header(name: &str, kind: u8, size: u64, mode: u32, modified: u64) -> Vec<u8> {
+    let mut records = String::new();
+    let (prefix, short_name) = TarStream::split_name(name).unwrap_or_else(|| {
+      TarStream::pax_record(&mut records, "path", name);
+      ("", "")
+    });
+
+    if size > 0o77777777777 {
+      TarStream::pax_record(&mut records, "size", &size.to_string());
+    }
+
+    let mut out = Vec::new();
+    if !records.is_empty() {
+      out.extend(TarStream::block(b"././@PaxHeader", b'x', records.len() as u64, 0o644, modified, b""));
+      out.extend(records.as_bytes());
+      out.resize(out.len().div_ceil(TAR_BLOCK) * TAR_BLOCK, 0);
+    }
+
+    let short_name = if short_name.is_empty() { &name.as_bytes()[..name.len().min(100)] } else { short_name.as_bytes() };
+    out.extend(TarStream::block(short_name, kind, size, mode, modified, prefix.as_bytes()));
+    out
+  }
+
+  fn block(name: &[u8], kind: u8, size: u64, mode: u32, modified: u64, prefix: &[u8]) -> Vec<u8> {
+    let mut block = vec![0u8; TAR_BLOCK];
+    let mut put = |at: usize, width: usize, value: &[u8]| {
+      let length = value.len().min(width);
+      block[at..at + length].copy_from_slice(&value[..length]);
+    };
+
+    let octal = |value: u64, width: usize| format!("{:0width$o}", value, width = width - 1).into_bytes();
+    put(0, 100, name);
+    put(100, 8, &octal(mode as u64, 8));
+    put(108, 8, &octal(0, 8));
+    put(116, 8, &octal(0, 8));
+    put(124, 12, &octal(size.min(0o77777777777), 12));
+    put(136, 12, &octal(modified.min(0o77777777777), 12));
+    put(148, 8, b"        ");
+    put(156, 1, &[kind]);
+    put(257, 6, b"ustar\0");
+    put(263, 2, b"00");
+    put(345, 155, prefix);
+
+    let checksum: u32 = block.iter().map(|b| *b as u32).sum();
+    block[148..155].copy_from_slice(format!("{:06o}\0", checksum).as_bytes());
+    block
+  }
+
+  // USTAR SPLITS LONG NAMES AT A `/` INTO A 155 BYTE PREFIX AND A 100 BYTE NAME
+  fn split_name(name: &str) -> Option<(&str, &str)> {
+    if name.len() <= 100 {
+      return Some(("", name));
+    }
+
+    let trimmed = name.trim_end_matches('/');
+    name
+      .match_indices('/')
+      .map(|(i, _)| i)
+      .filter(|&i| i < trimmed.len())
+      .find(|&i| i <= 155 && name.len() - i - 1 <= 100)
+      .map(|i| (&name[..i], &name[i + 1..]))
+  }
+
+  // "<length> <key>=<value>\n", THE LENGTH COUNTS ITS OWN DIGITS
+  fn pax_record(records: &mut String, key: &str, value: &str) {
+    let body = format!(" {}={}\n", key, value);
+    let mut length = body.len() + 1;
+    while length.to_string().len() + body.len() != length {
+      length += 1;
+    }
+    records.push_str(&format!("{}{}", length, body));
+  }
+}
+
+impl Read for TarStream {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    while self.offset >= self.buffer.len() {
+      if self.is_finished {
+        return Ok(0);
+      }
+      self.advance()?;
+    }
+
+    let count = buf.len().min(self.buffer.len() - self.offset);
+    buf[..count].copy_from_slice(&self.buffer[self.offset..self.offset + count]);
+    self.offset += count;
+    Ok(count)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::Read;
+
+  use crate::library::testing::{local_vfs, TempDir};
+
+  use super::{ArchiveEntries, TarStream, ZipStream};
+
+  fn selection(root: &TempDir) -> ArchiveEntries {
+    ArchiveEntries::new(local_vfs(root), vec![(String::from("/docs"), String::from("docs"))])
+  }
+
+  #[test]
+  fn zip_stream_test() {
+    let root = TempDir::new("archive");
+    root.mkdir("docs/empty");
+    root.write("docs/readme.txt", "hello archive ".repeat(100));
+    let mut zip = Vec::new();
+    ZipStream::new(selection(&root)).read_to_end(&mut zip).unwrap();
+
+    let end = &zip[zip.len() - 22..];
+    assert_eq!(&end[..4], &0x06054b50u32.to_le_bytes());
+    assert_eq!(u16::from_le_bytes([end[10], end[11]]), 3);
+    assert!(zip.windows(11).any(|w| w == b"docs/empty/"));
+  }
+
+  #[test]
+  fn tar_stream_test() {
+    let root = TempDir::new("archive");
+    root.mkdir("docs/empty");
+    root.write("docs/readme.txt", "hello archive ".repeat(100));
+    let mut tar = Vec::new();
+    TarStream::new(selection(&root)).read_to_end(&mut tar).unwrap();
+
+    assert_eq!(tar.len() % 512, 0);
+    assert_eq!(&tar[..5], b"docs/");
+    assert_eq!(&tar[257..263], b"ustar\0");
+    assert_eq!(&tar[512 + 156], &b'5');
+    assert_eq!(&tar[1024..1039], b"docs/readme.txt");
+    assert_eq!(&tar[1024 + 124..1024 + 135], format!("{:011o}", 1400).as_bytes());
+    assert!(tar[tar.len() - 1024..].iter().all(|b| *b == 0));
+  }
+}
@@ -0,0 +1,310 @@
+use std::{collections::{BTreeSet, HashMap}, io::{self, Read}};
+
+use flate2::Crc;
+use logger_main::Logger;
+use md5::{Digest, Md5};
+use sha1::Sha1;
+use sha2::Sha256;
+
+use crate::config::constants::CHECKSUM_BUFFER_SIZE;
+use crate::library::archive::ArchiveEntries;
+use crate::library::tasks::Task;
+use crate::library::vfs::{EntryKind, Stat, Vfs};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+  Crc32,
+  Md5,
+  Sha1,
+  Sha256,
+  Blake3,
+}
+
+pub enum Hasher {
+  Crc32(Crc),
+  Md5(Md5),
+  Sha1(Sha1),
+  Sha256(Sha256),
+  Blake3(Box<blake3::Hasher>),
+}
+
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+  pub ok: u64,
+  pub mismatched: Vec<String>,
+  pub missing: Vec<String>,
+  pub extra: Vec<String>,
+  pub unreadable: Vec<String>,
+}
+
+// EMITS ONE `<digest>  <path>` LINE PER FILE, HASHING EACH ONLY WHEN THE CLIENT ASKS FOR MORE
+pub struct ManifestStream {
+  entries: ArchiveEntries,
+  root: String,
+  algorithm: Algorithm,
+  buffer: Vec<u8>,
+  offset: usize,
+}
+
+impl Algorithm {
+  pub fn from(value: &str) -> Option<Self> {
+    match value.to_ascii_lowercase().replace('-', "").as_str() {
+      "crc32" => Some(Algorithm::Crc32),
+      "md5" => Some(Algorithm::Md5),
+      "sha1" => Some(Algorithm::Sha1),
+      "sha256" => Some(Algorithm::Sha256),
+      "blake3" => Some(Algorithm::Blake3),
+      _ => None,
+    }
+  }
+
+  // 64 HEX DIGITS COULD BE EITHER SHA-256 OR BLAKE3, SHA-256 IS WHAT `sha256sum` WRITES
+  pub fn from_digest_length(length: usize) -> Option<Self> {
+    match length {
+      8 => Some(Algorithm::Crc32),
+      32 => Some(Algorithm::Md5),
+      40 => Some(Algorithm::Sha1),
+      64 => Some(Algorithm::Sha256),
+      _ => None,
+    }
+  }
+
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Algorithm::Crc32 => "crc32",
+      Algorithm::Md5 => "md5",
+      Algorithm::Sha1 => "sha1",
+      Algorithm::Sha256 => "sha256",
+      Algorithm::Blake3 => "blake3",
+    }
+  }
+}
+
+impl Hasher {
+  pub fn new(algorithm: Algorithm) -> Self {
+    match algorithm {
+      Algorithm::Crc32 => Hasher::Crc32(Crc::new()),
+      Algorithm::Md5 => Hasher::Md5(Md5::new()),
+      Algorithm::Sha1 => Hasher::Sha1(Sha1::new()),
+      Algorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+      Algorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+    }
+  }
+
+  pub fn update(&mut self, bytes: &[u8]) {
+    match self {
+      Hasher::Crc32(crc) => crc.update(bytes),
+      Hasher::Md5(md5) => md5.update(bytes),
+      Hasher::Sha1(sha1) => sha1.update(bytes),
+      Hasher::Sha256(sha256) => sha256.update(bytes),
+      Hasher::Blake3(blake3) => drop(blake3.update(bytes)),
+    }
+  }
+
+  pub fn finish(self) -> String {
+    let digest: Vec<u8> = match self {
+      Hasher::Crc32(crc) => crc.sum().to_be_bytes().to_vec(),
+      Hasher::Md5(md5) => md5.finalize().to_vec(),
+      Hasher::Sha1(sha1) => sha1.finalize().to_vec(),
+      Hasher::Sha256(sha256) => sha256.finalize().to_vec(),
+      Hasher::Blake3(blake3) => blake3.finalize().as_bytes().to_vec(),
+    };
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+  }
+}
+
+// A TASK GETS PROGRESS FOR EVERY BUFFER AND CAN STOP THE HASH BETWEEN THEM
+pub fn hash(mut reader: impl Read, algorithm: Algorithm, task: Option<&Task>) -> io::Result<String> {
+  let mut hasher = Hasher::new(algorithm);
+  let mut buffer = vec![0; CHECKSUM_BUFFER_SIZE];
+  loop {
+    if let Some(task) = task {
+      task.checkpoint()?;
+    }
+
+    let read = match reader.read(&mut buffer) {
+      Ok(0) => break,
+      Ok(read) => read,
+      Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+      Err(e) => return Err(e),
+    };
+
+    hasher.update(&buffer[..read]);
+    if let Some(task) = task {
+      task.advance(read as u64);
+    }
+  }
+  Ok(hasher.finish())
+}
+
+// SAME ESCAPING AS GNU `sha256sum`: A LEADING `\` MARKS A NAME WITH `\` OR NEWLINES IN IT
+pub fn manifest_line(digest: &str, name: &str) -> String {
+  match name.contains(['\\', '\n', '\r']) {
+    true => format!("\\{}  {}\n", digest, name.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r")),
+    false => format!("{}  {}\n", digest, name),
+  }
+}
+
+pub fn parse_manifest(text: &str) -> Result<Vec<(String, String)>, String> {
+  let mut entries = Vec::new();
+  for (number, line) in text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+    let (is_escaped, line) = match line.strip_prefix('\\') {
+      Some(rest) => (true, rest),
+      None => (false, line),
+    };
+
+    let (digest, name) = line
+      .split_once("  ")
+      .or_else(|| line.split_once(" *"))
+      .filter(|(digest, name)| !name.is_empty() && !digest.is_empty() && digest.chars().all(|c| c.is_ascii_hexdigit()))
+      .ok_or_else(|| format!("line {} is not a `<digest>  <path>` entry", number + 1))?;
+
+    let name = match is_escaped {
+      true => unescape(name),
+      false => name.to_owned(),
+    };
+    entries.push((digest.to_ascii_lowercase(), name.trim_start_matches("./").to_owned()));
+  }
+  Ok(entries)
+}
+
+pub fn verify(vfs: &Vfs, root: &str, algorithm: Algorithm, entries: &[(String, String)], task: &Task) -> io::Result<VerifyReport> {
+  let mut report = VerifyReport::default();
+  let listed: BTreeSet<&str> = entries.iter().map(|(_, name)| name.as_str()).collect();
+
+  let files: Vec<(String, String, Stat)> = ArchiveEntries::new(vfs.clone(), vec![(root.to_owned(), String::new())])
+    .filter(|entry| entry.stat.kind == EntryKind::File)
+    .map(|entry| (entry.path, entry.name, entry.stat))
+    .collect();
+  let by_name: HashMap<&str, (&String, &Stat)> = files.iter().map(|(path, name, stat)| (name.as_str(), (path, stat))).collect();
+  task.set_total(entries.iter().filter_map(|(_, name)| by_name.get(name.as_str())).map(|(_, stat)| stat.size).sum());
+
+  for (expected, name) in entries {
+    task.checkpoint()?;
+    let Some((path, _)) = by_name.get(name.as_str()) else {
+      report.missing.push(name.clone());
+      continue;
+    };
+
+    match vfs.open_read(path, 0).and_then(|reader| hash(reader, algorithm, Some(task))) {
+      Ok(actual) if actual == *expected => report.ok += 1,
+      Ok(_) => report.mismatched.push(name.clone()),
+      Err(e) if e.kind() == io::ErrorKind::Interrupted => return Err(e),
+      Err(_) => report.unreadable.push(name.clone()),
+    }
+  }
+
+  report.extra = files.into_iter().map(|(_, name, _)| name).filter(|name| !listed.contains(name.as_str())).collect();
+  Ok(report)
+}
+
+fn unescape(value: &str) -> String {
+  let mut unescaped = String::with_capacity(value.len());
+  let mut chars = value.chars();
+  while let Some(c) = chars.next() {
+    match (c, c == '\\') {
+      (_, true) => match chars.next() {
+        Some('n') => unescaped.push('\n'),
+        Some('r') => unescaped.push('\r'),
+        Some(other) => unescaped.push(other),
+        None => unescaped.push('\\'),
+      },
+      (c, false) => unescaped.push(c),
+    }
+  }
+  unescaped
+}
+
+impl ManifestStream {
+  pub fn new(vfs: Vfs, root: String, algorithm: Algorithm) -> Self {
+    let entries = ArchiveEntries::new(vfs, vec![(root.clone(), String::new())]);
+    ManifestStream { entries, root, algorithm, buffer: Vec::new(), offset: 0 }
+  }
+}
+
+impl Read for ManifestStream {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    while self.offset >= self.buffer.len() {
+      let Some(entry) = self.entries.next() else {
+        return Ok(0);
+      };
+
+      if entry.stat.kind != EntryKind::File {
+        continue;
+      }
+
+      // A FILE THAT CANNOT BE READ IS LEFT OUT, VERIFYING LATER REPORTS IT AS EXTRA
+      match self.entries.open(&entry).and_then(|reader| hash(reader, self.algorithm, None)) {
+        Ok(digest) => {
+          self.buffer = manifest_line(&digest, &entry.name).into_bytes();
+          self.offset = 0;
+        },
+        Err(e) => Logger::warn(format!("Manifest - Skipped {} under {}: {}", entry.name, self.root, e)),
+      }
+    }
+
+    let count = buf.len().min(self.buffer.len() - self.offset);
+    buf[..count].copy_from_slice(&self.buffer[self.offset..self.offset + count]);
+    self.offset += count;
+    Ok(count)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::Read;
+
+  use crate::library::testing::{in_task, local_vfs, TempDir};
+
+  use super::{parse_manifest, verify, Algorithm, Hasher, ManifestStream};
+
+  fn manifest(root: &TempDir) -> String {
+    let mut manifest = String::new();
+    ManifestStream::new(local_vfs(root), String::from("/"), Algorithm::Sha256).read_to_string(&mut manifest).unwrap();
+    manifest
+  }
+
+  #[test]
+  fn hasher_digest_test() {
+    let digest = |algorithm| {
+      let mut hasher = Hasher::new(algorithm);
+      hasher.update(b"abc");
+      hasher.finish()
+    };
+    assert_eq!(digest(Algorithm::Crc32), "352441c2");
+    assert_eq!(digest(Algorithm::Md5), "900150983cd24fb0d6963f7d28e17f72");
+    assert_eq!(digest(Algorithm::Sha1), "a9993e364706816aba3e25717850c26c9cd0d89d");
+    assert_eq!(digest(Algorithm::Sha256), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    assert_eq!(digest(Algorithm::Blake3), "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85");
+  }
+
+  #[test]
+  fn manifest_stream_test() {
+    let root = TempDir::new("checksum");
+    root.write("docs/a.txt", "alpha");
+    root.write("b.txt", "beta");
+    let manifest = manifest(&root);
+    assert_eq!(manifest.lines().count(), 2);
+    assert!(manifest.contains("  docs/a.txt\n"));
+    assert_eq!(parse_manifest(&manifest).unwrap().len(), 2);
+  }
+
+  #[test]
+  fn manifest_verify_test() {
+    let root = TempDir::new("checksum");
+    root.write("a.txt", "alpha");
+    root.write("b.txt", "beta");
+    root.write("c.txt", "gamma");
+    let entries = parse_manifest(&manifest(&root)).unwrap();
+    root.write("b.txt", "changed");
+    std::fs::remove_file(root.join("c.txt")).unwrap();
+    root.write("d.txt", "delta");
+
+    let checked = local_vfs(&root);
+    let report = in_task("verify", move |task| verify(&checked, "/", Algorithm::Sha256, &entries, task)).unwrap();
+    assert_eq!(report.ok, 1);
+    assert_eq!(report.mismatched, vec![String::from("b.txt")]);
+    assert_eq!(report.missing, vec![String::from("c.txt")]);
+    assert_eq!(report.extra, vec![String::from("d.txt")]);
+  }
+}
@@ -0,0 +1,334 @@
+use std::{collections::{HashSet, VecDeque}, fs::{self, File}, io::{self, Cursor, Read}, path::{Component, Path, PathBuf}, sync::{atomic::{AtomicU64, Ordering}, LazyLock}, time::{Duration, SystemTime}};
+
+use crate::config::constants::{CHUNKED_ROOTS, CHUNK_AVERAGE_BITS, CHUNK_GC_GRACE_SECS, CHUNK_MAX_SIZE, CHUNK_MIN_SIZE, CHUNK_STORE, STORAGE_ROOT, TRASH_ROOT, VERSION_STORE};
+use crate::library::checksum::{Algorithm, Hasher};
+use crate::library::storage::Storage;
+use crate::library::tasks::Task;
+
+static CHUNKS: LazyLock<ChunkStore> = LazyLock::new(|| ChunkStore::new(PathBuf::from(CHUNK_STORE), PathBuf::from(STORAGE_ROOT), CHUNKED_ROOTS));
+static STAGED: AtomicU64 = AtomicU64::new(0);
+
+// ONE RANDOM-LOOKING WORD PER BYTE VALUE, FIXED SO THE SAME CONTENT ALWAYS SPLITS THE SAME WAY
+static GEAR: LazyLock<[u64; 256]> = LazyLock::new(|| {
+  let (mut table, mut state) = ([0u64; 256], 0x9e37_79b9_7f4a_7c15u64);
+  for word in table.iter_mut() {
+    state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    *word = z ^ (z >> 31);
+  }
+  table
+});
+
+const MANIFEST_HEADER: &str = "FMCHUNKS 1\n";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+  pub size: u64,
+  pub chunks: Vec<(String, u64)>,
+}
+
+#[derive(Debug, Default)]
+pub struct CollectReport {
+  pub referenced: u64,
+  pub removed: u64,
+  pub bytes: u64,
+}
+
+// FILES UNDER A CHUNKED ROOT ARE MANIFESTS, THEIR CONTENT IS STORED ONCE PER CHUNK UNDER THE CHUNK STORE
+pub struct ChunkStore {
+  root: PathBuf,
+  storage: PathBuf,
+  roots: Vec<String>,
+}
+
+// READS `length` BYTES FROM `offset` ONE CHUNK AT A TIME, CHECKING EVERY CHUNK AGAINST ITS NAME
+pub struct ChunkReader {
+  root: PathBuf,
+  chunks: VecDeque<(String, u64)>,
+  skip: u64,
+  remaining: u64,
+  current: Cursor<Vec<u8>>,
+}
+
+impl Manifest {
+  pub fn parse(text: &str) -> Option<Self> {
+    let mut lines = text.strip_prefix(MANIFEST_HEADER)?.lines();
+    let size = lines.next()?.strip_prefix("size ")?.parse().ok()?;
+    let chunks = lines
+      .map(|line| line.split_once(' ').and_then(|(hash, length)| Some((hash.to_owned(), length.parse().ok()?))))
+      .collect::<Option<Vec<(String, u64)>>>()?;
+
+    let is_valid = chunks.iter().all(|(hash, _)| hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()));
+    (is_valid && chunks.iter().map(|(_, length)| length).sum::<u64>() == size).then_some(Manifest { size, chunks })
+  }
+
+  pub fn render(&self) -> String {
+    let mut text = format!("{}size {}\n", MANIFEST_HEADER, self.size);
+    for (hash, length) in self.chunks.iter() {
+      text.push_str(&format!("{} {}\n", hash, length));
+    }
+    text
+  }
+
+  // NONE FOR A FILE THAT IS NOT A MANIFEST, ONLY THE HEADER IS READ TO TELL
+  pub fn read(path: &Path) -> io::Result<Option<Self>> {
+    let mut file = File::open(path)?;
+    let mut header = [0; MANIFEST_HEADER.len()];
+    if file.read_exact(&mut header).is_err() || header != MANIFEST_HEADER.as_bytes() {
+      return Ok(None);
+    }
+
+    let mut text = String::from(MANIFEST_HEADER);
+    file.read_to_string(&mut text)?;
+    Manifest::parse(&text).map(Some).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("{} is not a valid chunk manifest", path.display())))
+  }
+}
+
+// GEAR ROLLING HASH, A BOUNDARY FALLS WHERE THE LOW BITS ARE ZERO SO AN INSERT ONLY MOVES THE CHUNKS AROUND IT
+pub fn split(content: &[u8]) -> Vec<&[u8]> {
+  let mask = (1u64 << CHUNK_AVERAGE_BITS) - 1;
+  let (mut chunks, mut start, mut hash) = (Vec::new(), 0, 0u64);
+  for (index, byte) in content.iter().enumerate() {
+    hash = (hash << 1).wrapping_add(GEAR[*byte as usize]);
+    let length = index + 1 - start;
+    if (length >= CHUNK_MIN_SIZE && hash & mask == 0) || length >= CHUNK_MAX_SIZE {
+      chunks.push(&content[start..=index]);
+      start = index + 1;
+      hash = 0;
+    }
+  }
+
+  if start < content.len() {
+    chunks.push(&content[start..]);
+  }
+  chunks
+}
+
+impl ChunkStore {
+  pub fn new(root: PathBuf, storage: PathBuf, roots: &[&str]) -> Self {
+    let roots = roots.iter().map(|r| r.trim_matches('/').to_owned()).collect();
+    ChunkStore { root, storage, roots }
+  }
+
+  pub fn global() -> &'static ChunkStore {
+    &CHUNKS
+  }
+
+  pub fn is_chunked(&self, target: &Path) -> bool {
+    let Ok(relative) = target.strip_prefix(&self.storage) else {
+      return false;
+    };
+
+    let names: Option<Vec<String>> = relative.components().map(|c| match c {
+      Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+      _ => None,
+    }).collect();
+
+    let Some(key) = names.map(|n| n.join("/")) else {
+      return false;
+    };
+    self.roots.iter().any(|root| root.is_empty() || key == *root || key.starts_with(&format!("{}/", root)))
+  }
+
+  // A FILE WRITTEN BEFORE ITS ROOT BECAME CHUNKED IS STILL READ AS IT IS
+  pub fn manifest(&self, target: &Path) -> io::Result<Option<Manifest>> {
+    match self.is_chunked(target) {
+      true => Manifest::read(target),
+      false => Ok(None),
+    }
+  }
+
+  // CHUNKS GO IN FIRST, THE MANIFEST REPLACES THE OLD FILE LAST SO A READER SEES ONE OR THE OTHER
+  pub fn write(&self, target: &Path, content: &[u8]) -> io::Result<Manifest> {
+    let mut manifest = Manifest { size: content.len() as u64, chunks: Vec::new() };
+    for chunk in split(content) {
+      let mut hasher = Hasher::new(Algorithm::Blake3);
+      hasher.update(chunk);
+      let hash = hasher.finish();
+      self.store(&hash, chunk)?;
+      manifest.chunks.push((hash, chunk.len() as u64));
+    }
+
+    let name = target.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let staged = target.with_file_name(format!(".{}.{}.chunks", name, STAGED.fetch_add(1, Ordering::Relaxed)));
+    if let Err(e) = fs::write(&staged, manifest.render()).and_then(|_| fs::rename(&staged, target)) {
+      let _ = fs::remove_file(&staged);
+      return Err(e);
+    }
+    Ok(manifest)
+  }
+
+  pub fn reader(&self, manifest: &Manifest, offset: u64, length: u64) -> ChunkReader {
+    let mut chunks: VecDeque<(String, u64)> = manifest.chunks.iter().cloned().collect();
+    let mut skip = offset;
+    while let Some((_, size)) = chunks.front().filter(|(_, size)| *size <= skip) {
+      skip -= size;
+      chunks.pop_front();
+    }
+    ChunkReader { root: self.root.clone(), chunks, skip, remaining: length, current: Cursor::new(Vec::new()) }
+  }
+
+  // MARKS EVERY CHUNK A MANIFEST STILL NAMES, IN STORAGE, IN OLD VERSIONS OR IN THE TRASH, THEN SWEEPS THE REST.
+  // A CHUNK YOUNGER THAN THE GRACE PERIOD MAY BELONG TO A WRITE THAT HAS NOT PUT ITS MANIFEST IN PLACE YET
+  pub fn collect(&self, sources: &[PathBuf], task: &Task) -> io::Result<CollectReport> {
+    let mut referenced = HashSet::new();
+    for source in sources {
+      for (path, _) in Storage::files(source) {
+        task.checkpoint()?;
+        // A MANIFEST THAT CANNOT BE READ COULD NAME ANY CHUNK, SO NOTHING IS SWEPT
+        match Manifest::read(&path) {
+          Ok(Some(manifest)) => referenced.extend(manifest.chunks.into_iter().map(|(hash, _)| hash)),
+          Ok(None) => {},
+          Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+          Err(e) => return Err(e),
+        }
+      }
+    }
+
+    let mut report = CollectReport { referenced: referenced.len() as u64, ..CollectReport::default() };
+    let cutoff = SystemTime::now() - Duration::from_secs(CHUNK_GC_GRACE_SECS);
+    for (path, metadata) in Storage::files(&self.root) {
+      task.checkpoint()?;
+      let hash = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+      if referenced.contains(&hash) || metadata.modified().is_ok_and(|m| m > cutoff) {
+        continue;
+      }
+
+      fs::remove_file(&path)?;
+      report.removed += 1;
+      report.bytes += metadata.len();
+    }
+    Ok(report)
+  }
+
+  pub fn sources() -> Vec<PathBuf> {
+    vec![PathBuf::from(STORAGE_ROOT), PathBuf::from(VERSION_STORE), PathBuf::from(TRASH_ROOT)]
+  }
+}
+
+impl ChunkStore {
+  // AN EXISTING CHUNK IS TOUCHED INSTEAD, WHICH KEEPS A CONCURRENT COLLECTION FROM TAKING IT
+  fn store(&self, hash: &str, chunk: &[u8]) -> io::Result<()> {
+    let path = self.path(hash);
+    if let Ok(file) = File::options().append(true).open(&path) {
+      return file.set_modified(SystemTime::now());
+    }
+
+    fs::create_dir_all(path.parent().unwrap_or(&self.root))?;
+    let staged = path.with_extension(format!("{}.tmp", STAGED.fetch_add(1, Ordering::Relaxed)));
+    fs::write(&staged, chunk).and_then(|_| fs::rename(&staged, &path)).inspect_err(|_| {
+      let _ = fs::remove_file(&staged);
+    })
+  }
+
+  fn path(&self, hash: &str) -> PathBuf {
+    self.root.join(&hash[..2]).join(hash)
+  }
+}
+
+impl Read for ChunkReader {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    if self.remaining == 0 {
+      return Ok(0);
+    }
+
+    if self.current.position() >= self.current.get_ref().len() as u64 {
+      let Some((hash, length)) = self.chunks.pop_front() else {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "manifest ended before the requested range"));
+      };
+
+      let chunk = fs::read(self.root.join(&hash[..2]).join(&hash))?;
+      let mut hasher = Hasher::new(Algorithm::Blake3);
+      hasher.update(&chunk);
+      if chunk.len() as u64 != length || hasher.finish() != hash {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("chunk {} is corrupt", hash)));
+      }
+
+      self.current = Cursor::new(chunk);
+      self.current.set_position(std::mem::take(&mut self.skip));
+    }
+
+    let limit = (buf.len() as u64).min(self.remaining) as usize;
+    let read = self.current.read(&mut buf[..limit])?;
+    self.remaining -= read as u64;
+    Ok(read)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{collections::HashSet, fs, io::Read, path::PathBuf, time::UNIX_EPOCH};
+
+  use crate::library::testing::{in_task, noise, TempDir};
+
+  use super::{split, ChunkStore, Manifest};
+
+  fn store() -> (TempDir, ChunkStore, PathBuf) {
+    let root = TempDir::new("chunks");
+    let storage = root.mkdir("storage/backups").parent().unwrap().to_path_buf();
+    let store = ChunkStore::new(root.join("chunks"), storage.clone(), &["/backups"]);
+    (root, store, storage)
+  }
+
+  fn edited(content: &[u8]) -> Vec<u8> {
+    let mut edited = content.to_vec();
+    edited.splice(1000..1000, b"inserted".iter().copied());
+    edited
+  }
+
+  #[test]
+  fn chunk_split_test() {
+    let content = noise(400_000);
+    let chunks = split(&content);
+    assert!(chunks.len() > 10);
+    assert_eq!(chunks.concat(), content);
+
+    // AN INSERT NEAR THE START LEAVES MOST LATER CHUNKS AS THEY WERE
+    let edited = edited(&content);
+    let shared = split(&edited).iter().filter(|c| chunks.contains(c)).count();
+    assert!(shared + 3 >= chunks.len());
+  }
+
+  #[test]
+  fn chunk_store_read_test() {
+    let (_root, store, storage) = store();
+    assert!(!store.is_chunked(&storage.join("other.bin")));
+    assert!(store.is_chunked(&storage.join("backups/a.bin")));
+
+    let content = noise(200_000);
+    let target = storage.join("backups/a.bin");
+    let manifest = store.write(&target, &content).unwrap();
+    assert_eq!(store.manifest(&target).unwrap(), Some(manifest.clone()));
+    assert_eq!(Manifest::parse(&manifest.render()), Some(manifest.clone()));
+
+    let mut range = Vec::new();
+    store.reader(&manifest, 99_000, 50_000).read_to_end(&mut range).unwrap();
+    assert_eq!(range, &content[99_000..149_000]);
+  }
+
+  #[test]
+  fn chunk_collect_test() {
+    let (root, store, storage) = store();
+    let content = noise(400_000);
+    let (first, second) = (storage.join("backups/first.bin"), storage.join("backups/second.bin"));
+    store.write(&first, &content).unwrap();
+    let manifest = store.write(&second, &edited(&content)).unwrap();
+    fs::remove_file(&first).unwrap();
+
+    // EVERY CHUNK IS MADE OLDER THAN THE GRACE PERIOD SO THE SWEEP CAN TAKE IT
+    for entry in fs::read_dir(root.join("chunks")).unwrap().flat_map(|d| fs::read_dir(d.unwrap().path()).unwrap()) {
+      fs::File::options().append(true).open(entry.unwrap().path()).unwrap().set_modified(UNIX_EPOCH).unwrap();
+    }
+
+    let (chunks, sources) = (root.join("chunks"), vec![storage.clone()]);
+    let report = in_task("chunk-gc", move |task| ChunkStore::new(chunks, storage, &["/backups"]).collect(&sources, task).map_err(|e| e.kind())).unwrap();
+    assert_eq!(report.referenced as usize, manifest.chunks.iter().map(|(h, _)| h).collect::<HashSet<_>>().len());
+    assert!(report.removed > 0);
+
+    let mut whole = Vec::new();
+    store.reader(&manifest, 0, manifest.size).read_to_end(&mut whole).unwrap();
+    assert_eq!(whole, edited(&content));
+  }
+}
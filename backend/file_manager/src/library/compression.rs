@@ -0,0 +1,160 @@
+use std::io::{self, Read, Write};
+
+use flate2::{write::{GzEncoder, ZlibEncoder}, Compression};
+
+use crate::config::constants::{
+  COMPRESSION_BROTLI_QUALITY, COMPRESSION_BROTLI_WINDOW, COMPRESSION_BUFFER_SIZE, COMPRESSION_MIN_LENGTH, INCOMPRESSIBLE_MIME_TYPES,
+};
+use crate::enums::app_enums::ContentEncoding;
+use crate::parser::{http_request::HttpRequest, http_response::HttpResponse};
+use crate::router::middleware::{Context, Middleware, Next};
+
+// SERVER PREFERENCE ORDER, USED TO BREAK TIES BETWEEN EQUAL Q-VALUES
+const SUPPORTED_ENCODINGS: [ContentEncoding; 3] = [ContentEncoding::BROTLI, ContentEncoding::GZIP, ContentEncoding::DEFLATE];
+
+pub struct Compressor;
+
+impl Middleware for Compressor {
+  fn handle(&self, context: &mut Context, next: Next) -> HttpResponse {
+    let mut http_response = next.run(context);
+    compress(&mut http_response, &context.http_request);
+    http_response
+  }
+}
+
+pub fn compress(http_response: &mut HttpResponse, http_request: &HttpRequest) {
+  if http_response.get_header("Content-Encoding").is_some() || !is_compressible(&http_response.content_type) {
+    return;
+  }
+
+  if matches!(http_response.status.as_str(), "204" | "206" | "304") {
+    return;
+  }
+
+  if let Some(length) = http_response.content_length {
+    if length < COMPRESSION_MIN_LENGTH {
+      return;
+    }
+  }
+
+  http_response.header("Vary", "Accept-Encoding");
+  let encoding = negotiate(&http_request.accept_encoding);
+  if encoding == ContentEncoding::IDENTITY {
+    return;
+  }
+
+  http_response.encoding = encoding;
+  http_response.header("Content-Encoding", encoding.as_string());
+}
+
+pub fn negotiate(accept_encoding: &[String]) -> ContentEncoding {
+  let mut accepted: Vec<(String, f32)> = Vec::new();
+  for item in accept_encoding {
+    let mut parts = item.split(';');
+    let token = parts.next().unwrap_or_default().trim().to_ascii_lowercase();
+    let q = parts
+      .filter_map(|p| p.trim().strip_prefix("q=").map(|v| v.trim().parse::<f32>().unwrap_or(0.0)))
+      .next()
+      .unwrap_or(1.0);
+
+    accepted.push((token, q));
+  }
+
+  let wildcard = accepted.iter().find(|(t, _)| t == "*").map(|(_, q)| *q);
+  let mut selected = (ContentEncoding::IDENTITY, 0.0);
+  for encoding in SUPPORTED_ENCODINGS {
+    let q = accepted
+      .iter()
+      .find(|(t, _)| ContentEncoding::from(t) == Some(encoding))
+      .map(|(_, q)| *q)
+      .or(wildcard)
+      .unwrap_or(0.0);
+
+    if q > selected.1 {
+      selected = (encoding, q);
+    }
+  }
+
+  selected.0
+}
+
+pub fn is_compressible(content_type: &str) -> bool {
+  let mime = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+  if mime == "image/svg+xml" {
+    return true;
+  }
+
+  !INCOMPRESSIBLE_MIME_TYPES.iter().any(|m| mime.starts_with(m))
+}
+
+pub fn encode<W: Write>(encoding: &ContentEncoding, reader: &mut dyn Read, mut writer: W) -> io::Result<W> {
+  match encoding {
+    ContentEncoding::GZIP => {
+      let mut encoder = GzEncoder::new(writer, Compression::default());
+      io::copy(reader, &mut encoder)?;
+      encoder.finish()
+    },
+    ContentEncoding::DEFLATE => {
+      let mut encoder = ZlibEncoder::new(writer, Compression::default());
+      io::copy(reader, &mut encoder)?;
+      encoder.finish()
+    },
+    ContentEncoding::BROTLI => {
+      let mut encoder = brotli::CompressorWriter::new(
+        writer, COMPRESSION_BUFFER_SIZE, COMPRESSION_BROTLI_QUALITY, COMPRESSION_BROTLI_WINDOW
+      );
+      io::copy(reader, &mut encoder)?;
+      encoder.flush()?;
+      Ok(encoder.into_inner())
+    },
+    ContentEncoding::IDENTITY => {
+      io::copy(reader, &mut writer)?;
+      Ok(writer)
+    },
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::{Cursor, Read};
+
+  use flate2::read::GzDecoder;
+
+  use crate::enums::app_enums::ContentEncoding;
+
+  use super::{encode, is_compressible, negotiate};
+
+  fn tokens(values: &[&str]) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+  }
+
+  #[test]
+  fn negotiate_q_value_test() {
+    assert_eq!(negotiate(&tokens(&["gzip", "deflate", "br"])), ContentEncoding::BROTLI);
+    assert_eq!(negotiate(&tokens(&["gzip;q=1.0", "br;q=0.5"])), ContentEncoding::GZIP);
+    assert_eq!(negotiate(&tokens(&["deflate", "gzip;q=0"])), ContentEncoding::DEFLATE);
+    assert_eq!(negotiate(&tokens(&["*;q=0.3", "br;q=0"])), ContentEncoding::GZIP);
+    assert_eq!(negotiate(&tokens(&["identity"])), ContentEncoding::IDENTITY);
+    assert_eq!(negotiate(&[]), ContentEncoding::IDENTITY);
+  }
+
+  #[test]
+  fn compressible_mime_test() {
+    assert!(is_compressible("application/json"));
+    assert!(is_compressible("text/plain; charset=utf-8"));
+    assert!(is_compressible("image/svg+xml"));
+    assert!(!is_compressible("image/png"));
+    assert!(!is_compressible("application/zip"));
+  }
+
+  #[test]
+  fn encode_gzip_round_trip_test() {
+    let contents = "file_manager ".repeat(512);
+    let encoded = encode(&ContentEncoding::GZIP, &mut Cursor::new(contents.clone()), Vec::new()).unwrap();
+    assert!(encoded.len() < contents.len());
+
+    let mut decoded = String::new();
+    GzDecoder::new(encoded.as_slice()).read_to_string(&mut decoded).unwrap();
+    assert_eq!(decoded, contents);
+  }
+}
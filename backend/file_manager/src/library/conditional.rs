@@ -0,0 +1,98 @@
+use std::{fs::Metadata, os::unix::fs::MetadataExt, time::{SystemTime, UNIX_EPOCH}};
+
+use crate::config::utility::{format_http_date, parse_http_date};
+use crate::library::vfs::{EntryKind, Stat};
+use crate::parser::{http_request::HttpRequest, http_response::HttpResponse};
+
+pub struct Validators {
+  pub etag: String,
+  pub last_modified: SystemTime,
+}
+
+impl Validators {
+  pub fn from(metadata: &Metadata) -> Self {
+    Validators::build(metadata.ino(), metadata.size(), metadata.modified().unwrap_or(UNIX_EPOCH), metadata.is_dir())
+  }
+
+  pub fn from_stat(stat: &Stat) -> Self {
+    Validators::build(stat.inode, stat.size, stat.modified, stat.kind == EntryKind::Directory)
+  }
+
+  pub fn apply(&self, http_response: &mut HttpResponse) {
+    http_response
+      .header("ETag", self.etag.as_str())
+      .header("Last-Modified", format_http_date(self.last_modified));
+  }
+}
+
+impl Validators {
+  pub fn is_not_modified(&self, http_request: &HttpRequest) -> bool {
+    if let Some(if_none_match) = http_request.header("if-none-match") {
+      return if_none_match.trim() == "*"
+        || if_none_match.split(',').any(|tag| weak_compare(tag.trim(), &self.etag));
+    }
+
+    match http_request.header("if-modified-since").and_then(|v| parse_http_date(v)) {
+      Some(since) => unix_seconds(self.last_modified) <= unix_seconds(since),
+      None => false,
+    }
+  }
+}
+
+impl Validators {
+  fn build(inode: u64, size: u64, last_modified: SystemTime, is_directory: bool) -> Self {
+    let nanos = last_modified.duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+    let tag = format!("\"{:x}-{:x}-{:x}\"", inode, size, nanos);
+
+    // DIRECTORY LISTINGS ARE NOT BYTE-FOR-BYTE STABLE, SO ONLY A WEAK VALIDATOR IS OFFERED
+    let etag = if is_directory { format!("W/{}", tag) } else { tag };
+    Validators { etag, last_modified }
+  }
+}
+
+pub fn is_precondition_failed(http_request: &HttpRequest, current: Option<&Validators>) -> bool {
+  if let Some(if_match) = http_request.header("if-match") {
+    return match current {
+      None => true,
+      Some(_) if if_match.trim() == "*" => false,
+      Some(validators) => !if_match.split(',').any(|tag| strong_compare(tag.trim(), &validators.etag)),
+    };
+  }
+
+  if let (Some(since), Some(validators)) = (http_request.header("if-unmodified-since").and_then(|v| parse_http_date(v)), current) {
+    if unix_seconds(validators.last_modified) > unix_seconds(since) {
+      return true;
+    }
+  }
+
+  match (http_request.header("if-none-match"), current) {
+    (Some(if_none_match), Some(validators)) => if_none_match.trim() == "*"
+      || if_none_match.split(',').any(|tag| weak_compare(tag.trim(), &validators.etag)),
+    _ => false,
+  }
+}
+
+fn weak_compare(a: &str, b: &str) -> bool {
+  a.trim_start_matches("W/") == b.trim_start_matches("W/")
+}
+
+fn strong_compare(a: &str, b: &str) -> bool {
+  !a.starts_with("W/") && !b.starts_with("W/") && a == b
+}
+
+fn unix_seconds(dt: SystemTime) -> u64 {
+  dt.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{strong_compare, weak_compare};
+
+  #[test]
+  fn etag_compare_test() {
+    assert!(weak_compare("W/\"1-2-3\"", "\"1-2-3\""));
+    assert!(!strong_compare("W/\"1-2-3\"", "\"1-2-3\""));
+    assert!(strong_compare("\"1-2-3\"", "\"1-2-3\""));
+    assert!(!strong_compare("\"1-2-3\"", "\"1-2-4\""));
+  }
+}
@@ -0,0 +1,175 @@
+use crate::config::constants::{
+  CORS_ALLOWED_HEADERS, CORS_ALLOWED_METHODS, CORS_ALLOWED_ORIGINS, CORS_ALLOW_CREDENTIALS, CORS_EXPOSED_HEADERS, CORS_MAX_AGE,
+};
+use crate::enums::{app_enums::HttpMethod, app_error::AppError};
+use crate::parser::{http_request::HttpRequest, http_response::HttpResponse};
+use crate::router::middleware::{Context, Middleware, Next};
+
+pub enum AllowedOrigins {
+  Any,
+  List(Vec<String>),
+}
+
+pub struct CorsPolicy {
+  pub origins: AllowedOrigins,
+  pub methods: Vec<String>,
+  pub headers: Vec<String>,
+  pub exposed_headers: Vec<String>,
+  pub credentials: bool,
+  pub max_age: u32,
+}
+
+impl CorsPolicy {
+  pub fn new() -> Self {
+    let origins = match CORS_ALLOWED_ORIGINS.contains(&"*") {
+      true => AllowedOrigins::Any,
+      false => AllowedOrigins::List(CORS_ALLOWED_ORIGINS.iter().map(|o| o.to_string()).collect()),
+    };
+
+    CorsPolicy {
+      origins,
+      methods: CORS_ALLOWED_METHODS.iter().map(|m| m.to_string()).collect(),
+      headers: CORS_ALLOWED_HEADERS.iter().map(|h| h.to_string()).collect(),
+      exposed_headers: CORS_EXPOSED_HEADERS.iter().map(|h| h.to_string()).collect(),
+      credentials: CORS_ALLOW_CREDENTIALS,
+      max_age: CORS_MAX_AGE,
+    }
+  }
+}
+
+impl CorsPolicy {
+  pub fn is_preflight(&self, http_request: &HttpRequest) -> bool {
+    http_request.method == HttpMethod::OPTIONS
+      && http_request.header("origin").is_some()
+      && http_request.header("access-control-request-method").is_some()
+  }
+
+  pub fn preflight(&self, http_request: &HttpRequest, request_id: &str) -> HttpResponse {
+    let origin = http_request.header("origin").cloned().unwrap_or_default();
+    let method = http_request.header("access-control-request-method").cloned().unwrap_or_default();
+    let requested_headers: Vec<String> = http_request
+      .header("access-control-request-headers")
+      .map(|h| h.split(',').map(|x| x.trim().to_owned()).filter(|x| !x.is_empty()).collect())
+      .unwrap_or_default();
+
+    let is_allowed = self.allows_origin(&origin)
+      && self.methods.iter().any(|m| m.eq_ignore_ascii_case(&method))
+      && requested_headers.iter().all(|h| self.allows_header(h));
+
+    if !is_allowed {
+      return AppError::Forbidden(format!("CORS preflight from {} is not allowed", origin)).into_response(request_id);
+    }
+
+    let allowed_headers = match self.headers.iter().any(|h| h == "*") {
+      true => requested_headers.join(", "),
+      false => self.headers.join(", "),
+    };
+
+    let mut http_response = HttpResponse::new("204", "No Content", "");
+    self.allow_origin(&origin, &mut http_response);
+    http_response
+      .header("Access-Control-Allow-Methods", self.methods.join(", "))
+      .header("Access-Control-Allow-Headers", allowed_headers)
+      .header("Access-Control-Max-Age", self.max_age.to_string())
+      .header("Vary", "Access-Control-Request-Method, Access-Control-Request-Headers");
+    http_response
+  }
+
+  pub fn apply(&self, http_request: &HttpRequest, http_response: &mut HttpResponse) {
+    let Some(origin) = http_request.header("origin") else {
+      return;
+    };
+
+    if !self.allows_origin(origin) {
+      return;
+    }
+
+    self.allow_origin(origin, http_response);
+    if !self.exposed_headers.is_empty() {
+      http_response.header("Access-Control-Expose-Headers", self.exposed_headers.join(", "));
+    }
+  }
+}
+
+impl Middleware for CorsPolicy {
+  fn handle(&self, context: &mut Context, next: Next) -> HttpResponse {
+    if self.is_preflight(&context.http_request) {
+      return self.preflight(&context.http_request, &context.request_id());
+    }
+
+    let mut http_response = next.run(context);
+    self.apply(&context.http_request, &mut http_response);
+    http_response
+  }
+}
+
+impl CorsPolicy {
+  pub fn allows_origin(&self, origin: &str) -> bool {
+    match &self.origins {
+      AllowedOrigins::Any => true,
+      AllowedOrigins::List(origins) => origins.iter().any(|pattern| CorsPolicy::matches_origin(pattern, origin)),
+    }
+  }
+
+  fn allows_header(&self, header: &str) -> bool {
+    self.headers.iter().any(|h| h == "*" || h.eq_ignore_ascii_case(header))
+  }
+
+  fn allow_origin(&self, origin: &str, http_response: &mut HttpResponse) {
+    // A LITERAL `*` IS NOT ACCEPTED BY BROWSERS TOGETHER WITH CREDENTIALS
+    match (&self.origins, self.credentials) {
+      (AllowedOrigins::Any, false) => {
+        http_response.header("Access-Control-Allow-Origin", "*");
+      },
+      _ => {
+        http_response.header("Access-Control-Allow-Origin", origin).header("Vary", "Origin");
+      },
+    }
+
+    if self.credentials {
+      http_response.header("Access-Control-Allow-Credentials", "true");
+    }
+  }
+
+  fn matches_origin(pattern: &str, origin: &str) -> bool {
+    match pattern.split_once("*.") {
+      Some((scheme, domain)) => origin
+        .strip_prefix(scheme)
+        .and_then(|host| host.strip_suffix(domain))
+        .is_some_and(|sub| sub.ends_with('.') && sub.len() > 1 && !sub.contains('/')),
+      None => pattern.eq_ignore_ascii_case(origin),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::library::testing::content;
+  use crate::parser::http_request::HttpRequest;
+
+  use super::{AllowedOrigins, CorsPolicy};
+
+  #[test]
+  fn cors_origin_match_test() {
+    assert!(CorsPolicy::matches_origin("http://localhost:7001", "http://localhost:7001"));
+    assert!(!CorsPolicy::matches_origin("http://localhost:7001", "http://localhost:7002"));
+    assert!(CorsPolicy::matches_origin("https://*.example.com", "https://app.example.com"));
+    assert!(CorsPolicy::matches_origin("https://*.example.com", "https://a.b.example.com"));
+    assert!(!CorsPolicy::matches_origin("https://*.example.com", "https://example.com"));
+    assert!(!CorsPolicy::matches_origin("https://*.example.com", "https://evil.com/.example.com"));
+  }
+
+  #[test]
+  fn cors_preflight_rejected_test() {
+    let policy = CorsPolicy { origins: AllowedOrigins::List(vec![String::from("http://localhost:7001")]), ..CorsPolicy::new() };
+    let lines = ["OPTIONS /files HTTP/1.1", "Origin: http://evil.test", "Access-Control-Request-Method: GET"];
+    let http_request = HttpRequest::construct(lines.iter().map(|l| l.to_string()).collect());
+    assert!(policy.is_preflight(&http_request));
+
+    let http_response = policy.preflight(&http_request, "req-1");
+    assert_eq!(http_response.status, "403");
+    let body = String::from_utf8(content(http_response)).unwrap();
+    assert!(body.contains("\"request_id\":\"req-1\""), "{}", body);
+    assert!(!body.contains("CORS Preflight Rejected"));
+  }
+}
@@ -0,0 +1,203 @@
+use std::{collections::{HashMap, HashSet}, io::{self, Read}};
+
+use crate::config::constants::DUPLICATE_BLOCK_SIZE;
+use crate::library::archive::ArchiveEntries;
+use crate::library::checksum::{hash, Algorithm, Hasher};
+use crate::library::events::{Events, FsEvent};
+use crate::library::tasks::Task;
+use crate::library::trash::Trash;
+use crate::library::vfs::{EntryKind, Vfs};
+
+#[derive(Debug)]
+pub struct DuplicateSet {
+  pub size: u64,
+  pub digest: String,
+  pub paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupeAction {
+  Link,
+  Trash,
+}
+
+impl DuplicateSet {
+  pub fn wasted(&self) -> u64 {
+    self.size * (self.paths.len() as u64).saturating_sub(1)
+  }
+}
+
+impl DedupeAction {
+  pub fn from(value: &str) -> Option<Self> {
+    match value {
+      "link" => Some(DedupeAction::Link),
+      "trash" => Some(DedupeAction::Trash),
+      _ => None,
+    }
+  }
+
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      DedupeAction::Link => "link",
+      DedupeAction::Trash => "trash",
+    }
+  }
+}
+
+// SIZE, THEN THE FIRST AND LAST BLOCK, THEN THE WHOLE CONTENT, EACH ROUND ONLY READS WHAT THE LAST ONE COULD NOT TELL APART
+pub fn find(vfs: &Vfs, root: &str, min_size: u64, task: &Task) -> io::Result<Vec<DuplicateSet>> {
+  let mut by_size: HashMap<u64, Vec<String>> = HashMap::new();
+  let mut inodes = HashSet::new();
+  for entry in ArchiveEntries::new(vfs.clone(), vec![(root.to_owned(), String::new())]) {
+    task.checkpoint()?;
+    if entry.stat.kind != EntryKind::File || entry.stat.size < min_size.max(1) {
+      continue;
+    }
+
+    // HARD LINKS OF ONE ANOTHER ALREADY SHARE THEIR DATA, ONLY THE FIRST ONE TAKES PART
+    if entry.stat.links > 1 && !inodes.insert((entry.stat.device, entry.stat.inode)) {
+      continue;
+    }
+    by_size.entry(entry.stat.size).or_default().push(entry.path);
+  }
+
+  let mut candidates = Vec::new();
+  for (size, group) in by_size.into_iter().filter(|(_, group)| group.len() > 1) {
+    candidates.extend(split(group, |path| partial_hash(vfs, path, size), task)?.into_iter().map(|group| (size, group)));
+  }
+
+  task.set_total(candidates.iter().map(|(size, group)| size * group.len() as u64).sum());
+  let mut sets = Vec::new();
+  for (size, group) in candidates {
+    for (digest, mut paths) in split_keyed(group, |path| hash(vfs.open_read(path, 0)?, Algorithm::Blake3, Some(task)), task)? {
+      paths.sort();
+      sets.push(DuplicateSet { size, digest, paths });
+    }
+  }
+
+  sets.sort_by(|a, b| b.wasted().cmp(&a.wasted()).then(a.paths.cmp(&b.paths)));
+  Ok(sets)
+}
+
+// THE CONTENT IS HASHED AGAIN RIGHT BEFORE, EITHER FILE MAY HAVE CHANGED SINCE THE SCAN. BOTH PATHS ARE ON THE SAME MOUNT
+pub fn resolve(vfs: &Vfs, keep: &str, duplicate: &str, action: DedupeAction, batch: &str, task: &Task) -> io::Result<u64> {
+  let ((backend, kept_path), (_, relative)) = match (vfs.resolve(keep), vfs.resolve(duplicate)) {
+    (Some(kept), Some(target)) if std::ptr::addr_eq(kept.0, target.0) => (kept, target),
+    _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "both files have to be on the same mount")),
+  };
+
+  let (kept, stat) = (backend.stat(&kept_path)?, backend.stat(&relative)?);
+  if kept.kind != EntryKind::File || stat.kind != EntryKind::File {
+    return Err(io::Error::new(io::ErrorKind::InvalidInput, "only regular files can be deduplicated"));
+  }
+
+  if (kept.device, kept.inode) == (stat.device, stat.inode) {
+    return Err(io::Error::new(io::ErrorKind::InvalidInput, "already the same file"));
+  }
+
+  let digest = |path: &str| hash(backend.open_read(path, 0)?, Algorithm::Blake3, Some(task));
+  if kept.size != stat.size || digest(&kept_path)? != digest(&relative)? {
+    return Err(io::Error::new(io::ErrorKind::InvalidData, "content differs from the kept file"));
+  }
+
+  match action {
+    DedupeAction::Link => backend.link(&kept_path, &relative)?,
+    // A FILE ON DISK IS MOVED ASIDE SO IT CAN BE PUT BACK BY HAND, ANYWHERE ELSE IT IS REMOVED
+    DedupeAction::Trash => match backend.local_path(&relative) {
+      Some(target) => {
+        Trash::store(&target, duplicate, batch)?;
+        Events::publish(FsEvent::Removed(target));
+      },
+      None => backend.remove(&relative)?,
+    },
+  }
+
+  // ANOTHER HARD LINK STILL HOLDS THE OLD DATA, SO NOTHING IS RECLAIMED
+  Ok(if stat.links == 1 { stat.size } else { 0 })
+}
+
+fn partial_hash(vfs: &Vfs, path: &str, size: u64) -> io::Result<String> {
+  let mut hasher = Hasher::new(Algorithm::Blake3);
+  let mut block = vec![0; DUPLICATE_BLOCK_SIZE.min(size) as usize];
+  vfs.open_read(path, 0)?.read_exact(&mut block)?;
+  hasher.update(&block);
+
+  if size > DUPLICATE_BLOCK_SIZE {
+    vfs.open_read(path, size - block.len() as u64)?.read_exact(&mut block)?;
+    hasher.update(&block);
+  }
+  Ok(hasher.finish())
+}
+
+fn split<F>(group: Vec<String>, key: F, task: &Task) -> io::Result<Vec<Vec<String>>>
+where
+  F: Fn(&str) -> io::Result<String>,
+{
+  Ok(split_keyed(group, key, task)?.into_iter().map(|(_, group)| group).collect())
+}
+
+// FILES THAT CANNOT BE READ ARE LEFT OUT, ONLY GROUPS THAT STILL HAVE TWO MEMBERS ARE KEPT
+fn split_keyed<F>(group: Vec<String>, key: F, task: &Task) -> io::Result<Vec<(String, Vec<String>)>>
+where
+  F: Fn(&str) -> io::Result<String>,
+{
+  let mut keyed: HashMap<String, Vec<String>> = HashMap::new();
+  for path in group {
+    task.checkpoint()?;
+    match key(&path) {
+      Ok(key) => keyed.entry(key).or_default().push(path),
+      Err(e) if e.kind() == io::ErrorKind::Interrupted => return Err(e),
+      Err(_) => continue,
+    }
+  }
+  Ok(keyed.into_iter().filter(|(_, group)| group.len() > 1).collect())
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{fs, io, os::unix::fs::MetadataExt};
+
+  use crate::library::testing::{in_task, local_vfs, TempDir};
+
+  use super::{find, resolve, DedupeAction};
+
+  fn tail(last: u8) -> Vec<u8> {
+    let mut content = vec![1; 20_000];
+    content.push(last);
+    content
+  }
+
+  #[test]
+  fn duplicate_find_test() {
+    let root = TempDir::new("duplicates");
+    root.write("a.bin", tail(1));
+    root.write("copies/a.bin", tail(1));
+    root.write("copies/b.bin", tail(2));
+    root.write("copies/c.bin", vec![1; 20_001].iter().enumerate().map(|(i, b)| if i == 10_000 { 9 } else { *b }).collect::<Vec<u8>>());
+    fs::hard_link(root.join("a.bin"), root.join("linked.bin")).unwrap();
+
+    let vfs = local_vfs(&root);
+    let sets = in_task("duplicates", move |task| find(&vfs, "/", 1, task)).unwrap();
+    assert_eq!(sets.len(), 1);
+    assert_eq!(sets[0].wasted(), 20_001);
+    assert_eq!(sets[0].paths.len(), 2);
+    assert!(sets[0].paths.contains(&String::from("/copies/a.bin")));
+  }
+
+  #[test]
+  fn duplicate_resolve_link_test() {
+    let root = TempDir::new("duplicates");
+    root.write("a.bin", tail(1));
+    root.write("copies/a.bin", tail(1));
+    root.write("copies/b.bin", tail(2));
+
+    let vfs = local_vfs(&root);
+    let outcome = in_task("duplicates", move |task| {
+      let mismatch = resolve(&vfs, "/a.bin", "/copies/b.bin", DedupeAction::Link, "test", task).map_err(|e| e.kind());
+      (mismatch, resolve(&vfs, "/a.bin", "/copies/a.bin", DedupeAction::Link, "test", task).map_err(|e| e.kind()))
+    });
+
+    assert_eq!(outcome, (Err(io::ErrorKind::InvalidData), Ok(20_001)));
+    assert_eq!(fs::metadata(root.join("copies/a.bin")).unwrap().ino(), fs::metadata(root.join("a.bin")).unwrap().ino());
+  }
+}
@@ -0,0 +1,27 @@
+use std::{path::PathBuf, sync::{mpsc, LazyLock, Mutex, PoisonError}};
+
+static SUBSCRIBERS: LazyLock<Mutex<Vec<mpsc::Sender<FsEvent>>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FsEvent {
+  Changed(PathBuf),
+  Removed(PathBuf),
+}
+
+pub struct Events;
+
+impl Events {
+  pub fn subscribe() -> mpsc::Receiver<FsEvent> {
+    let (sender, receiver) = mpsc::channel();
+    SUBSCRIBERS.lock().unwrap_or_else(PoisonError::into_inner).push(sender);
+    receiver
+  }
+
+  // SUBSCRIBERS THAT WENT AWAY ARE DROPPED ON THE NEXT PUBLISH
+  pub fn publish(event: FsEvent) {
+    SUBSCRIBERS
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+  }
+}
@@ -0,0 +1,364 @@
+use std::{collections::VecDeque, io::{self, Read}, sync::{Arc, LazyLock, Mutex, PoisonError}, thread, time::{Duration, SystemTime, UNIX_EPOCH}};
+
+use json_builder::{Json, JsonBuilder};
+use logger_main::Logger;
+
+use crate::config::constants::{ARCHIVE_JOB_HISTORY, ARCHIVE_MAX_EXTRACT_SIZE, ARCHIVE_MAX_JOBS, ARCHIVE_MAX_RATIO, ARCHIVE_RATIO_GRACE};
+use crate::config::utility::escape_json;
+use crate::enums::app_error::AppError;
+use crate::library::unpack::{member_path, ArchiveKind, Budget, Member, MemberKind, TarReader, ZipArchive};
+use crate::library::vfs::{Backend, EntryKind, Vfs};
+
+const MAX_REPORTED_SKIPS: usize = 1000;
+
+static JOBS: LazyLock<Mutex<VecDeque<Arc<ExtractJob>>>> = LazyLock::new(|| Mutex::new(VecDeque::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobState {
+  Running,
+  Completed,
+  Failed,
+}
+
+// BOTH PATHS ARE IN THE VFS, THE MEMBERS ARE WRITTEN THROUGH THE BACKEND MOUNTED AT THE TARGET
+pub struct ExtractRequest {
+  pub vfs: Vfs,
+  pub archive: String,
+  pub kind: ArchiveKind,
+  pub target: String,
+  pub members: Vec<String>,
+  pub overwrite: bool,
+}
+
+pub struct ExtractJob {
+  pub id: String,
+  pub archive: String,
+  pub target: String,
+  status: Mutex<JobStatus>,
+}
+
+struct JobStatus {
+  state: JobState,
+  extracted: u64,
+  bytes: u64,
+  skipped: Vec<(String, String)>,
+  skipped_total: u64,
+  error: Option<String>,
+  started: SystemTime,
+  finished: Option<SystemTime>,
+}
+
+impl ExtractJob {
+  // AT MOST ARCHIVE_MAX_JOBS RUN AT ONCE, FINISHED ONES ARE KEPT AROUND FOR POLLING
+  pub fn spawn(id: String, archive: &str, target: &str, request: ExtractRequest) -> Result<Arc<ExtractJob>, AppError> {
+    let job = Arc::new(ExtractJob {
+      id,
+      archive: archive.to_owned(),
+      target: target.to_owned(),
+      status: Mutex::new(JobStatus {
+        state: JobState::Running,
+        extracted: 0,
+        bytes: 0,
+        skipped: Vec::new(),
+        skipped_total: 0,
+        error: None,
+        started: SystemTime::now(),
+        finished: None,
+      }),
+    });
+
+    {
+      let mut jobs = JOBS.lock().unwrap_or_else(PoisonError::into_inner);
+      if jobs.iter().filter(|j| j.state() == JobState::Running).count() >= ARCHIVE_MAX_JOBS {
+        return Err(AppError::ServiceUnavailable(format!("{} extractions are already running", ARCHIVE_MAX_JOBS)));
+      }
+
+      jobs.push_back(job.clone());
+      while jobs.len() > ARCHIVE_JOB_HISTORY {
+        match jobs.iter().position(|j| j.state() != JobState::Running) {
+          Some(index) => drop(jobs.remove(index)),
+          None => break,
+        }
+      }
+    }
+
+    let worker = job.clone();
+    thread::spawn(move || {
+      let result = worker.run(&request);
+      let mut status = worker.lock();
+      status.finished = Some(SystemTime::now());
+      match result {
+        Ok(_) => status.state = JobState::Completed,
+        Err(e) => {
+          Logger::warn(format!("Extraction {} - Failed: {}", worker.id, e));
+          status.state = JobState::Failed;
+          status.error = Some(e.to_string());
+        },
+      }
+    });
+
+    Ok(job)
+  }
+
+  pub fn find(id: &str) -> Option<Arc<ExtractJob>> {
+    JOBS.lock().unwrap_or_else(PoisonError::into_inner).iter().find(|j| j.id == id).cloned()
+  }
+
+  pub fn state(&self) -> JobState {
+    self.lock().state
+  }
+
+  pub fn to_json(&self) -> String {
+    let status = self.lock();
+    let seconds = |time: SystemTime| time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+
+    let mut skipped = Json::array();
+    for (name, reason) in status.skipped.iter() {
+      let mut json_object = Json::object();
+      json_object.insert("name", escape_json(name));
+      json_object.insert("reason", escape_json(reason));
+      skipped.append(json_object);
+    }
+
+    let mut json_object = Json::object();
+    json_object.insert("id", escape_json(&self.id));
+    json_object.insert("archive", escape_json(&self.archive));
+    json_object.insert("target", escape_json(&self.target));
+    json_object.insert("state", match status.state {
+      JobState::Running => "running",
+      JobState::Completed => "completed",
+      JobState::Failed => "failed",
+    });
+    json_object.insert("extracted", status.extracted);
+    json_object.insert("bytes", status.bytes);
+    json_object.insert("skipped_count", status.skipped_total);
+    json_object.insert("skipped", skipped);
+    json_object.insert("started", seconds(status.started));
+    if let Some(finished) = status.finished {
+      json_object.insert("finished", seconds(finished));
+    }
+    if let Some(error) = status.error.as_deref() {
+      json_object.insert("error", escape_json(error));
+    }
+    Json::build(json_object)
+  }
+
+  fn lock(&self) -> std::sync::MutexGuard<'_, JobStatus> {
+    self.status.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+}
+
+impl ExtractJob {
+  fn run(&self, request: &ExtractRequest) -> io::Result<()> {
+    let budget = Budget::new(request.vfs.stat(&request.archive)?.size);
+    let (backend, target) = request.vfs.resolve(&request.target).ok_or_else(|| io::Error::new(io::ErrorKind::PermissionDenied, "target is outside of the storage root"))?;
+    backend.mkdir(&target)?;
+
+    match request.kind {
+      ArchiveKind::Zip => {
+        let archive = ZipArchive::open(&request.vfs, &request.archive)?;
+        let selected: Vec<&Member> = archive.members.iter().filter(|m| request.is_selected(&m.name)).collect();
+
+        let declared: u64 = selected.iter().map(|m| m.size).sum();
+        if declared > ARCHIVE_MAX_EXTRACT_SIZE {
+          return Err(io::Error::new(io::ErrorKind::InvalidData, format!("archive declares {} bytes, the limit is {}", declared, ARCHIVE_MAX_EXTRACT_SIZE)));
+        }
+
+        for member in selected {
+          let ratio = member.size / member.compressed.unwrap_or(0).max(1);
+          if member.kind == MemberKind::File && member.size > ARCHIVE_RATIO_GRACE && ratio > ARCHIVE_MAX_RATIO {
+            self.skip(&member.name, &format!("compression ratio {}x is above the limit", ratio));
+            continue;
+          }
+
+          let mut reader: Box<dyn Read + Send> = match member.kind {
+            MemberKind::File => match archive.reader(member, &budget) {
+              Ok(reader) => reader,
+              Err(e) if e.kind() == io::ErrorKind::Unsupported => {
+                self.skip(&member.name, &e.to_string());
+                continue;
+              },
+              Err(e) => return Err(e),
+            },
+            _ => Box::new(io::empty()),
+          };
+          self.write(request, backend, &target, member, &mut reader)?;
+        }
+      },
+      kind => {
+        let mut reader = TarReader::open(request.vfs.open_read(&request.archive, 0)?, kind, &budget)?;
+        while let Some(member) = reader.next_member()? {
+          if request.is_selected(&member.name) {
+            self.write(request, backend, &target, &member, &mut reader)?;
+          }
+        }
+      },
+    }
+    Ok(())
+  }
+
+  // ONLY THE MEMBER ITSELF IS SKIPPED FOR A BAD NAME, A LINK OR A PATH THE BACKEND REFUSES, I/O AND LIMIT ERRORS STOP THE JOB
+  fn write(&self, request: &ExtractRequest, backend: &dyn Backend, target: &str, member: &Member, reader: &mut dyn Read) -> io::Result<()> {
+    let Some(path) = member_path(&member.name) else {
+      self.skip(&member.name, "path escapes the target directory");
+      return Ok(());
+    };
+
+    let reason = match member.kind {
+      MemberKind::Symlink => Some("symlinks are not extracted"),
+      MemberKind::Other => Some("unsupported member type"),
+      _ => None,
+    };
+    if let Some(reason) = reason {
+      self.skip(&member.name, reason);
+      return Ok(());
+    }
+
+    let path = if target.is_empty() { path } else { format!("{}/{}", target, path) };
+    let reason = match backend.stat(&path).map(|s| s.kind) {
+      Ok(EntryKind::Directory) if member.kind == MemberKind::Directory => return Ok(()),
+      Ok(EntryKind::Directory | EntryKind::Other) => Some("a directory or link is in the way"),
+      Ok(_) if member.kind == MemberKind::Directory => Some("a file is in the way"),
+      Ok(_) if !request.overwrite => Some("file already exists"),
+      _ => None,
+    };
+    if let Some(reason) = reason {
+      self.skip(&member.name, reason);
+      return Ok(());
+    }
+
+    let written = match member.kind {
+      MemberKind::Directory => backend.mkdir(&path).and_then(|_| ExtractJob::restore(backend, &path, member)).map(|_| None),
+      _ => ExtractJob::store(backend, &path, reader).and_then(|written| ExtractJob::restore(backend, &path, member).map(|_| Some(written))),
+    };
+
+    let written = match written {
+      Ok(written) => written,
+      Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+        self.skip(&member.name, &e.to_string());
+        return Ok(());
+      },
+      Err(e) => return Err(io::Error::new(e.kind(), format!("{}: {}", member.name, e))),
+    };
+
+    if let Some(written) = written {
+      let mut status = self.lock();
+      status.extracted += 1;
+      status.bytes += written;
+    }
+    Ok(())
+  }
+
+  // NOTHING SHOWS UP AT THE PATH UNTIL THE WHOLE MEMBER HAS BEEN READ, A CORRUPT ONE LEAVES NO PARTIAL FILE BEHIND
+  fn store(backend: &dyn Backend, path: &str, reader: &mut dyn Read) -> io::Result<u64> {
+    backend.mkdir(path.rsplit_once('/').map_or("", |(parent, _)| parent))?;
+    let mut writer = backend.open_write(path)?;
+    let written = io::copy(reader, &mut writer)?;
+    writer.commit()?;
+    Ok(written)
+  }
+
+  // SETUID, SETGID AND STICKY BITS FROM AN UPLOADED ARCHIVE ARE NEVER HONOURED, A DIRECTORY STAYS WRITABLE FOR THE MEMBERS BELOW IT
+  fn restore(backend: &dyn Backend, path: &str, member: &Member) -> io::Result<()> {
+    match member.kind {
+      MemberKind::Directory => backend.set_mode(path, member.mode & 0o777 | 0o700),
+      _ => {
+        backend.set_modified(path, UNIX_EPOCH + Duration::from_secs(member.modified))?;
+        backend.set_mode(path, member.mode & 0o777)
+      },
+    }
+  }
+
+  fn skip(&self, name: &str, reason: &str) {
+    let mut status = self.lock();
+    status.skipped_total += 1;
+    if status.skipped.len() < MAX_REPORTED_SKIPS {
+      status.skipped.push((name.to_owned(), reason.to_owned()));
+    }
+  }
+}
+
+impl ExtractRequest {
+  // NO MEMBERS MEANS EVERYTHING, A DIRECTORY NAME SELECTS ALL OF ITS CONTENTS
+  fn is_selected(&self, name: &str) -> bool {
+    self.members.is_empty()
+      || self.members.iter().map(|m| m.trim_matches('/')).any(|m| name == m || name.strip_prefix(m).is_some_and(|rest| rest.starts_with('/')))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{fs, io::Read, os::unix::fs::PermissionsExt, thread, time::{Duration, UNIX_EPOCH}};
+
+  use crate::library::archive::{ArchiveEntries, ArchiveFormat, ZipStream};
+  use crate::library::testing::{local_vfs, TempDir};
+  use crate::library::unpack::{member_path, ArchiveKind};
+
+  use super::{ExtractJob, ExtractRequest, JobState};
+
+  #[test]
+  fn extract_job_test() {
+    let root = TempDir::new("extract");
+    root.write("source/docs/a.txt", "alpha");
+    root.write("source/b.txt", "beta");
+
+    let vfs = local_vfs(&root);
+    let mut zip = Vec::new();
+    ZipStream::new(ArchiveEntries::new(vfs.clone(), vec![(String::from("/source"), String::new())])).read_to_end(&mut zip).unwrap();
+    root.write("test.zip", zip);
+
+    let request = ExtractRequest {
+      vfs,
+      archive: String::from("/test.zip"),
+      kind: ArchiveKind::Zip,
+      target: String::from("/out"),
+      members: vec![String::from("docs")],
+      overwrite: false,
+    };
+    let job = ExtractJob::spawn(String::from("test-job"), "/test.zip", "/out", request).unwrap();
+    while job.state() == JobState::Running {
+      thread::sleep(Duration::from_millis(10));
+    }
+
+    let report = job.to_json();
+    assert!(report.contains("\"state\":\"completed\""), "{}", report);
+    assert_eq!(fs::read_to_string(root.join("out/docs/a.txt")).unwrap(), "alpha");
+    assert!(!root.join("out/b.txt").exists());
+  }
+
+  #[test]
+  fn extract_mode_test() {
+    let root = TempDir::new("extract-mode");
+    let script = root.write("source/run.sh", "#!/bin/sh");
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    let modified = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+    fs::File::open(&script).unwrap().set_modified(modified).unwrap();
+
+    let vfs = local_vfs(&root);
+    for (format, kind, name) in [(ArchiveFormat::Zip, ArchiveKind::Zip, "test.zip"), (ArchiveFormat::TarGz, ArchiveKind::TarGz, "test.tar.gz")] {
+      let mut archive = Vec::new();
+      format.stream(ArchiveEntries::new(vfs.clone(), vec![(String::from("/source"), String::new())])).read_to_end(&mut archive).unwrap();
+      root.write(name, archive);
+
+      let (archive, target) = (format!("/{}", name), format!("/{}.out", name));
+      let request = ExtractRequest { vfs: vfs.clone(), archive: archive.clone(), kind, target: target.clone(), members: Vec::new(), overwrite: false };
+      let job = ExtractJob::spawn(format!("mode-{}", name), &archive, &target, request).unwrap();
+      while job.state() == JobState::Running {
+        thread::sleep(Duration::from_millis(10));
+      }
+
+      let metadata = fs::metadata(root.join(format!("{}.out/run.sh", name))).unwrap();
+      assert_eq!(metadata.permissions().mode() & 0o7777, 0o755, "{}", name);
+      assert_eq!(metadata.modified().unwrap(), modified, "{}", name);
+    }
+  }
+
+  #[test]
+  fn member_path_test() {
+    assert_eq!(member_path("../etc/passwd"), None);
+    assert_eq!(member_path("/etc/passwd"), None);
+    assert_eq!(member_path("C:/windows"), None);
+    assert_eq!(member_path("a/../../b"), None);
+    assert_eq!(member_path("./a/b"), Some(String::from("a/b")));
+  }
+}
@@ -0,0 +1,108 @@
+use std::{fs, path::{Path, PathBuf}, sync::Arc};
+
+use globset::{GlobBuilder, GlobMatcher};
+
+pub struct IgnoreFile {
+  base: PathBuf,
+  rules: Vec<Rule>,
+}
+
+struct Rule {
+  matcher: GlobMatcher,
+  negated: bool,
+  dir_only: bool,
+  anchored: bool,
+}
+
+#[derive(Clone, Default)]
+pub struct IgnoreStack {
+  files: Vec<Arc<IgnoreFile>>,
+}
+
+impl IgnoreFile {
+  pub fn load(dir: &Path, name: &str) -> Option<IgnoreFile> {
+    let contents = fs::read_to_string(dir.join(name)).ok()?;
+    Some(IgnoreFile::parse(dir, &contents))
+  }
+
+  // GITIGNORE SEMANTICS: `#` COMMENTS, `!` RE-INCLUDES, A TRAILING `/` ONLY MATCHES DIRECTORIES
+  // AND A PATTERN WITH A `/` IN IT IS ANCHORED TO THE DIRECTORY HOLDING THE FILE
+  pub fn parse(base: &Path, contents: &str) -> IgnoreFile {
+    let rules = contents
+      .lines()
+      .map(|line| line.trim_end())
+      .filter(|line| !line.is_empty() && !line.starts_with('#'))
+      .filter_map(|line| {
+        let (negated, pattern) = match line.strip_prefix('!') {
+          Some(rest) => (true, rest),
+          None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+
+        let dir_only = pattern.ends_with('/');
+        let pattern = pattern.trim_end_matches('/');
+        let anchored = pattern.contains('/');
+        let pattern = pattern.trim_start_matches('/');
+        let matcher = GlobBuilder::new(pattern).literal_separator(true).build().ok()?.compile_matcher();
+
+        Some(Rule { matcher, negated, dir_only, anchored })
+      })
+      .collect();
+
+    IgnoreFile { base: base.to_path_buf(), rules }
+  }
+
+  // NONE WHEN NO RULE APPLIES, OTHERWISE THE LAST MATCHING RULE DECIDES
+  pub fn matches(&self, path: &Path, is_dir: bool) -> Option<bool> {
+    let relative = path.strip_prefix(&self.base).ok()?;
+    let name = path.file_name()?;
+
+    self.rules.iter().rev().find_map(|rule| {
+      if rule.dir_only && !is_dir {
+        return None;
+      }
+
+      let is_match = match rule.anchored {
+        true => rule.matcher.is_match(relative),
+        false => rule.matcher.is_match(name),
+      };
+      is_match.then_some(!rule.negated)
+    })
+  }
+}
+
+impl IgnoreStack {
+  pub fn with(&self, file: Option<IgnoreFile>) -> IgnoreStack {
+    let mut stack = self.clone();
+    stack.files.extend(file.map(Arc::new));
+    stack
+  }
+
+  // FILES CLOSER TO THE PATH OVERRIDE THE ONES ABOVE THEM
+  pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+    self.files.iter().rev().find_map(|file| file.matches(path, is_dir)).unwrap_or(false)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::Path;
+
+  use super::{IgnoreFile, IgnoreStack};
+
+  #[test]
+  fn ignore_rules_test() {
+    let root = Path::new("/data");
+    let stack = IgnoreStack::default()
+      .with(Some(IgnoreFile::parse(root, "# comment\n*.log\n!keep.log\nbuild/\n/dist\n")))
+      .with(Some(IgnoreFile::parse(&root.join("app"), "secret.txt\n")));
+
+    assert!(stack.is_ignored(&root.join("a/b/trace.log"), false));
+    assert!(!stack.is_ignored(&root.join("a/keep.log"), false));
+    assert!(stack.is_ignored(&root.join("a/build"), true));
+    assert!(!stack.is_ignored(&root.join("a/build"), false));
+    assert!(stack.is_ignored(&root.join("dist"), true));
+    assert!(!stack.is_ignored(&root.join("a/dist"), true));
+    assert!(stack.is_ignored(&root.join("app/secret.txt"), false));
+    assert!(!stack.is_ignored(&root.join("secret.txt"), false));
+  }
+}
@@ -0,0 +1,346 @@
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, fs, io::{self, BufRead, BufReader, BufWriter, Write}, path::Path};
+
+use crate::config::constants::INDEX_MAX_TERM_LENGTH;
+use crate::config::utility::decode_uri_component;
+
+const INDEX_HEADER: &str = "FMINDEX 1";
+
+pub struct Document {
+  pub path: String,
+  pub modified: u64,
+  pub size: u64,
+  terms: Vec<String>,
+}
+
+// EVERY OCCURRENCE KEEPS ITS TOKEN OFFSET FOR PHRASE MATCHING AND ITS LINE FOR REPORTING
+struct Posting {
+  doc: u32,
+  positions: Vec<(u32, u32)>,
+}
+
+pub struct Token {
+  pub term: String,
+  pub position: u32,
+  pub line: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Clause {
+  Term(String),
+  Prefix(String),
+  Phrase(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hit {
+  pub path: String,
+  pub line: u32,
+}
+
+#[derive(Default)]
+pub struct InvertedIndex {
+  docs: Vec<Option<Document>>,
+  paths: HashMap<String, u32>,
+  terms: BTreeMap<String, Vec<Posting>>,
+}
+
+pub fn tokenize(text: &str) -> Vec<Token> {
+  let mut tokens = Vec::new();
+  let mut position = 0;
+  for (number, line) in text.lines().enumerate() {
+    for word in line.split(|c: char| !c.is_alphanumeric() && c != '_').filter(|w| !w.is_empty()) {
+      if word.chars().count() > INDEX_MAX_TERM_LENGTH {
+        continue;
+      }
+
+      tokens.push(Token { term: word.to_lowercase(), position, line: number as u32 + 1 });
+      position += 1;
+    }
+  }
+  tokens
+}
+
+impl Clause {
+  // `"quoted words"` ARE PHRASES, A TRAILING `*` MAKES A PREFIX, EVERYTHING ELSE IS A TERM
+  pub fn parse(query: &str) -> Vec<Clause> {
+    let mut clauses = Vec::new();
+    for (index, segment) in query.split('"').enumerate() {
+      let is_quoted = index % 2 == 1;
+      let words: Vec<&str> = match is_quoted {
+        true => vec![segment],
+        false => segment.split_whitespace().collect(),
+      };
+
+      for word in words {
+        let is_prefix = !is_quoted && word.ends_with('*');
+        let terms: Vec<String> = tokenize(word).into_iter().map(|t| t.term).collect();
+        match (terms.len(), is_prefix) {
+          (0, _) => {},
+          (1, true) => clauses.push(Clause::Prefix(terms[0].clone())),
+          (1, false) => clauses.push(Clause::Term(terms[0].clone())),
+          _ => clauses.push(Clause::Phrase(terms)),
+        }
+      }
+    }
+    clauses
+  }
+}
+
+impl InvertedIndex {
+  pub fn len(&self) -> usize {
+    self.paths.len()
+  }
+
+  pub fn document(&self, path: &str) -> Option<&Document> {
+    self.paths.get(path).and_then(|&id| self.docs[id as usize].as_ref())
+  }
+
+  pub fn paths(&self) -> Vec<String> {
+    self.paths.keys().cloned().collect()
+  }
+
+  pub fn add(&mut self, path: &str, modified: u64, size: u64, text: &str) {
+    self.remove(path);
+
+    let mut grouped: BTreeMap<String, Vec<(u32, u32)>> = BTreeMap::new();
+    for token in tokenize(text) {
+      grouped.entry(token.term).or_default().push((token.position, token.line));
+    }
+
+    // IDS ONLY GROW, SO APPENDING KEEPS EVERY POSTING LIST SORTED BY DOCUMENT
+    let doc = self.docs.len() as u32;
+    let terms: Vec<String> = grouped.keys().cloned().collect();
+    for (term, positions) in grouped {
+      self.terms.entry(term).or_default().push(Posting { doc, positions });
+    }
+
+    self.docs.push(Some(Document { path: path.to_owned(), modified, size, terms }));
+    self.paths.insert(path.to_owned(), doc);
+  }
+
+  pub fn remove(&mut self, path: &str) -> bool {
+    let Some(doc) = self.paths.remove(path) else {
+      return false;
+    };
+
+    let Some(document) = self.docs[doc as usize].take() else {
+      return false;
+    };
+
+    for term in document.terms {
+      if let Some(postings) = self.terms.get_mut(&term) {
+        if let Ok(index) = postings.binary_search_by_key(&doc, |p| p.doc) {
+          postings.remove(index);
+        }
+        if postings.is_empty() {
+          self.terms.remove(&term);
+        }
+      }
+    }
+    true
+  }
+
+  pub fn remove_prefix(&mut self, directory: &str) -> usize {
+    let prefix = format!("{}/", directory.trim_end_matches('/'));
+    let paths: Vec<String> = self.paths.keys().filter(|p| p.starts_with(&prefix)).cloned().collect();
+    paths.iter().filter(|path| self.remove(path)).count()
+  }
+}
+
+impl InvertedIndex {
+  // EVERY CLAUSE HAS TO MATCH SOMEWHERE IN A FILE, EACH LINE WHERE ANY OF THEM MATCHES IS A HIT
+  pub fn search(&self, clauses: &[Clause], limit: usize) -> Vec<Hit> {
+    let mut matched: Option<HashMap<u32, BTreeSet<u32>>> = None;
+    for clause in clauses {
+      let lines = self.evaluate(clause);
+      matched = Some(match matched {
+        None => lines,
+        Some(previous) => previous
+          .into_iter()
+          .filter_map(|(doc, mut set)| {
+            let other = lines.get(&doc)?;
+            set.extend(other);
+            Some((doc, set))
+          })
+          .collect(),
+      });
+    }
+
+    let mut hits: Vec<Hit> = matched
+      .unwrap_or_default()
+      .into_iter()
+      .filter_map(|(doc, lines)| Some((self.docs[doc as usize].as_ref()?.path.clone(), lines)))
+      .flat_map(|(path, lines)| lines.into_iter().map(move |line| Hit { path: path.clone(), line }))
+      .collect();
+
+    hits.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+    hits.truncate(limit);
+    hits
+  }
+
+  fn evaluate(&self, clause: &Clause) -> HashMap<u32, BTreeSet<u32>> {
+    let mut lines: HashMap<u32, BTreeSet<u32>> = HashMap::new();
+    match clause {
+      Clause::Term(term) => {
+        for posting in self.terms.get(term).into_iter().flatten() {
+          lines.entry(posting.doc).or_default().extend(posting.positions.iter().map(|(_, line)| *line));
+        }
+      },
+      Clause::Prefix(prefix) => {
+        let postings = self.terms.range(prefix.clone()..).take_while(|(term, _)| term.starts_with(prefix.as_str()));
+        for posting in postings.flat_map(|(_, postings)| postings) {
+          lines.entry(posting.doc).or_default().extend(posting.positions.iter().map(|(_, line)| *line));
+        }
+      },
+      Clause::Phrase(words) => {
+        let lists: Option<Vec<&Vec<Posting>>> = words.iter().map(|word| self.terms.get(word)).collect();
+        let Some(lists) = lists else {
+          return lines;
+        };
+
+        for first in lists[0] {
+          let rest: Option<Vec<&Posting>> = lists[1..]
+            .iter()
+            .map(|list| list.binary_search_by_key(&first.doc, |p| p.doc).ok().map(|i| &list[i]))
+            .collect();
+          let Some(rest) = rest else {
+            continue;
+          };
+
+          for (position, line) in first.positions.iter() {
+            let is_phrase = rest.iter().enumerate().all(|(offset, posting)| {
+              let expected = position + offset as u32 + 1;
+              posting.positions.binary_search_by_key(&expected, |(p, _)| *p).is_ok()
+            });
+            if is_phrase {
+              lines.entry(first.doc).or_default().insert(*line);
+            }
+          }
+        }
+      },
+    }
+    lines
+  }
+}
+
+impl InvertedIndex {
+  // IDS ARE COMPACTED ON SAVE, REMOVED DOCUMENTS LEAVE NO GAPS IN THE FILE
+  pub fn save(&self, path: &Path) -> io::Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+      fs::create_dir_all(parent)?;
+    }
+
+    let temporary = path.with_extension("tmp");
+    let mut writer = BufWriter::new(fs::File::create(&temporary)?);
+    let mut remap: HashMap<u32, u32> = HashMap::new();
+    writeln!(writer, "{}", INDEX_HEADER)?;
+    for (doc, document) in self.docs.iter().enumerate().filter_map(|(i, d)| Some((i as u32, d.as_ref()?))) {
+      let id = remap.len() as u32;
+      remap.insert(doc, id);
+      let encoded = document.path.replace('%', "%25").replace('\t', "%09").replace('\n', "%0A").replace('\r', "%0D");
+      writeln!(writer, "D\t{}\t{}\t{}\t{}", id, document.modified, document.size, encoded)?;
+    }
+
+    for (term, postings) in self.terms.iter() {
+      let encoded: Vec<String> = postings
+        .iter()
+        .filter_map(|posting| {
+          let positions: Vec<String> = posting.positions.iter().map(|(p, l)| format!("{}@{}", p, l)).collect();
+          Some(format!("{}:{}", remap.get(&posting.doc)?, positions.join(",")))
+        })
+        .collect();
+      writeln!(writer, "T\t{}\t{}", term, encoded.join(" "))?;
+    }
+
+    writer.flush()?;
+    drop(writer);
+    fs::rename(temporary, path)
+  }
+
+  pub fn load(path: &Path) -> io::Result<InvertedIndex> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "corrupt content index");
+    let mut lines = BufReader::new(fs::File::open(path)?).lines();
+    if lines.next().transpose()?.as_deref() != Some(INDEX_HEADER) {
+      return Err(invalid());
+    }
+
+    let mut index = InvertedIndex::default();
+    for line in lines {
+      let line = line?;
+      let fields: Vec<&str> = line.split('\t').collect();
+      match fields.as_slice() {
+        ["D", id, modified, size, path] => {
+          if id.parse::<usize>().ok() != Some(index.docs.len()) {
+            return Err(invalid());
+          }
+
+          let path = decode_uri_component(path);
+          index.paths.insert(path.clone(), index.docs.len() as u32);
+          index.docs.push(Some(Document {
+            path,
+            modified: modified.parse().map_err(|_| invalid())?,
+            size: size.parse().map_err(|_| invalid())?,
+            terms: Vec::new(),
+          }));
+        },
+        ["T", term, encoded] => {
+          let mut postings = Vec::new();
+          for posting in encoded.split(' ').filter(|p| !p.is_empty()) {
+            let (doc, positions) = posting.split_once(':').ok_or_else(invalid)?;
+            let doc: u32 = doc.parse().map_err(|_| invalid())?;
+            let positions = positions
+              .split(',')
+              .map(|p| p.split_once('@').and_then(|(p, l)| Some((p.parse().ok()?, l.parse().ok()?))))
+              .collect::<Option<Vec<(u32, u32)>>>()
+              .ok_or_else(invalid)?;
+
+            let document = index.docs.get_mut(doc as usize).and_then(|d| d.as_mut()).ok_or_else(invalid)?;
+            document.terms.push(term.to_string());
+            postings.push(Posting { doc, positions });
+          }
+          index.terms.insert(term.to_string(), postings);
+        },
+        _ => return Err(invalid()),
+      }
+    }
+
+    Ok(index)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::library::testing::TempDir;
+
+  use super::{Clause, Hit, InvertedIndex};
+
+  fn index() -> InvertedIndex {
+    let mut index = InvertedIndex::default();
+    index.add("/logs/app.log", 1, 10, "server started\nconnection refused by upstream\nserver stopped");
+    index.add("/etc/app.conf", 2, 20, "upstream = 10.0.0.1\nretry_connection = true");
+    index
+  }
+
+  fn hit(path: &str, line: u32) -> Hit {
+    Hit { path: path.to_owned(), line }
+  }
+
+  #[test]
+  fn inverted_index_query_test() {
+    let index = index();
+    assert_eq!(index.search(&Clause::parse("\"connection refused\""), 10), vec![hit("/logs/app.log", 2)]);
+    assert_eq!(index.search(&Clause::parse("server stop*"), 10), vec![hit("/logs/app.log", 1), hit("/logs/app.log", 3)]);
+    assert_eq!(index.search(&Clause::parse("upstream"), 10), vec![hit("/etc/app.conf", 1), hit("/logs/app.log", 2)]);
+  }
+
+  #[test]
+  fn inverted_index_persist_test() {
+    let (root, mut index) = (TempDir::new("index"), index());
+    index.remove("/etc/app.conf");
+    index.save(&root.join("content.idx")).unwrap();
+
+    let loaded = InvertedIndex::load(&root.join("content.idx")).unwrap();
+    assert_eq!(loaded.len(), 1);
+    assert_eq!(loaded.search(&Clause::parse("upstream"), 10), vec![hit("/logs/app.log", 2)]);
+  }
+}
@@ -0,0 +1,195 @@
+use std::{collections::HashSet, fs, io::Read, path::{Path, PathBuf}, sync::{mpsc::{Receiver, RecvTimeoutError}, OnceLock, PoisonError, RwLock}, thread, time::{Duration, UNIX_EPOCH}};
+
+use logger_main::Logger;
+
+use crate::config::constants::{INDEX_MAX_FILE_SIZE, INDEX_PATH, INDEX_RESCAN_SECS, SEARCH_IGNORE_FILES, SEARCH_MAX_DEPTH, STORAGE_ROOT};
+use crate::library::chunks::{ChunkStore, Manifest};
+use crate::library::events::{Events, FsEvent};
+use crate::library::ignore::{IgnoreFile, IgnoreStack};
+use crate::library::index::{Clause, Hit, InvertedIndex};
+use crate::library::mime::Mime;
+
+static INDEXER: OnceLock<Indexer> = OnceLock::new();
+
+pub struct Indexer {
+  index: RwLock<InvertedIndex>,
+  root: PathBuf,
+  chunks: &'static ChunkStore,
+}
+
+impl Indexer {
+  // LOADS THE SAVED INDEX AND KEEPS IT CURRENT ON A BACKGROUND THREAD
+  pub fn start() {
+    let index = match InvertedIndex::load(Path::new(INDEX_PATH)) {
+      Ok(index) => index,
+      Err(e) => {
+        Logger::warn(format!("Content Index - Starting empty, could not load {}: {}", INDEX_PATH, e));
+        InvertedIndex::default()
+      },
+    };
+
+    let indexer = INDEXER.get_or_init(|| Indexer { index: RwLock::new(index), root: PathBuf::from(STORAGE_ROOT), chunks: ChunkStore::global() });
+    let events = Events::subscribe();
+    thread::spawn(move || indexer.run(events));
+  }
+
+  pub fn global() -> Option<&'static Indexer> {
+    INDEXER.get()
+  }
+
+  pub fn search(&self, clauses: &[Clause], limit: usize) -> Vec<Hit> {
+    self.index.read().unwrap_or_else(PoisonError::into_inner).search(clauses, limit)
+  }
+
+  pub fn len(&self) -> usize {
+    self.index.read().unwrap_or_else(PoisonError::into_inner).len()
+  }
+}
+
+impl Indexer {
+  fn run(&self, events: Receiver<FsEvent>) {
+    let mut is_dirty = self.reconcile();
+    loop {
+      if is_dirty {
+        match self.index.read().unwrap_or_else(PoisonError::into_inner).save(Path::new(INDEX_PATH)) {
+          Ok(_) => is_dirty = false,
+          Err(e) => Logger::warn(format!("Content Index - Failed to save {}: {}", INDEX_PATH, e)),
+        }
+      }
+
+      is_dirty |= match events.recv_timeout(Duration::from_secs(INDEX_RESCAN_SECS)) {
+        Ok(FsEvent::Changed(path)) => self.update(&path, &self.ignores_for(&path)),
+        Ok(FsEvent::Removed(path)) => self.remove(&path),
+        Err(RecvTimeoutError::Timeout) => self.reconcile(),
+        Err(RecvTimeoutError::Disconnected) => return,
+      };
+    }
+  }
+
+  // CHANGES THAT DID NOT GO THROUGH THE API ARE ONLY PICKED UP HERE
+  fn reconcile(&self) -> bool {
+    let mut seen = HashSet::new();
+    let mut is_dirty = false;
+    let mut directories = vec![(self.root.clone(), 0, IgnoreStack::default())];
+
+    while let Some((directory, depth, ignores)) = directories.pop() {
+      let ignores = SEARCH_IGNORE_FILES.iter().fold(ignores, |stack, name| stack.with(IgnoreFile::load(&directory, name)));
+      let Ok(entries) = fs::read_dir(&directory) else {
+        continue;
+      };
+
+      for path in entries.filter_map(|e| e.ok().map(|e| e.path())) {
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+          continue;
+        };
+
+        if ignores.is_ignored(&path, metadata.is_dir()) {
+          continue;
+        }
+
+        if metadata.is_dir() && depth + 1 < SEARCH_MAX_DEPTH {
+          directories.push((path, depth + 1, ignores.clone()));
+        } else if metadata.is_file() {
+          seen.insert(self.display_path(&path));
+          is_dirty |= self.update(&path, &ignores);
+        }
+      }
+    }
+
+    let mut index = self.index.write().unwrap_or_else(PoisonError::into_inner);
+    for path in index.paths().into_iter().filter(|p| !seen.contains(p)) {
+      is_dirty |= index.remove(&path);
+    }
+    is_dirty
+  }
+
+  // FILES ARE READ OUTSIDE THE LOCK, SEARCHES ONLY WAIT FOR THE POSTINGS TO BE SWAPPED IN
+  fn update(&self, path: &Path, ignores: &IgnoreStack) -> bool {
+    let display = self.display_path(path);
+    let metadata = match fs::symlink_metadata(path) {
+      Ok(metadata) if metadata.is_file() => metadata,
+      _ => return self.remove(path),
+    };
+
+    let manifest = self.chunks.manifest(path).ok().flatten();
+    let size = manifest.as_ref().map_or(metadata.len(), |m| m.size);
+    let modified = metadata.modified().ok().and_then(|m| m.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_secs()).unwrap_or(0);
+    let is_current = self
+      .index
+      .read()
+      .unwrap_or_else(PoisonError::into_inner)
+      .document(&display)
+      .is_some_and(|d| d.modified == modified && d.size == size);
+    if is_current {
+      return false;
+    }
+
+    let contents = match size <= INDEX_MAX_FILE_SIZE && !ignores.is_ignored(path, false) {
+      true => self.read(path, manifest.as_ref()).filter(|bytes| Mime::is_text(bytes)),
+      false => None,
+    };
+
+    let mut index = self.index.write().unwrap_or_else(PoisonError::into_inner);
+    match contents {
+      Some(bytes) => {
+        index.add(&display, modified, size, &String::from_utf8_lossy(&bytes));
+        true
+      },
+      None => index.remove(&display),
+    }
+  }
+
+  // A CHUNKED FILE ON DISK IS ITS MANIFEST, THE CONTENT COMES BACK FROM THE CHUNKS
+  fn read(&self, path: &Path, manifest: Option<&Manifest>) -> Option<Vec<u8>> {
+    let Some(manifest) = manifest else {
+      return fs::read(path).ok();
+    };
+
+    let mut bytes = Vec::with_capacity(manifest.size as usize);
+    self.chunks.reader(manifest, 0, manifest.size).read_to_end(&mut bytes).ok()?;
+    Some(bytes)
+  }
+
+  fn remove(&self, path: &Path) -> bool {
+    let display = self.display_path(path);
+    let mut index = self.index.write().unwrap_or_else(PoisonError::into_inner);
+    let is_removed = index.remove(&display);
+    index.remove_prefix(&display) > 0 || is_removed
+  }
+
+  fn ignores_for(&self, path: &Path) -> IgnoreStack {
+    let mut ancestors: Vec<&Path> = path.ancestors().skip(1).take_while(|a| a.starts_with(&self.root)).collect();
+    ancestors.reverse();
+    ancestors.into_iter().fold(IgnoreStack::default(), |stack, directory| {
+      SEARCH_IGNORE_FILES.iter().fold(stack, |stack, name| stack.with(IgnoreFile::load(directory, name)))
+    })
+  }
+
+  fn display_path(&self, path: &Path) -> String {
+    format!("/{}", path.strip_prefix(&self.root).map(|p| p.to_string_lossy().into_owned()).unwrap_or_default())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::RwLock;
+
+  use crate::library::{chunks::ChunkStore, ignore::IgnoreStack, index::{Clause, InvertedIndex}, testing::TempDir};
+
+  use super::Indexer;
+
+  #[test]
+  fn indexer_chunked_test() {
+    let root = TempDir::new("indexer");
+    let chunks = Box::leak(Box::new(ChunkStore::new(root.join("chunks"), root.join("storage"), &["/backups"])));
+    let text = "quarterly numbers\n".repeat(1000);
+    let target = root.join("storage/backups/notes.txt");
+    root.mkdir("storage/backups");
+    chunks.write(&target, text.as_bytes()).unwrap();
+
+    let indexer = Indexer { index: RwLock::new(InvertedIndex::default()), root: root.join("storage"), chunks };
+    assert!(indexer.update(&target, &IgnoreStack::default()));
+    assert_eq!(indexer.index.read().unwrap().document("/backups/notes.txt").map(|d| d.size), Some(text.len() as u64));
+    assert_eq!(indexer.search(&[Clause::Term(String::from("quarterly"))], 1).len(), 1);
+  }
+}
@@ -1 +1,40 @@
-pub type Job = Box<dyn FnOnce() + Send + 'static>;
\ No newline at end of file
+use std::{cmp::Ordering, time::Instant};
+
+use super::tp::Priority;
+
+pub type Job = Box<dyn FnOnce() + Send + 'static>;
+
+pub struct QueuedJob {
+  pub job: Job,
+  pub priority: Priority,
+  pub enqueued_at: Instant,
+  pub deadline: u64,
+  pub sequence: u64,
+}
+
+impl QueuedJob {
+  pub fn new(job: Job, priority: Priority) -> Self {
+    QueuedJob { job, priority, enqueued_at: Instant::now(), deadline: 0, sequence: 0 }
+  }
+}
+
+// BINARY HEAP IS A MAX HEAP, THE EARLIEST DEADLINE HAS TO COMPARE AS THE GREATEST
+impl Ord for QueuedJob {
+  fn cmp(&self, other: &Self) -> Ordering {
+    (other.deadline, other.sequence).cmp(&(self.deadline, self.sequence))
+  }
+}
+
+impl PartialOrd for QueuedJob {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl PartialEq for QueuedJob {
+  fn eq(&self, other: &Self) -> bool {
+    (self.deadline, self.sequence) == (other.deadline, other.sequence)
+  }
+}
+
+impl Eq for QueuedJob {}
@@ -0,0 +1,148 @@
+use std::{collections::BinaryHeap, sync::{mpsc::{RecvTimeoutError, TrySendError}, Condvar, Mutex, MutexGuard, PoisonError}, time::{Duration, Instant}};
+
+use super::job::QueuedJob;
+use super::tp::Priority;
+
+pub struct JobQueue {
+  inner: Mutex<QueueInner>,
+  available: Condvar,
+  space: Condvar,
+  capacity: usize,
+  limits: [usize; Priority::COUNT],
+  aging: Duration,
+  origin: Instant,
+}
+
+#[derive(Default)]
+struct QueueInner {
+  heaps: [BinaryHeap<QueuedJob>; Priority::COUNT],
+  running: [usize; Priority::COUNT],
+  len: usize,
+  sequence: u64,
+  closed: bool,
+}
+
+impl JobQueue {
+  pub fn new(capacity: usize, limits: [usize; Priority::COUNT], aging: Duration) -> Self {
+    JobQueue {
+      inner: Mutex::new(QueueInner::default()),
+      available: Condvar::new(),
+      space: Condvar::new(),
+      capacity: capacity.max(1),
+      limits,
+      aging,
+      origin: Instant::now(),
+    }
+  }
+
+  pub fn push(&self, mut queued: QueuedJob, block: bool) -> Result<(), TrySendError<QueuedJob>> {
+    let mut inner = self.lock();
+    while !inner.closed && inner.len >= self.capacity {
+      if !block {
+        return Err(TrySendError::Full(queued));
+      }
+      inner = self.space.wait(inner).unwrap_or_else(PoisonError::into_inner);
+    }
+
+    if inner.closed {
+      return Err(TrySendError::Disconnected(queued));
+    }
+
+    // AGING: A JOB IS DUE AT ITS ENQUEUE TIME MINUS A HEAD START PER PRIORITY LEVEL,
+    // SO A BULK JOB THAT HAS WAITED LONG ENOUGH OVERTAKES FRESH INTERACTIVE WORK
+    let head_start = self.aging.as_micros() as u64 * queued.priority.rank();
+    let enqueued = queued.enqueued_at.duration_since(self.origin).as_micros() as u64;
+    queued.deadline = (enqueued + Priority::COUNT as u64 * self.aging.as_micros() as u64) - head_start;
+    queued.sequence = inner.sequence;
+    inner.sequence += 1;
+    inner.len += 1;
+    inner.heaps[queued.priority.index()].push(queued);
+    drop(inner);
+
+    self.available.notify_one();
+    Ok(())
+  }
+
+  pub fn pop(&self, timeout: Duration) -> Result<QueuedJob, RecvTimeoutError> {
+    let started = Instant::now();
+    let mut inner = self.lock();
+    loop {
+      if let Some(queued) = self.take_next(&mut inner) {
+        drop(inner);
+        self.space.notify_one();
+        return Ok(queued);
+      }
+
+      if inner.closed && inner.len == 0 {
+        return Err(RecvTimeoutError::Disconnected);
+      }
+
+      let Some(remaining) = timeout.checked_sub(started.elapsed()) else {
+        return Err(RecvTimeoutError::Timeout);
+      };
+      inner = self.available.wait_timeout(inner, remaining).unwrap_or_else(PoisonError::into_inner).0;
+    }
+  }
+
+  // FREES THE CONCURRENCY SLOT TAKEN BY POP, A JOB OF THAT CLASS MAY HAVE BEEN HELD BACK BY IT
+  pub fn finish(&self, priority: Priority) {
+    let mut inner = self.lock();
+    inner.running[priority.index()] -= 1;
+    drop(inner);
+
+    self.available.notify_all();
+  }
+
+  pub fn close(&self) {
+    self.lock().closed = true;
+    self.available.notify_all();
+    self.space.notify_all();
+  }
+
+  pub fn running(&self) -> [usize; Priority::COUNT] {
+    self.lock().running
+  }
+
+  fn take_next(&self, inner: &mut QueueInner) -> Option<QueuedJob> {
+    let index = (0..Priority::COUNT)
+      .filter(|&i| inner.running[i] < self.limits[i])
+      .filter_map(|i| inner.heaps[i].peek().map(|queued| (i, queued)))
+      .max_by(|a, b| a.1.cmp(b.1))
+      .map(|(i, _)| i)?;
+
+    let queued = inner.heaps[index].pop()?;
+    inner.running[index] += 1;
+    inner.len -= 1;
+    Some(queued)
+  }
+
+  fn lock(&self) -> MutexGuard<'_, QueueInner> {
+    self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use super::JobQueue;
+  use crate::library::{job::QueuedJob, tp::Priority};
+
+  #[test]
+  fn job_queue_priority_aging_test() {
+    let queue = JobQueue::new(16, [4, 4, 1], Duration::from_millis(50));
+    queue.push(QueuedJob::new(Box::new(|| {}), Priority::Bulk), false).unwrap();
+    queue.push(QueuedJob::new(Box::new(|| {}), Priority::Bulk), false).unwrap();
+    queue.push(QueuedJob::new(Box::new(|| {}), Priority::Interactive), false).unwrap();
+
+    // INTERACTIVE WORK JUMPS THE QUEUE, THEN THE BULK CLASS IS HELD TO ONE RUNNING JOB
+    assert_eq!(queue.pop(Duration::ZERO).unwrap().priority, Priority::Interactive);
+    assert_eq!(queue.pop(Duration::ZERO).unwrap().priority, Priority::Bulk);
+    assert!(queue.pop(Duration::ZERO).is_err());
+    queue.finish(Priority::Bulk);
+
+    std::thread::sleep(Duration::from_millis(120));
+    queue.push(QueuedJob::new(Box::new(|| {}), Priority::Interactive), false).unwrap();
+    assert_eq!(queue.pop(Duration::ZERO).unwrap().priority, Priority::Bulk);
+  }
+}
@@ -0,0 +1,297 @@
+use std::{fs::{self, File, Metadata}, io::{self, Read, Seek, SeekFrom, Write}, os::unix::fs::{MetadataExt, PermissionsExt}, path::{Path, PathBuf}, sync::atomic::{AtomicU64, Ordering}, time::{SystemTime, UNIX_EPOCH}};
+
+use crate::library::chunks::ChunkStore;
+use crate::library::events::{Events, FsEvent};
+use crate::library::vfs::{Backend, DirEntry, EntryKind, Stat, WriteHandle};
+use crate::library::versions::{VersionReason, VersionStore};
+
+static STAGED: AtomicU64 = AtomicU64::new(0);
+
+// A DIRECTORY ON DISK. CHUNKED FILES ARE READ AND WRITTEN THROUGH THEIR MANIFESTS, OVERWRITES AND DELETES KEEP A VERSION
+pub struct LocalBackend {
+  root: PathBuf,
+  chunks: &'static ChunkStore,
+}
+
+// CHUNKED TARGETS ARE KEPT IN MEMORY UNTIL THE COMMIT, THE CHUNKER NEEDS THE WHOLE CONTENT
+struct LocalWriter {
+  chunks: &'static ChunkStore,
+  target: PathBuf,
+  staged: Option<(PathBuf, File)>,
+  buffer: Vec<u8>,
+}
+
+impl LocalBackend {
+  pub fn new(root: impl Into<PathBuf>) -> Self {
+    LocalBackend { root: root.into(), chunks: ChunkStore::global() }
+  }
+
+  // TESTS KEEP THEIR CHUNKS OUT OF THE CONFIGURED STORE
+  #[cfg(test)]
+  pub fn with_chunks(mut self, chunks: &'static ChunkStore) -> Self {
+    self.chunks = chunks;
+    self
+  }
+}
+
+impl Backend for LocalBackend {
+  fn stat(&self, path: &str) -> io::Result<Stat> {
+    let target = self.path(path);
+    Ok(self.describe(&target, &fs::metadata(&target)?))
+  }
+
+  fn list(&self, path: &str) -> io::Result<Vec<DirEntry>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(self.path(path))? {
+      let entry = entry?;
+      let stat = self.describe(&entry.path(), &entry.metadata()?);
+      entries.push(DirEntry { name: entry.file_name().to_string_lossy().into_owned(), stat });
+    }
+    Ok(entries)
+  }
+
+  fn open_read(&self, path: &str, offset: u64) -> io::Result<Box<dyn Read + Send>> {
+    let target = self.path(path);
+    let chunks = self.chunks;
+    if let Some(manifest) = chunks.manifest(&target)? {
+      return Ok(Box::new(chunks.reader(&manifest, offset, manifest.size.saturating_sub(offset))));
+    }
+
+    let mut file = File::open(&target)?;
+    file.seek(SeekFrom::Start(offset))?;
+    Ok(Box::new(file))
+  }
+
+  fn open_write(&self, path: &str) -> io::Result<Box<dyn WriteHandle + '_>> {
+    let target = self.writable(path)?;
+    if fs::metadata(&target).is_ok_and(|m| m.is_dir()) {
+      return Err(io::Error::new(io::ErrorKind::IsADirectory, format!("{} is a directory", path)));
+    }
+
+    let staged = match self.chunks.is_chunked(&target) {
+      true => None,
+      false => {
+        let staged = LocalBackend::staged(&target, "upload");
+        let file = File::create(&staged)?;
+        Some((staged, file))
+      },
+    };
+    Ok(Box::new(LocalWriter { chunks: self.chunks, target, staged, buffer: Vec::new() }))
+  }
+
+  // A CHUNKED FILE LEAVING ITS ROOT IS PUT BACK TOGETHER, ITS MANIFEST WOULD MEAN NOTHING ANYWHERE ELSE
+  fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+    let (source, destination) = (self.writable(from)?, self.writable(to)?);
+    let chunks = self.chunks;
+    let metadata = fs::symlink_metadata(&source)?;
+    if metadata.is_dir() && chunks.is_chunked(&source) && !chunks.is_chunked(&destination) {
+      return Err(io::Error::new(io::ErrorKind::Unsupported, format!("{} holds chunked files and cannot leave its root", from)));
+    }
+
+    VersionStore::global().snapshot(&destination, VersionReason::Overwrite)?;
+    match chunks.manifest(&source)? {
+      Some(manifest) if !chunks.is_chunked(&destination) => {
+        let staged = LocalBackend::staged(&destination, "move");
+        let copied = File::create(&staged).and_then(|mut file| io::copy(&mut chunks.reader(&manifest, 0, manifest.size), &mut file));
+        if let Err(e) = copied.and_then(|_| fs::rename(&staged, &destination)) {
+          let _ = fs::remove_file(&staged);
+          return Err(e);
+        }
+        fs::remove_file(&source)?;
+      },
+      _ => fs::rename(&source, &destination)?,
+    }
+
+    Events::publish(FsEvent::Removed(source));
+    Events::publish(FsEvent::Changed(destination));
+    Ok(())
+  }
+
+  fn remove(&self, path: &str) -> io::Result<()> {
+    let target = self.writable(path)?;
+    match fs::symlink_metadata(&target)?.is_dir() {
+      true => {
+        VersionStore::global().snapshot_tree(&target, VersionReason::Delete)?;
+        fs::remove_dir_all(&target)?
+      },
+      false => {
+        VersionStore::global().snapshot(&target, VersionReason::Delete)?;
+        fs::remove_file(&target)?
+      },
+    };
+    Events::publish(FsEvent::Removed(target));
+    Ok(())
+  }
+
+  fn mkdir(&self, path: &str) -> io::Result<()> {
+    fs::create_dir_all(self.writable(path)?)
+  }
+
+  // LINKED NEXT TO THE TARGET FIRST, THE RENAME SWAPS IT IN WITHOUT A MOMENT WHERE THE PATH IS MISSING.
+  // A CHUNKED FILE IS ITS MANIFEST, SO THE LINK SHARES THE CHUNKS TOO
+  fn link(&self, from: &str, to: &str) -> io::Result<()> {
+    let (source, target) = (self.path(from), self.writable(to)?);
+    let chunks = self.chunks;
+    if chunks.is_chunked(&source) != chunks.is_chunked(&target) {
+      return Err(io::Error::new(io::ErrorKind::Unsupported, format!("{} and {} are not stored the same way", from, to)));
+    }
+
+    VersionStore::global().snapshot(&target, VersionReason::Overwrite)?;
+    let staged = LocalBackend::staged(&target, "link");
+    fs::hard_link(&source, &staged)?;
+    if let Err(e) = fs::rename(&staged, &target) {
+      let _ = fs::remove_file(&staged);
+      return Err(e);
+    }
+
+    Events::publish(FsEvent::Changed(target));
+    Ok(())
+  }
+
+  fn local_path(&self, path: &str) -> Option<PathBuf> {
+    Some(self.path(path))
+  }
+
+  fn set_mode(&self, path: &str, mode: u32) -> io::Result<()> {
+    fs::set_permissions(self.writable(path)?, fs::Permissions::from_mode(mode))
+  }
+
+  // A CHUNKED FILE TAKES THE TIME ON ITS MANIFEST, THAT IS WHERE `stat` READS IT FROM
+  fn set_modified(&self, path: &str, modified: SystemTime) -> io::Result<()> {
+    let target = self.writable(path)?;
+    File::open(&target)?.set_modified(modified)?;
+    Events::publish(FsEvent::Changed(target));
+    Ok(())
+  }
+}
+
+impl LocalBackend {
+  fn path(&self, path: &str) -> PathBuf {
+    path.split('/').filter(|n| !n.is_empty()).fold(self.root.clone(), |target, name| target.join(name))
+  }
+
+  // A LINK ANYWHERE BETWEEN THE ROOT AND THE TARGET WOULD SEND A WRITE ELSEWHERE, THE TARGET ITSELF MAY BE ONE
+  fn writable(&self, path: &str) -> io::Result<PathBuf> {
+    let target = self.path(path);
+    let mut ancestors = target.ancestors().skip(1).take_while(|a| *a != self.root);
+    match ancestors.any(|a| fs::symlink_metadata(a).is_ok_and(|m| m.is_symlink())) {
+      true => Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("{} is reached through a symbolic link", path))),
+      false => Ok(target),
+    }
+  }
+
+  fn staged(target: &Path, purpose: &str) -> PathBuf {
+    let name = target.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    target.with_file_name(format!(".{}.{}.{}", name, STAGED.fetch_add(1, Ordering::Relaxed), purpose))
+  }
+
+  // A CHUNKED FILE REPORTS THE SIZE OF ITS CONTENT, NOT OF ITS MANIFEST. ITS CHUNKS MAY BE SHARED, SO THAT SIZE IS ALSO WHAT IT TAKES UP
+  fn describe(&self, target: &Path, metadata: &Metadata) -> Stat {
+    let kind = match metadata.file_type() {
+      t if t.is_dir() => EntryKind::Directory,
+      t if t.is_file() => EntryKind::File,
+      _ => EntryKind::Other,
+    };
+
+    let (size, allocated) = match kind {
+      EntryKind::File => self.chunks.manifest(target).ok().flatten().map_or((metadata.len(), metadata.blocks() * 512), |m| (m.size, m.size)),
+      _ => (metadata.len(), metadata.blocks() * 512),
+    };
+    Stat { kind, size, modified: metadata.modified().unwrap_or(UNIX_EPOCH), inode: metadata.ino(), mode: metadata.mode() & 0o7777, device: metadata.dev(), links: metadata.nlink(), allocated }
+  }
+}
+
+impl Write for LocalWriter {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    match self.staged.as_mut() {
+      Some((_, file)) => file.write(buf),
+      None => {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+      },
+    }
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    self.staged.as_mut().map_or(Ok(()), |(_, file)| file.flush())
+  }
+}
+
+impl WriteHandle for LocalWriter {
+  // THE NEW CONTENT IS RENAMED OVER THE OLD ONE, HARD LINKS TO THE OLD FILE KEEP WHAT THEY HAD.
+  // THE WRITE DOES NOT HAPPEN IF THE OLD CONTENT COULD NOT BE KEPT
+  fn commit(mut self: Box<Self>) -> io::Result<()> {
+    VersionStore::global().snapshot(&self.target, VersionReason::Overwrite)?;
+    match self.staged.take() {
+      Some((staged, file)) => {
+        drop(file);
+        if let Err(e) = fs::rename(&staged, &self.target) {
+          let _ = fs::remove_file(&staged);
+          return Err(e);
+        }
+      },
+      None => drop(self.chunks.write(&self.target, &self.buffer)?),
+    }
+
+    Events::publish(FsEvent::Changed(self.target.clone()));
+    Ok(())
+  }
+}
+
+impl Drop for LocalWriter {
+  fn drop(&mut self) {
+    if let Some((staged, _)) = self.staged.take() {
+      let _ = fs::remove_file(staged);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{fs, io::{Read, Write}};
+
+  use crate::library::testing::TempDir;
+  use crate::library::vfs::{Backend, EntryKind};
+
+  use super::LocalBackend;
+
+  fn write(backend: &LocalBackend, path: &str, content: &[u8]) {
+    let mut writer = backend.open_write(path).unwrap();
+    writer.write_all(content).unwrap();
+    writer.commit().unwrap();
+  }
+
+  #[test]
+  fn local_write_commit_test() {
+    let root = TempDir::new("local-backend");
+    let backend = LocalBackend::new(root.path());
+    backend.mkdir("docs").unwrap();
+    write(&backend, "docs/a.txt", b"first");
+    fs::hard_link(root.join("docs/a.txt"), root.join("linked.txt")).unwrap();
+
+    // THE OVERWRITE IS A NEW FILE, A HARD LINK TO THE OLD ONE KEEPS ITS CONTENT
+    write(&backend, "docs/a.txt", b"second");
+    assert_eq!(fs::read_to_string(root.join("linked.txt")).unwrap(), "first");
+    assert_eq!(fs::read_to_string(root.join("docs/a.txt")).unwrap(), "second");
+
+    drop(backend.open_write("docs/b.txt").unwrap());
+    let names: Vec<String> = backend.list("docs").unwrap().into_iter().map(|e| e.name).collect();
+    assert_eq!(names, vec![String::from("a.txt")]);
+  }
+
+  #[test]
+  fn local_rename_remove_test() {
+    let root = TempDir::new("local-backend");
+    let backend = LocalBackend::new(root.path());
+    root.write("docs/a.txt", "second");
+
+    backend.rename("docs/a.txt", "b.txt").unwrap();
+    let mut content = String::new();
+    backend.open_read("b.txt", 3).unwrap().read_to_string(&mut content).unwrap();
+    assert_eq!(content, "ond");
+    assert_eq!(backend.stat("").unwrap().kind, EntryKind::Directory);
+
+    backend.remove("docs").unwrap();
+    assert!(backend.stat("docs").is_err());
+  }
+}
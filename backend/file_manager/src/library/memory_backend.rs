@@ -0,0 +1,296 @@
+use std::{collections::BTreeMap, io::{self, Cursor, Read, Write}, path::PathBuf, sync::{atomic::{AtomicU64, Ordering}, Arc, PoisonError, RwLock, RwLockWriteGuard}, time::SystemTime};
+
+use crate::library::vfs::{Backend, DirEntry, EntryKind, Stat, WriteHandle};
+
+#[derive(Debug, Clone)]
+enum Node {
+  Directory { modified: SystemTime, inode: u64 },
+  File { data: Arc<[u8]>, modified: SystemTime, inode: u64 },
+}
+
+// EVERYTHING IS KEPT IN ONE ORDERED MAP, SO A DIRECTORY AND ALL OF ITS CHILDREN ARE ONE RANGE OF KEYS
+pub struct MemoryBackend {
+  nodes: RwLock<BTreeMap<String, Node>>,
+  inodes: AtomicU64,
+}
+
+struct MemoryWriter<'a> {
+  backend: &'a MemoryBackend,
+  path: String,
+  buffer: Vec<u8>,
+}
+
+impl Node {
+  fn stat(&self) -> Stat {
+    match self {
+      Node::Directory { modified, inode } => Stat { kind: EntryKind::Directory, size: 0, modified: *modified, inode: *inode, mode: 0o755, device: 0, links: 1, allocated: 0 },
+      Node::File { data, modified, inode } => {
+        Stat { kind: EntryKind::File, size: data.len() as u64, modified: *modified, inode: *inode, mode: 0o644, device: 0, links: 1, allocated: data.len() as u64 }
+      },
+    }
+  }
+}
+
+impl MemoryBackend {
+  pub fn new() -> Self {
+    let mut nodes = BTreeMap::new();
+    nodes.insert(String::new(), Node::Directory { modified: SystemTime::now(), inode: 1 });
+    MemoryBackend { nodes: RwLock::new(nodes), inodes: AtomicU64::new(2) }
+  }
+}
+
+impl Default for MemoryBackend {
+  fn default() -> Self {
+    MemoryBackend::new()
+  }
+}
+
+impl Backend for MemoryBackend {
+  fn stat(&self, path: &str) -> io::Result<Stat> {
+    let nodes = self.nodes.read().unwrap_or_else(PoisonError::into_inner);
+    nodes.get(path).map(Node::stat).ok_or_else(|| MemoryBackend::missing(path))
+  }
+
+  fn list(&self, path: &str) -> io::Result<Vec<DirEntry>> {
+    let nodes = self.nodes.read().unwrap_or_else(PoisonError::into_inner);
+    match nodes.get(path) {
+      Some(Node::Directory { .. }) => {},
+      Some(Node::File { .. }) => return Err(io::Error::new(io::ErrorKind::NotADirectory, format!("{} is not a directory", path))),
+      None => return Err(MemoryBackend::missing(path)),
+    }
+
+    let prefix = MemoryBackend::children_of(path);
+    Ok(nodes
+      .range(prefix.clone()..)
+      .take_while(|(key, _)| key.starts_with(&prefix))
+      .filter(|(key, _)| !key.is_empty() && !key[prefix.len()..].contains('/'))
+      .map(|(key, node)| DirEntry { name: key[prefix.len()..].to_owned(), stat: node.stat() })
+      .collect())
+  }
+
+  fn open_read(&self, path: &str, offset: u64) -> io::Result<Box<dyn Read + Send>> {
+    let nodes = self.nodes.read().unwrap_or_else(PoisonError::into_inner);
+    match nodes.get(path) {
+      Some(Node::File { data, .. }) => {
+        let mut cursor = Cursor::new(data.clone());
+        cursor.set_position(offset.min(data.len() as u64));
+        Ok(Box::new(cursor))
+      },
+      Some(Node::Directory { .. }) => Err(io::Error::new(io::ErrorKind::IsADirectory, format!("{} is a directory", path))),
+      None => Err(MemoryBackend::missing(path)),
+    }
+  }
+
+  fn open_write(&self, path: &str) -> io::Result<Box<dyn WriteHandle + '_>> {
+    MemoryBackend::check_writable(&self.nodes(), path)?;
+    Ok(Box::new(MemoryWriter { backend: self, path: path.to_owned(), buffer: Vec::new() }))
+  }
+
+  fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+    let mut nodes = self.nodes();
+    let source = nodes.get(from).cloned().ok_or_else(|| MemoryBackend::missing(from))?;
+    if from.is_empty() || to.is_empty() {
+      return Err(io::Error::new(io::ErrorKind::PermissionDenied, "the root cannot be moved"));
+    }
+    if to.starts_with(&MemoryBackend::children_of(from)) {
+      return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} cannot be moved inside itself", from)));
+    }
+
+    MemoryBackend::check_parent(&nodes, to)?;
+    match (&source, nodes.get(to)) {
+      (_, Some(Node::Directory { .. })) => return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", to))),
+      (Node::Directory { .. }, Some(Node::File { .. })) => return Err(io::Error::new(io::ErrorKind::NotADirectory, format!("{} is not a directory", to))),
+      _ => {},
+    }
+
+    let prefix = MemoryBackend::children_of(from);
+    let moved: Vec<String> = nodes.range(prefix.clone()..).map(|(key, _)| key).take_while(|key| key.starts_with(&prefix)).cloned().collect();
+    for key in moved {
+      if let Some(node) = nodes.remove(&key) {
+        nodes.insert(format!("{}/{}", to, &key[prefix.len()..]), node);
+      }
+    }
+
+    nodes.remove(from);
+    nodes.insert(to.to_owned(), source);
+    MemoryBackend::touch(&mut nodes, from);
+    MemoryBackend::touch(&mut nodes, to);
+    Ok(())
+  }
+
+  fn remove(&self, path: &str) -> io::Result<()> {
+    let mut nodes = self.nodes();
+    if path.is_empty() {
+      return Err(io::Error::new(io::ErrorKind::PermissionDenied, "the root cannot be removed"));
+    }
+    nodes.remove(path).ok_or_else(|| MemoryBackend::missing(path))?;
+
+    let prefix = MemoryBackend::children_of(path);
+    let removed: Vec<String> = nodes.range(prefix.clone()..).map(|(key, _)| key).take_while(|key| key.starts_with(&prefix)).cloned().collect();
+    for key in removed {
+      nodes.remove(&key);
+    }
+    MemoryBackend::touch(&mut nodes, path);
+    Ok(())
+  }
+
+  fn mkdir(&self, path: &str) -> io::Result<()> {
+    let mut nodes = self.nodes();
+    let mut current = String::new();
+    for name in path.split('/').filter(|n| !n.is_empty()) {
+      current = if current.is_empty() { name.to_owned() } else { format!("{}/{}", current, name) };
+      match nodes.get(&current) {
+        Some(Node::Directory { .. }) => continue,
+        Some(Node::File { .. }) => return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} is a file", current))),
+        None => {
+          let inode = self.inodes.fetch_add(1, Ordering::Relaxed);
+          nodes.insert(current.clone(), Node::Directory { modified: SystemTime::now(), inode });
+          MemoryBackend::touch(&mut nodes, &current);
+        },
+      }
+    }
+    Ok(())
+  }
+
+  fn link(&self, from: &str, _to: &str) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, format!("{} cannot be linked in memory", from)))
+  }
+
+  fn local_path(&self, _path: &str) -> Option<PathBuf> {
+    None
+  }
+
+  fn set_modified(&self, path: &str, time: SystemTime) -> io::Result<()> {
+    match self.nodes().get_mut(path).ok_or_else(|| MemoryBackend::missing(path))? {
+      Node::Directory { modified, .. } | Node::File { modified, .. } => *modified = time,
+    }
+    Ok(())
+  }
+}
+
+impl MemoryBackend {
+  fn nodes(&self) -> RwLockWriteGuard<'_, BTreeMap<String, Node>> {
+    self.nodes.write().unwrap_or_else(PoisonError::into_inner)
+  }
+
+  fn missing(path: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("{} does not exist", path))
+  }
+
+  fn parent(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(parent, _)| parent)
+  }
+
+  fn children_of(path: &str) -> String {
+    if path.is_empty() { String::new() } else { format!("{}/", path) }
+  }
+
+  fn check_parent(nodes: &BTreeMap<String, Node>, path: &str) -> io::Result<()> {
+    let parent = MemoryBackend::parent(path);
+    match nodes.get(parent) {
+      Some(Node::Directory { .. }) => Ok(()),
+      Some(Node::File { .. }) => Err(io::Error::new(io::ErrorKind::NotADirectory, format!("{} is not a directory", parent))),
+      None => Err(MemoryBackend::missing(parent)),
+    }
+  }
+
+  fn check_writable(nodes: &BTreeMap<String, Node>, path: &str) -> io::Result<()> {
+    MemoryBackend::check_parent(nodes, path)?;
+    match nodes.get(path) {
+      Some(Node::Directory { .. }) => Err(io::Error::new(io::ErrorKind::IsADirectory, format!("{} is a directory", path))),
+      _ => Ok(()),
+    }
+  }
+
+  // A DIRECTORY CHANGES WHEN AN ENTRY IN IT DOES, LIKE ON DISK
+  fn touch(nodes: &mut BTreeMap<String, Node>, path: &str) {
+    if let Some(Node::Directory { modified, .. }) = nodes.get_mut(MemoryBackend::parent(path)) {
+      *modified = SystemTime::now();
+    }
+  }
+}
+
+impl Write for MemoryWriter<'_> {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    self.buffer.extend_from_slice(buf);
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+impl WriteHandle for MemoryWriter<'_> {
+  // THE PARENT IS CHECKED AGAIN, IT MAY HAVE BEEN REMOVED WHILE THE CONTENT WAS WRITTEN
+  fn commit(self: Box<Self>) -> io::Result<()> {
+    let mut nodes = self.backend.nodes();
+    MemoryBackend::check_writable(&nodes, &self.path)?;
+    let inode = self.backend.inodes.fetch_add(1, Ordering::Relaxed);
+    nodes.insert(self.path.clone(), Node::File { data: Arc::from(self.buffer), modified: SystemTime::now(), inode });
+    MemoryBackend::touch(&mut nodes, &self.path);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::{Read, Write};
+
+  use crate::library::vfs::{Backend, EntryKind};
+
+  use super::MemoryBackend;
+
+  fn backend() -> MemoryBackend {
+    let backend = MemoryBackend::new();
+    backend.mkdir("docs/old").unwrap();
+    let mut writer = backend.open_write("docs/a.txt").unwrap();
+    writer.write_all(b"hello world").unwrap();
+    writer.commit().unwrap();
+    backend
+  }
+
+  #[test]
+  fn memory_write_commit_test() {
+    let backend = MemoryBackend::new();
+    assert!(backend.open_write("docs/a.txt").is_err());
+
+    backend.mkdir("docs").unwrap();
+    let mut writer = backend.open_write("docs/a.txt").unwrap();
+    writer.write_all(b"hello").unwrap();
+    assert!(backend.stat("docs/a.txt").is_err());
+    writer.commit().unwrap();
+    assert_eq!(backend.stat("docs/a.txt").unwrap().size, 5);
+
+    drop(backend.open_write("docs/b.txt").unwrap());
+    assert!(backend.stat("docs/b.txt").is_err());
+    assert!(backend.mkdir("docs/a.txt/nested").is_err());
+  }
+
+  #[test]
+  fn memory_read_list_test() {
+    let backend = backend();
+    let mut content = String::new();
+    backend.open_read("docs/a.txt", 6).unwrap().read_to_string(&mut content).unwrap();
+    assert_eq!(content, "world");
+    assert!(backend.open_read("docs", 0).is_err());
+
+    let names: Vec<(String, EntryKind)> = backend.list("docs").unwrap().into_iter().map(|e| (e.name, e.stat.kind)).collect();
+    assert_eq!(names, vec![(String::from("a.txt"), EntryKind::File), (String::from("old"), EntryKind::Directory)]);
+    assert!(backend.list("docs/a.txt").is_err());
+  }
+
+  #[test]
+  fn memory_rename_remove_test() {
+    let backend = backend();
+    backend.rename("docs", "archive").unwrap();
+    assert!(backend.stat("archive/a.txt").is_ok());
+    assert!(backend.stat("docs/a.txt").is_err());
+    assert!(backend.rename("archive", "archive/old/inner").is_err());
+
+    backend.remove("archive").unwrap();
+    assert!(backend.stat("archive/old").is_err());
+    assert_eq!(backend.list("").unwrap(), Vec::new());
+    assert!(backend.remove("").is_err());
+  }
+}
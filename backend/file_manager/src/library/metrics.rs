@@ -0,0 +1,208 @@
+use std::{collections::BTreeMap, fmt::Write as _, io::{self, Write}, sync::{atomic::{AtomicI64, AtomicU64, Ordering}, LazyLock, Mutex, PoisonError}, time::Duration};
+
+use crate::config::constants::METRICS_LATENCY_BUCKETS;
+use crate::library::tp::PoolStats;
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+pub struct Metrics {
+  requests: Mutex<BTreeMap<(String, String, String), u64>>,
+  latency: Mutex<BTreeMap<(String, String), Histogram>>,
+  fs_errors: Mutex<BTreeMap<String, u64>>,
+  pool: Mutex<Option<PoolStats>>,
+  bytes_in: AtomicU64,
+  bytes_out: AtomicU64,
+  connections: AtomicI64,
+}
+
+#[derive(Default)]
+struct Histogram {
+  counts: Vec<u64>,
+  sum: f64,
+  count: u64,
+}
+
+pub struct ConnectionGuard;
+
+pub struct CountingWriter<'a, W: Write> {
+  inner: &'a mut W,
+  pub written: u64,
+}
+
+impl Metrics {
+  pub fn new() -> Self {
+    Metrics {
+      requests: Mutex::new(BTreeMap::new()),
+      latency: Mutex::new(BTreeMap::new()),
+      fs_errors: Mutex::new(BTreeMap::new()),
+      pool: Mutex::new(None),
+      bytes_in: AtomicU64::new(0),
+      bytes_out: AtomicU64::new(0),
+      connections: AtomicI64::new(0),
+    }
+  }
+
+  pub fn global() -> &'static Metrics {
+    &METRICS
+  }
+}
+
+impl Metrics {
+  pub fn observe_request(&self, route: &str, method: &str, status: &str, elapsed: Duration, bytes_in: usize) {
+    let key = (route.to_owned(), method.to_owned(), status.to_owned());
+    *self.requests.lock().unwrap_or_else(PoisonError::into_inner).entry(key).or_default() += 1;
+
+    let seconds = elapsed.as_secs_f64();
+    let mut latency = self.latency.lock().unwrap_or_else(PoisonError::into_inner);
+    let histogram = latency.entry((route.to_owned(), method.to_owned())).or_default();
+    histogram.counts.resize(METRICS_LATENCY_BUCKETS.len(), 0);
+    if let Some(index) = METRICS_LATENCY_BUCKETS.iter().position(|bound| seconds <= *bound) {
+      histogram.counts[index] += 1;
+    }
+    histogram.sum += seconds;
+    histogram.count += 1;
+
+    self.bytes_in.fetch_add(bytes_in as u64, Ordering::Relaxed);
+  }
+
+  pub fn observe_bytes_out(&self, bytes: u64) {
+    self.bytes_out.fetch_add(bytes, Ordering::Relaxed);
+  }
+
+  pub fn observe_fs_error(&self, error: &io::Error) {
+    let kind = format!("{:?}", error.kind());
+    *self.fs_errors.lock().unwrap_or_else(PoisonError::into_inner).entry(kind).or_default() += 1;
+  }
+
+  pub fn observe_pool(&self, stats: PoolStats) {
+    *self.pool.lock().unwrap_or_else(PoisonError::into_inner) = Some(stats);
+  }
+
+  pub fn pool(&self) -> Option<PoolStats> {
+    *self.pool.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+
+  pub fn connection(&self) -> ConnectionGuard {
+    self.connections.fetch_add(1, Ordering::Relaxed);
+    ConnectionGuard
+  }
+}
+
+impl Metrics {
+  // PROMETHEUS TEXT EXPOSITION FORMAT 0.0.4
+  pub fn render(&self) -> String {
+    let mut out = String::new();
+
+    Metrics::family(&mut out, "file_manager_http_requests_total", "counter", "HTTP requests by route, method and status");
+    for ((route, method, status), value) in self.requests.lock().unwrap_or_else(PoisonError::into_inner).iter() {
+      let labels = Metrics::labels(&[("route", route), ("method", method), ("status", status)]);
+      let _ = writeln!(out, "file_manager_http_requests_total{} {}", labels, value);
+    }
+
+    Metrics::family(&mut out, "file_manager_http_request_duration_seconds", "histogram", "Time spent handling a request");
+    for ((route, method), histogram) in self.latency.lock().unwrap_or_else(PoisonError::into_inner).iter() {
+      let mut cumulative = 0;
+      for (bound, count) in METRICS_LATENCY_BUCKETS.iter().zip(histogram.counts.iter()) {
+        cumulative += count;
+        let labels = Metrics::labels(&[("route", route), ("method", method), ("le", &bound.to_string())]);
+        let _ = writeln!(out, "file_manager_http_request_duration_seconds_bucket{} {}", labels, cumulative);
+      }
+
+      let labels = Metrics::labels(&[("route", route), ("method", method), ("le", "+Inf")]);
+      let _ = writeln!(out, "file_manager_http_request_duration_seconds_bucket{} {}", labels, histogram.count);
+      let labels = Metrics::labels(&[("route", route), ("method", method)]);
+      let _ = writeln!(out, "file_manager_http_request_duration_seconds_sum{} {}", labels, histogram.sum);
+      let _ = writeln!(out, "file_manager_http_request_duration_seconds_count{} {}", labels, histogram.count);
+    }
+
+    Metrics::family(&mut out, "file_manager_http_request_bytes_total", "counter", "Request body bytes received");
+    let _ = writeln!(out, "file_manager_http_request_bytes_total {}", self.bytes_in.load(Ordering::Relaxed));
+    Metrics::family(&mut out, "file_manager_http_response_bytes_total", "counter", "Response bytes written to clients");
+    let _ = writeln!(out, "file_manager_http_response_bytes_total {}", self.bytes_out.load(Ordering::Relaxed));
+    Metrics::family(&mut out, "file_manager_active_connections", "gauge", "Connections accepted and not yet answered");
+    let _ = writeln!(out, "file_manager_active_connections {}", self.connections.load(Ordering::Relaxed));
+
+    Metrics::family(&mut out, "file_manager_filesystem_errors_total", "counter", "Filesystem operations that failed, by error kind");
+    for (kind, value) in self.fs_errors.lock().unwrap_or_else(PoisonError::into_inner).iter() {
+      let _ = writeln!(out, "file_manager_filesystem_errors_total{} {}", Metrics::labels(&[("kind", kind)]), value);
+    }
+
+    if let Some(stats) = self.pool() {
+      Metrics::family(&mut out, "file_manager_thread_pool_workers", "gauge", "Thread pool workers by state");
+      let _ = writeln!(out, "file_manager_thread_pool_workers{{state=\"busy\"}} {}", stats.busy);
+      let _ = writeln!(out, "file_manager_thread_pool_workers{{state=\"idle\"}} {}", stats.idle);
+      Metrics::family(&mut out, "file_manager_thread_pool_queue_depth", "gauge", "Jobs waiting for a worker");
+      let _ = writeln!(out, "file_manager_thread_pool_queue_depth {}", stats.queued);
+      Metrics::family(&mut out, "file_manager_thread_pool_queue_capacity", "gauge", "Maximum number of waiting jobs");
+      let _ = writeln!(out, "file_manager_thread_pool_queue_capacity {}", stats.queue_depth);
+      Metrics::family(&mut out, "file_manager_thread_pool_jobs_total", "counter", "Thread pool jobs by final state");
+      let _ = writeln!(out, "file_manager_thread_pool_jobs_total{{state=\"completed\"}} {}", stats.completed);
+      let _ = writeln!(out, "file_manager_thread_pool_jobs_total{{state=\"panicked\"}} {}", stats.panicked);
+      let _ = writeln!(out, "file_manager_thread_pool_jobs_total{{state=\"rejected\"}} {}", stats.rejected);
+      Metrics::family(&mut out, "file_manager_thread_pool_wait_seconds_max", "gauge", "Longest time a job waited in the queue");
+      let _ = writeln!(out, "file_manager_thread_pool_wait_seconds_max {}", stats.wait_max_micros as f64 / 1_000_000.0);
+    }
+
+    out
+  }
+
+  fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+  }
+
+  fn labels(pairs: &[(&str, &str)]) -> String {
+    let pairs: Vec<String> = pairs
+      .iter()
+      .map(|(k, v)| format!("{}=\"{}\"", k, v.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")))
+      .collect();
+    format!("{{{}}}", pairs.join(","))
+  }
+}
+
+impl Drop for ConnectionGuard {
+  fn drop(&mut self) {
+    METRICS.connections.fetch_sub(1, Ordering::Relaxed);
+  }
+}
+
+impl<'a, W: Write> CountingWriter<'a, W> {
+  pub fn new(inner: &'a mut W) -> Self {
+    CountingWriter { inner, written: 0 }
+  }
+}
+
+impl<W: Write> Write for CountingWriter<'_, W> {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    let written = self.inner.write(buf)?;
+    self.written += written as u64;
+    Ok(written)
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    self.inner.flush()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use super::Metrics;
+
+  #[test]
+  fn metrics_render_test() {
+    let metrics = Metrics::new();
+    metrics.observe_request("/files", "GET", "200", Duration::from_millis(3), 0);
+    metrics.observe_request("/files", "GET", "200", Duration::from_secs(60), 10);
+    metrics.observe_fs_error(&std::io::Error::from(std::io::ErrorKind::NotFound));
+
+    let text = metrics.render();
+    assert!(text.contains("# TYPE file_manager_http_requests_total counter\n"));
+    assert!(text.contains("file_manager_http_requests_total{route=\"/files\",method=\"GET\",status=\"200\"} 2\n"));
+    assert!(text.contains("file_manager_http_request_duration_seconds_bucket{route=\"/files\",method=\"GET\",le=\"0.005\"} 1\n"));
+    assert!(text.contains("file_manager_http_request_duration_seconds_bucket{route=\"/files\",method=\"GET\",le=\"+Inf\"} 2\n"));
+    assert!(text.contains("file_manager_http_request_bytes_total 10\n"));
+    assert!(text.contains("file_manager_filesystem_errors_total{kind=\"NotFound\"} 1\n"));
+  }
+}
@@ -0,0 +1,182 @@
+use std::{fs::File, io::{self, Read}, path::Path};
+
+pub const DEFAULT_MIME_TYPE: &str = "application/octet-stream";
+pub const TEXT_MIME_TYPE: &str = "text/plain; charset=utf-8";
+
+const SNIFF_LENGTH: usize = 512;
+// SHORTER SIGNATURES SHOW UP BY CHANCE AT THE START OF PLAIN FILES, THEY ONLY DECIDE WHEN THE EXTENSION DOES NOT
+const STRONG_SIGNATURE_LENGTH: usize = 4;
+
+// A BROWSER RUNS SCRIPTS IN THESE, SERVED FROM OUR ORIGIN THEY COULD ACT AS THE SIGNED-IN USER
+const ACTIVE_MIME_TYPES: &[&str] = &["text/html", "application/xhtml+xml", "image/svg+xml", "application/xml", "text/xml", "text/javascript", "application/javascript"];
+
+const EXTENSION_TABLE: &[(&str, &str)] = &[
+  // TEXT
+  ("txt", "text/plain; charset=utf-8"), ("log", "text/plain; charset=utf-8"), ("md", "text/markdown; charset=utf-8"),
+  ("csv", "text/csv; charset=utf-8"), ("html", "text/html; charset=utf-8"), ("htm", "text/html; charset=utf-8"),
+  ("css", "text/css; charset=utf-8"), ("scss", "text/x-scss; charset=utf-8"), ("xml", "application/xml"),
+  ("js", "text/javascript; charset=utf-8"), ("mjs", "text/javascript; charset=utf-8"), ("json", "application/json"),
+  ("map", "application/json"), ("yaml", "application/yaml"), ("yml", "application/yaml"), ("toml", "application/toml"),
+  ("rs", "text/x-rust; charset=utf-8"), ("sh", "application/x-sh"), ("svg", "image/svg+xml"),
+  // IMAGE
+  ("png", "image/png"), ("jpg", "image/jpeg"), ("jpeg", "image/jpeg"), ("gif", "image/gif"), ("webp", "image/webp"),
+  ("bmp", "image/bmp"), ("ico", "image/x-icon"), ("avif", "image/avif"),
+  // AUDIO / VIDEO
+  ("mp3", "audio/mpeg"), ("ogg", "audio/ogg"), ("flac", "audio/flac"), ("wav", "audio/wav"),
+  ("mp4", "video/mp4"), ("webm", "video/webm"), ("mkv", "video/x-matroska"),
+  // FONT
+  ("woff", "font/woff"), ("woff2", "font/woff2"), ("ttf", "font/ttf"), ("otf", "font/otf"),
+  // ARCHIVE
+  ("zip", "application/zip"), ("gz", "application/gzip"), ("tgz", "application/gzip"), ("bz2", "application/x-bzip2"),
+  ("xz", "application/x-xz"), ("7z", "application/x-7z-compressed"), ("tar", "application/x-tar"),
+  ("jar", "application/java-archive"), ("epub", "application/epub+zip"),
+  ("docx", "application/vnd.openxmlformats-officedocument.wordprocessingml.document"),
+  ("xlsx", "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+  ("pptx", "application/vnd.openxmlformats-officedocument.presentationml.presentation"),
+  // OTHER
+  ("pdf", "application/pdf"), ("wasm", "application/wasm"), ("sqlite", "application/vnd.sqlite3"),
+];
+
+const MAGIC_TABLE: &[(usize, &[u8], &str)] = &[
+  (0, b"%PDF-", "application/pdf"),
+  (0, b"\x89PNG\r\n\x1a\n", "image/png"),
+  (0, b"\xFF\xD8\xFF", "image/jpeg"),
+  (0, b"GIF87a", "image/gif"),
+  (0, b"GIF89a", "image/gif"),
+  (0, b"BM", "image/bmp"),
+  (0, b"\x00\x00\x01\x00", "image/x-icon"),
+  (0, b"PK\x03\x04", "application/zip"),
+  (0, b"PK\x05\x06", "application/zip"),
+  (0, b"\x1F\x8B", "application/gzip"),
+  (0, b"BZh", "application/x-bzip2"),
+  (0, b"\xFD7zXZ\x00", "application/x-xz"),
+  (0, b"7z\xBC\xAF\x27\x1C", "application/x-7z-compressed"),
+  (257, b"ustar", "application/x-tar"),
+  (0, b"\x7FELF", "application/x-executable"),
+  (0, b"MZ", "application/vnd.microsoft.portable-executable"),
+  (0, b"\x00asm", "application/wasm"),
+  (0, b"ID3", "audio/mpeg"),
+  (0, b"OggS", "audio/ogg"),
+  (0, b"fLaC", "audio/flac"),
+  (0, b"\x1A\x45\xDF\xA3", "video/webm"),
+  (0, b"wOFF", "font/woff"),
+  (0, b"wOF2", "font/woff2"),
+  (0, b"SQLite format 3\x00", "application/vnd.sqlite3"),
+];
+
+pub struct Mime;
+
+impl Mime {
+  pub fn detect(path: &Path) -> String {
+    match File::open(path) {
+      Ok(file) => Mime::detect_from(path, file),
+      Err(_) => Mime::detect_from(path, io::empty()),
+    }
+  }
+
+  // FOR CONTENT THAT IS NOT STORED AS IT IS AT `path`, LIKE A CHUNKED FILE
+  pub fn detect_from(path: &Path, reader: impl Read) -> String {
+    let mut sample = Vec::with_capacity(SNIFF_LENGTH);
+    let _ = reader.take(SNIFF_LENGTH as u64).read_to_end(&mut sample);
+    Mime::resolve(Mime::from_extension(path), &sample).to_owned()
+  }
+
+  pub fn from_extension(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    EXTENSION_TABLE.iter().find(|(e, _)| *e == extension).map(|(_, m)| *m)
+  }
+
+  pub fn is_active(mime: &str) -> bool {
+    let essence = mime.split(';').next().unwrap_or_default().trim();
+    ACTIVE_MIME_TYPES.iter().any(|active| essence.eq_ignore_ascii_case(active))
+  }
+
+  fn resolve(by_extension: Option<&'static str>, sample: &[u8]) -> &'static str {
+    match (by_extension, Mime::signature(sample)) {
+      // ZIP AND TEXT ARE CONTAINERS FOR MANY FORMATS, THE EXTENSION IS MORE SPECIFIC THERE
+      (Some(ext), Some(("application/zip", _))) if ext.ends_with("+zip") || ext.starts_with("application/vnd.") || ext == "application/java-archive" => ext,
+      (Some(ext), Some((TEXT_MIME_TYPE, _))) => ext,
+      (Some(ext), Some((_, false))) => ext,
+      (_, Some((sniffed, _))) => sniffed,
+      (Some(ext), None) => ext,
+      (None, None) if sample.is_empty() => TEXT_MIME_TYPE,
+      (None, None) => DEFAULT_MIME_TYPE,
+    }
+  }
+
+  // THE SNIFFED TYPE AND WHETHER THE MATCH IS STRONG ENOUGH TO OVERRIDE THE EXTENSION
+  fn signature(sample: &[u8]) -> Option<(&'static str, bool)> {
+    let magic = MAGIC_TABLE.iter().find(|(offset, signature, _)| {
+      sample.len() >= offset + signature.len() && &sample[*offset..offset + signature.len()] == *signature
+    });
+
+    if let Some((_, signature, mime)) = magic {
+      return Some((*mime, signature.len() >= STRONG_SIGNATURE_LENGTH));
+    }
+
+    if sample.len() >= 12 && &sample[0..4] == b"RIFF" {
+      return match &sample[8..12] {
+        b"WEBP" => Some(("image/webp", true)),
+        b"WAVE" => Some(("audio/wav", true)),
+        _ => None,
+      };
+    }
+
+    if sample.len() >= 12 && &sample[4..8] == b"ftyp" {
+      return Some((if &sample[8..12] == b"avif" { "image/avif" } else { "video/mp4" }, true));
+    }
+
+    match Mime::is_text(sample) {
+      true => Some((TEXT_MIME_TYPE, false)),
+      false => None,
+    }
+  }
+
+  pub fn is_text(sample: &[u8]) -> bool {
+    if sample.contains(&0) {
+      return false;
+    }
+
+    match std::str::from_utf8(sample) {
+      Ok(_) => true,
+      // A MULTI-BYTE CHARACTER MAY BE CUT AT THE END OF THE SAMPLE
+      Err(e) => e.error_len().is_none() && sample.len() - e.valid_up_to() < 4,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::Path;
+
+  use super::{Mime, DEFAULT_MIME_TYPE, TEXT_MIME_TYPE};
+
+  #[test]
+  fn mime_sniff_test() {
+    assert_eq!(Mime::signature(b"%PDF-1.7\n"), Some(("application/pdf", true)));
+    assert_eq!(Mime::signature(b"\x89PNG\r\n\x1a\n\x00\x00"), Some(("image/png", true)));
+    assert_eq!(Mime::signature(b"\x7FELF\x02\x01\x01"), Some(("application/x-executable", true)));
+    assert_eq!(Mime::signature(b"\x1F\x8B\x08"), Some(("application/gzip", false)));
+    assert_eq!(Mime::signature("plain text – utf8".as_bytes()), Some((TEXT_MIME_TYPE, false)));
+    assert_eq!(Mime::signature(b"\x00\x01\x02\x03"), None);
+  }
+
+  #[test]
+  fn mime_resolve_test() {
+    assert_eq!(Mime::resolve(Mime::from_extension(Path::new("a.json")), b"{}"), "application/json");
+    assert_eq!(Mime::resolve(Mime::from_extension(Path::new("a.txt")), b"\x1F\x8B\x08"), TEXT_MIME_TYPE);
+    assert_eq!(Mime::resolve(Mime::from_extension(Path::new("a.txt")), b"%PDF-1.7"), "application/pdf");
+    assert_eq!(Mime::resolve(None, b"\x1F\x8B\x08"), "application/gzip");
+    assert_eq!(Mime::resolve(Mime::from_extension(Path::new("a.docx")), b"PK\x03\x04"), Mime::from_extension(Path::new("a.docx")).unwrap());
+    assert_eq!(Mime::resolve(None, b"\x00\x01\x02"), DEFAULT_MIME_TYPE);
+  }
+
+  #[test]
+  fn mime_weak_signature_test() {
+    assert_eq!(Mime::resolve(Mime::from_extension(Path::new("notes.txt")), b"BM is a short signature"), TEXT_MIME_TYPE);
+    assert_eq!(Mime::resolve(Mime::from_extension(Path::new("README.md")), b"MZ\x00\x01"), "text/markdown; charset=utf-8");
+    assert_eq!(Mime::resolve(Mime::from_extension(Path::new("song.txt")), b"ID3\x04\x00"), TEXT_MIME_TYPE);
+    assert_eq!(Mime::resolve(None, b"BM\x00\x01"), "image/bmp");
+    assert_eq!(Mime::resolve(Mime::from_extension(Path::new("a.txt")), b"\x89PNG\r\n\x1a\n"), "image/png");
+  }
+}
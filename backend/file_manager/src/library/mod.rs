@@ -1,4 +1,34 @@
 pub mod tp;
+pub mod access_log;
+pub mod archive;
+pub mod compression;
+pub mod conditional;
+pub mod checksum;
+pub mod chunks;
+pub mod cors;
+pub mod duplicates;
+pub mod events;
+pub mod extraction;
+pub mod ignore;
+pub mod index;
+pub mod indexer;
+pub mod local_backend;
+pub mod memory_backend;
+pub mod metrics;
+pub mod mime;
+pub mod rate_limit;
+pub mod search;
+pub mod range;
+pub mod storage;
+pub mod tasks;
+#[cfg(test)]
+pub mod testing;
+pub mod trash;
+pub mod unpack;
+pub mod usage;
+pub mod vfs;
+pub mod versions;
 
 mod worker;
-mod job;
\ No newline at end of file
+mod job;
+mod job_queue;
\ No newline at end of file
@@ -0,0 +1,72 @@
+use crate::parser::http_request::HttpRequest;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+  Full,
+  Partial(u64, u64),
+  Unsatisfiable,
+}
+
+impl ByteRange {
+  // ONLY A SINGLE `bytes=` RANGE IS SERVED, ANYTHING ELSE GETS THE WHOLE BODY WHICH RFC 9110 ALLOWS
+  pub fn parse(value: &str, size: u64) -> Self {
+    let Some(spec) = value.trim().strip_prefix("bytes=").filter(|s| !s.contains(',')) else {
+      return ByteRange::Full;
+    };
+
+    let Some((start, end)) = spec.trim().split_once('-') else {
+      return ByteRange::Full;
+    };
+
+    let range = match (start.trim(), end.trim()) {
+      ("", suffix) => match suffix.parse::<u64>() {
+        Ok(0) => return ByteRange::Unsatisfiable,
+        Ok(suffix) => (size.saturating_sub(suffix), u64::MAX),
+        Err(_) => return ByteRange::Full,
+      },
+      (start, "") => match start.parse::<u64>() {
+        Ok(start) => (start, u64::MAX),
+        Err(_) => return ByteRange::Full,
+      },
+      (start, end) => match (start.parse::<u64>(), end.parse::<u64>()) {
+        (Ok(start), Ok(end)) => (start, end),
+        _ => return ByteRange::Full,
+      },
+    };
+
+    match range {
+      (start, end) if end < start => ByteRange::Full,
+      (start, _) if start >= size => ByteRange::Unsatisfiable,
+      (start, end) => ByteRange::Partial(start, end.min(size - 1)),
+    }
+  }
+
+  // A STALE `If-Range` VALIDATOR MEANS THE CLIENT'S PIECES NO LONGER FIT TOGETHER, SO IT GETS EVERYTHING
+  pub fn from_request(http_request: &HttpRequest, size: u64, etag: &str) -> Self {
+    if http_request.header("if-range").is_some_and(|tag| tag.trim() != etag || etag.starts_with("W/")) {
+      return ByteRange::Full;
+    }
+
+    match http_request.header("range") {
+      Some(value) => ByteRange::parse(value, size),
+      None => ByteRange::Full,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::ByteRange;
+
+  #[test]
+  fn byte_range_parse_test() {
+    assert_eq!(ByteRange::parse("bytes=0-99", 1000), ByteRange::Partial(0, 99));
+    assert_eq!(ByteRange::parse("bytes=900-", 1000), ByteRange::Partial(900, 999));
+    assert_eq!(ByteRange::parse("bytes=-100", 1000), ByteRange::Partial(900, 999));
+    assert_eq!(ByteRange::parse("bytes=500-5000", 1000), ByteRange::Partial(500, 999));
+    assert_eq!(ByteRange::parse("bytes=1000-", 1000), ByteRange::Unsatisfiable);
+    assert_eq!(ByteRange::parse("bytes=0-1,5-9", 1000), ByteRange::Full);
+    assert_eq!(ByteRange::parse("bytes=9-1", 1000), ByteRange::Full);
+    assert_eq!(ByteRange::parse("items=0-1", 1000), ByteRange::Full);
+  }
+}
@@ -0,0 +1,259 @@
+use std::{collections::HashMap, sync::{Mutex, PoisonError}, time::{Duration, Instant}};
+
+use logger_main::Logger;
+
+use crate::config::constants::{
+  EXPENSIVE_ROUTING_TABLE, RATE_LIMIT_CAPACITY, RATE_LIMIT_EXPENSIVE_CAPACITY, RATE_LIMIT_EXPENSIVE_WINDOW,
+  RATE_LIMIT_MAX_CLIENTS, RATE_LIMIT_SWEEP_SECS, RATE_LIMIT_WINDOW,
+};
+use crate::enums::app_error::AppError;
+use crate::parser::{http_request::HttpRequest, http_response::HttpResponse};
+use crate::router::middleware::{Context, Middleware, Next};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Budget {
+  pub name: &'static str,
+  pub capacity: u32,
+  pub window: u32,
+}
+
+// `updated` ONLY MOVES WHEN THE CLIENT IS SEEN, SO IT DOUBLES AS THE LAST-SEEN TIME
+struct Bucket {
+  budget: Budget,
+  tokens: f64,
+  updated: Instant,
+}
+
+struct Buckets {
+  clients: HashMap<String, Bucket>,
+  swept: Instant,
+}
+
+struct Quota {
+  budget: Budget,
+  remaining: u32,
+  reset: u64,
+  retry_after: Option<u64>,
+}
+
+pub struct RateLimiter {
+  general: Budget,
+  expensive: Budget,
+  max_clients: usize,
+  sweep_interval: Duration,
+  buckets: Mutex<Buckets>,
+}
+
+impl Budget {
+  pub fn new(name: &'static str, capacity: u32, window: u32) -> Self {
+    Budget { name, capacity: capacity.max(1), window: window.max(1) }
+  }
+
+  fn rate(&self) -> f64 {
+    self.capacity as f64 / self.window as f64
+  }
+}
+
+impl Bucket {
+  fn refill(&mut self, now: Instant) {
+    self.tokens = self.available(now);
+    self.updated = now;
+  }
+
+  fn available(&self, now: Instant) -> f64 {
+    let elapsed = now.duration_since(self.updated).as_secs_f64();
+    (self.tokens + elapsed * self.budget.rate()).min(self.budget.capacity as f64)
+  }
+}
+
+impl RateLimiter {
+  pub fn new() -> Self {
+    RateLimiter::with_budgets(
+      Budget::new("general", RATE_LIMIT_CAPACITY, RATE_LIMIT_WINDOW),
+      Budget::new("expensive", RATE_LIMIT_EXPENSIVE_CAPACITY, RATE_LIMIT_EXPENSIVE_WINDOW),
+    )
+  }
+
+  pub fn with_budgets(general: Budget, expensive: Budget) -> Self {
+    RateLimiter {
+      general,
+      expensive,
+      max_clients: RATE_LIMIT_MAX_CLIENTS,
+      sweep_interval: Duration::from_secs(RATE_LIMIT_SWEEP_SECS),
+      buckets: Mutex::new(Buckets { clients: HashMap::new(), swept: Instant::now() }),
+    }
+  }
+}
+
+impl RateLimiter {
+  // BUCKETS ARE KEYED BY CLIENT ADDRESS, REQUESTS WITHOUT ONE SHARE A SINGLE BUCKET
+  fn client(context: &Context) -> String {
+    match context.http_request.remote_addr {
+      Some(addr) => format!("ip:{}", addr),
+      None => String::from("anonymous"),
+    }
+  }
+
+  fn budgets(&self, http_request: &HttpRequest) -> Vec<Budget> {
+    let is_expensive = EXPENSIVE_ROUTING_TABLE
+      .iter()
+      .any(|(method, path)| *method == http_request.method && *path == http_request.path);
+
+    match is_expensive {
+      true => vec![self.general, self.expensive],
+      false => vec![self.general],
+    }
+  }
+
+  // EVERY BUDGET IS CHECKED BEFORE ANY TOKEN IS TAKEN, A REJECTED REQUEST COSTS NOTHING
+  fn acquire(&self, client: &str, budgets: &[Budget]) -> Quota {
+    let now = Instant::now();
+    let mut guard = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+    if guard.clients.len() >= self.max_clients || now.duration_since(guard.swept) >= self.sweep_interval {
+      self.sweep(&mut guard, now);
+    }
+
+    let buckets = &mut guard.clients;
+    let keys: Vec<String> = budgets.iter().map(|b| format!("{}:{}", b.name, client)).collect();
+    for (key, budget) in keys.iter().zip(budgets) {
+      let bucket = buckets
+        .entry(key.clone())
+        .or_insert_with(|| Bucket { budget: *budget, tokens: budget.capacity as f64, updated: now });
+      bucket.refill(now);
+    }
+
+    let is_allowed = keys.iter().all(|key| buckets[key].tokens >= 1.0);
+    let mut quotas: Vec<Quota> = keys.iter().zip(budgets).map(|(key, budget)| {
+      let bucket = buckets.get_mut(key).expect("bucket was inserted above");
+      if is_allowed {
+        bucket.tokens -= 1.0;
+      }
+
+      let retry_after = (bucket.tokens < 1.0).then(|| ((1.0 - bucket.tokens) / budget.rate()).ceil() as u64);
+      Quota {
+        budget: *budget,
+        remaining: bucket.tokens.floor() as u32,
+        reset: ((budget.capacity as f64 - bucket.tokens) / budget.rate()).ceil() as u64,
+        retry_after: retry_after.filter(|_| !is_allowed),
+      }
+    }).collect();
+
+    // THE TIGHTEST BUDGET IS THE ONE THE CLIENT NEEDS TO HEAR ABOUT
+    quotas.sort_by_key(|quota| (quota.retry_after.is_none(), quota.remaining));
+    quotas.remove(0)
+  }
+
+  // A BUCKET THAT WOULD BE FULL AGAIN IS NO DIFFERENT FROM A NEW ONE. WHEN THAT DOES NOT FREE ENOUGH ROOM
+  // THE CLIENTS SEEN LEAST RECENTLY GO, DOWN TO THREE QUARTERS OF THE LIMIT SO THE NEXT SWEEP IS NOT ONE REQUEST AWAY
+  fn sweep(&self, buckets: &mut Buckets, now: Instant) {
+    buckets.clients.retain(|_, bucket| bucket.available(now) < bucket.budget.capacity as f64);
+    buckets.swept = now;
+    if buckets.clients.len() < self.max_clients {
+      return;
+    }
+
+    let mut seen: Vec<(Instant, String)> = buckets.clients.iter().map(|(key, bucket)| (bucket.updated, key.clone())).collect();
+    seen.sort_unstable();
+    let evicted = seen.len() - self.max_clients * 3 / 4;
+    for (_, key) in seen.into_iter().take(evicted) {
+      buckets.clients.remove(&key);
+    }
+  }
+}
+
+impl Middleware for RateLimiter {
+  fn handle(&self, context: &mut Context, next: Next) -> HttpResponse {
+    let client = RateLimiter::client(context);
+    let quota = self.acquire(&client, &self.budgets(&context.http_request));
+
+    let mut http_response = match quota.retry_after {
+      None => next.run(context),
+      Some(retry_after) => {
+        Logger::warn(format!("Rate limited [CLIENT: {} | BUDGET: {} | PATH: {}]", client, quota.budget.name, context.http_request.path));
+        let message = format!("Rate limit for {} requests exceeded, retry in {} seconds", quota.budget.name, retry_after);
+        let mut http_response = AppError::TooManyRequests(message).into_response(&context.request_id());
+        http_response.header("Retry-After", retry_after.to_string());
+        http_response
+      },
+    };
+
+    http_response
+      .header("RateLimit-Limit", quota.budget.capacity.to_string())
+      .header("RateLimit-Remaining", quota.remaining.to_string())
+      .header("RateLimit-Reset", quota.reset.to_string())
+      .header("RateLimit-Policy", format!("{};w={}", quota.budget.capacity, quota.budget.window));
+    http_response
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{sync::Arc, thread, time::Duration};
+
+  use crate::enums::app_error::AppError;
+  use crate::parser::{http_request::HttpRequest, http_response::HttpResponse};
+  use crate::router::middleware::{Context, Layer, Next};
+
+  use super::{Budget, RateLimiter};
+
+  fn ok(_: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::new("200", "OK", ""))
+  }
+
+  fn request(method: &str, client: &str) -> Context {
+    let mut http_request = HttpRequest::construct(vec![format!("{} /files HTTP/1.1", method)]);
+    http_request.remote_addr = client.parse().ok();
+    Context::new(http_request)
+  }
+
+  #[test]
+  fn rate_limit_budget_test() {
+    let layers: Vec<Layer> = vec![Arc::new(RateLimiter::with_budgets(Budget::new("general", 3, 60), Budget::new("expensive", 1, 60)))];
+
+    let first = Next::new(&layers, &ok).run(&mut request("POST", "10.0.0.1"));
+    assert_eq!(first.status, "200");
+    assert_eq!(first.get_header("RateLimit-Remaining").map(String::as_str), Some("0"));
+
+    let second = Next::new(&layers, &ok).run(&mut request("POST", "10.0.0.1"));
+    assert_eq!(second.status, "429");
+    assert_eq!(second.get_header("Retry-After").map(String::as_str), Some("60"));
+
+    // THE REJECTED UPLOAD DID NOT SPEND FROM THE GENERAL BUDGET
+    let third = Next::new(&layers, &ok).run(&mut request("GET", "10.0.0.1"));
+    assert_eq!(third.status, "200");
+    assert_eq!(third.get_header("RateLimit-Remaining").map(String::as_str), Some("1"));
+    assert_eq!(Next::new(&layers, &ok).run(&mut request("GET", "10.0.0.1")).status, "200");
+    assert_eq!(Next::new(&layers, &ok).run(&mut request("GET", "10.0.0.1")).status, "429");
+    assert_eq!(Next::new(&layers, &ok).run(&mut request("GET", "10.0.0.2")).status, "200");
+  }
+
+  #[test]
+  fn rate_limit_sweep_budget_test() {
+    let mut limiter = RateLimiter::with_budgets(Budget::new("general", 1000, 1), Budget::new("expensive", 1, 3600));
+    limiter.sweep_interval = Duration::ZERO;
+    let layers: Vec<Layer> = vec![Arc::new(limiter)];
+
+    assert_eq!(Next::new(&layers, &ok).run(&mut request("POST", "10.0.0.1")).status, "200");
+    thread::sleep(Duration::from_millis(10));
+
+    // THE SWEEP RUNS ON THIS REQUEST, AT THE GENERAL RATE THE EXPENSIVE BUCKET WOULD LOOK FULL AGAIN
+    assert_eq!(Next::new(&layers, &ok).run(&mut request("POST", "10.0.0.1")).status, "429");
+  }
+
+  #[test]
+  fn rate_limit_sweep_eviction_test() {
+    let mut limiter = RateLimiter::with_budgets(Budget::new("general", 1, 3600), Budget::new("expensive", 1, 3600));
+    limiter.max_clients = 4;
+    let layers: Vec<Layer> = vec![Arc::new(limiter)];
+
+    // NO BUCKET EVER REFILLS, SO ONLY THE LAST-SEEN TIME CAN MAKE ROOM
+    for client in 1..=4 {
+      assert_eq!(Next::new(&layers, &ok).run(&mut request("GET", &format!("10.0.0.{}", client))).status, "200");
+      thread::sleep(Duration::from_millis(2));
+    }
+    assert_eq!(Next::new(&layers, &ok).run(&mut request("GET", "10.0.0.5")).status, "200");
+
+    assert_eq!(Next::new(&layers, &ok).run(&mut request("GET", "10.0.0.4")).status, "429");
+    assert_eq!(Next::new(&layers, &ok).run(&mut request("GET", "10.0.0.1")).status, "200");
+  }
+}
@@ -0,0 +1,314 @@
+use std::{collections::VecDeque, io::{self, Read}, path::Path, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+
+use globset::{GlobBuilder, GlobMatcher};
+use json_builder::{Json, JsonBuilder};
+use regex::{Regex, RegexBuilder};
+
+use crate::config::constants::{SEARCH_DEFAULT_LIMIT, SEARCH_IGNORE_FILES, SEARCH_MAX_DEPTH, SEARCH_MAX_LIMIT, SEARCH_TIMEOUT_SECS};
+use crate::config::utility::{escape_json, parse_timestamp};
+use crate::enums::app_error::AppError;
+use crate::library::ignore::{IgnoreFile, IgnoreStack};
+use crate::library::vfs::{EntryKind, Stat, Vfs};
+use crate::parser::http_request::HttpRequest;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryType {
+  Any,
+  File,
+  Directory,
+}
+
+pub struct SearchQuery {
+  pub path: String,
+  pub name: Option<GlobMatcher>,
+  pub regex: Option<Regex>,
+  pub entry_type: EntryType,
+  pub min_size: Option<u64>,
+  pub max_size: Option<u64>,
+  pub modified_after: Option<SystemTime>,
+  pub modified_before: Option<SystemTime>,
+  pub max_depth: usize,
+  pub limit: usize,
+  pub use_ignore_files: bool,
+}
+
+struct Candidate {
+  path: String,
+  name: String,
+  stat: Stat,
+}
+
+pub struct SearchStream {
+  query: SearchQuery,
+  vfs: Vfs,
+  directories: Vec<(String, usize, IgnoreStack)>,
+  candidates: VecDeque<Candidate>,
+  buffer: Vec<u8>,
+  offset: usize,
+  matched: usize,
+  visited: usize,
+  deadline: Instant,
+  is_finished: bool,
+}
+
+impl SearchQuery {
+  pub fn from(http_request: &HttpRequest) -> Result<Self, AppError> {
+    let param = |name: &str| http_request.param(name).map(|v| v.trim()).filter(|v| !v.is_empty());
+    let invalid = |name: &str| AppError::BadRequest(format!("Query parameter `{}` is invalid", name));
+
+    let name = param("name")
+      .map(|glob| GlobBuilder::new(glob).case_insensitive(param("case") == Some("insensitive")).build())
+      .transpose()
+      .map_err(|e| AppError::BadRequest(format!("Invalid glob: {}", e)))?
+      .map(|glob| glob.compile_matcher());
+
+    let regex = param("regex")
+      .map(|pattern| RegexBuilder::new(pattern).case_insensitive(param("case") == Some("insensitive")).size_limit(1 << 20).build())
+      .transpose()
+      .map_err(|e| AppError::BadRequest(format!("Invalid regex: {}", e)))?;
+
+    let entry_type = match param("type") {
+      None | Some("any") => EntryType::Any,
+      Some("file") => EntryType::File,
+      Some("directory") | Some("dir") => EntryType::Directory,
+      Some(_) => return Err(invalid("type")),
+    };
+
+    let size = |name: &str| param(name).map(|v| SearchQuery::parse_size(v).ok_or_else(|| invalid(name))).transpose();
+    let timestamp = |name: &str| param(name).map(|v| parse_timestamp(v).ok_or_else(|| invalid(name))).transpose();
+    let number = |name: &str, default: usize, max: usize| {
+      param(name).map(|v| v.parse::<usize>().map_err(|_| invalid(name))).transpose().map(|v| v.unwrap_or(default).min(max))
+    };
+
+    Ok(SearchQuery {
+      path: param("path").unwrap_or("/").to_owned(),
+      name,
+      regex,
+      entry_type,
+      min_size: size("min_size")?,
+      max_size: size("max_size")?,
+      modified_after: timestamp("modified_after")?,
+      modified_before: timestamp("modified_before")?,
+      max_depth: number("max_depth", SEARCH_MAX_DEPTH, SEARCH_MAX_DEPTH)?,
+      limit: number("limit", SEARCH_DEFAULT_LIMIT, SEARCH_MAX_LIMIT)?,
+      use_ignore_files: param("ignore_files") != Some("false"),
+    })
+  }
+
+  // PLAIN BYTES OR A BINARY `k`, `m`, `g` SUFFIX
+  fn parse_size(value: &str) -> Option<u64> {
+    let value = value.to_ascii_lowercase();
+    let (digits, multiplier) = match value.chars().last()? {
+      'k' => (&value[..value.len() - 1], 1 << 10),
+      'm' => (&value[..value.len() - 1], 1 << 20),
+      'g' => (&value[..value.len() - 1], 1 << 30),
+      _ => (value.as_str(), 1),
+    };
+
+    digits.parse::<u64>().ok()?.checked_mul(multiplier)
+  }
+
+  fn matches(&self, candidate: &Candidate) -> bool {
+    let (name, stat) = (&candidate.name, &candidate.stat);
+    let is_type_match = match self.entry_type {
+      EntryType::Any => true,
+      EntryType::File => stat.kind == EntryKind::File,
+      EntryType::Directory => stat.kind == EntryKind::Directory,
+    };
+
+    is_type_match
+      && self.name.as_ref().is_none_or(|glob| glob.is_match(name))
+      && self.regex.as_ref().is_none_or(|regex| regex.is_match(name))
+      && self.min_size.is_none_or(|min| stat.size >= min)
+      && self.max_size.is_none_or(|max| stat.size <= max)
+      && self.modified_after.is_none_or(|after| stat.modified >= after)
+      && self.modified_before.is_none_or(|before| stat.modified <= before)
+  }
+}
+
+impl SearchStream {
+  pub fn new(vfs: Vfs, query: SearchQuery) -> Self {
+    SearchStream {
+      directories: vec![(query.path.clone(), 0, IgnoreStack::default())],
+      query,
+      vfs,
+      candidates: VecDeque::new(),
+      buffer: Vec::new(),
+      offset: 0,
+      matched: 0,
+      visited: 0,
+      deadline: Instant::now() + Duration::from_secs(SEARCH_TIMEOUT_SECS),
+      is_finished: false,
+    }
+  }
+}
+
+impl SearchStream {
+  // WALKS UNTIL THERE IS SOMETHING TO SEND, SO THE CLIENT SEES EACH HIT AS SOON AS IT IS FOUND
+  fn advance(&mut self) {
+    loop {
+      if self.matched >= self.query.limit {
+        return self.finish(Some("limit"));
+      }
+
+      if Instant::now() >= self.deadline {
+        return self.finish(Some("timeout"));
+      }
+
+      if let Some(candidate) = self.candidates.pop_front() {
+        self.visited += 1;
+        if self.query.matches(&candidate) {
+          self.matched += 1;
+          self.emit_entry(&candidate);
+          return;
+        }
+        continue;
+      }
+
+      let Some((directory, depth, ignores)) = self.directories.pop() else {
+        return self.finish(None);
+      };
+
+      if let Err(e) = self.read_directory(directory.clone(), depth, ignores) {
+        self.emit_error(&directory, &e);
+        return;
+      }
+    }
+  }
+
+  fn read_directory(&mut self, directory: String, depth: usize, ignores: IgnoreStack) -> io::Result<()> {
+    let ignores = match self.query.use_ignore_files {
+      true => SEARCH_IGNORE_FILES.iter().fold(ignores, |stack, name| stack.with(self.ignore_file(&directory, name))),
+      false => ignores,
+    };
+
+    let mut subdirectories = Vec::new();
+    for entry in self.vfs.list(&directory)? {
+      // LINKS ARE REPORTED BUT NEVER FOLLOWED, A LINK CYCLE WOULD NEVER FINISH
+      let path = Vfs::join(&directory, &entry.name);
+      let is_dir = entry.stat.kind == EntryKind::Directory;
+      if ignores.is_ignored(Path::new(&path), is_dir) {
+        continue;
+      }
+
+      if is_dir && depth + 1 < self.query.max_depth {
+        subdirectories.push((path.clone(), depth + 1, ignores.clone()));
+      }
+      self.candidates.push_back(Candidate { path, name: entry.name, stat: entry.stat });
+    }
+
+    self.directories.extend(subdirectories.into_iter().rev());
+    Ok(())
+  }
+
+  fn ignore_file(&self, directory: &str, name: &str) -> Option<IgnoreFile> {
+    let mut contents = String::new();
+    self.vfs.open_read(&Vfs::join(directory, name), 0).and_then(|mut reader| reader.read_to_string(&mut contents)).ok()?;
+    Some(IgnoreFile::parse(Path::new(directory), &contents))
+  }
+
+  fn emit_entry(&mut self, candidate: &Candidate) {
+    let stat = &candidate.stat;
+    let modified = stat.modified.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let entry_type = match stat.kind {
+      EntryKind::Directory => "directory",
+      EntryKind::File => "file",
+      EntryKind::Other => "other",
+    };
+
+    let mut json_object = Json::object();
+    json_object.insert("path", escape_json(&candidate.path));
+    json_object.insert("name", escape_json(&candidate.name));
+    json_object.insert("type", entry_type);
+    json_object.insert("size", stat.size);
+    json_object.insert("modified", modified);
+    self.emit(Json::build(json_object));
+  }
+
+  fn emit_error(&mut self, path: &str, e: &io::Error) {
+    let mut json_object = Json::object();
+    json_object.insert("path", escape_json(path));
+    json_object.insert("error", format!("{:?}", e.kind()));
+    self.emit(Json::build(json_object));
+  }
+
+  fn finish(&mut self, reason: Option<&str>) {
+    let mut json_object = Json::object();
+    json_object.insert("done", true);
+    json_object.insert("matched", self.matched as u64);
+    json_object.insert("visited", self.visited as u64);
+    json_object.insert("truncated", reason.is_some());
+    if let Some(reason) = reason {
+      json_object.insert("reason", reason);
+    }
+
+    self.emit(Json::build(json_object));
+    self.is_finished = true;
+  }
+
+  fn emit(&mut self, line: String) {
+    self.buffer.extend_from_slice(line.as_bytes());
+    self.buffer.push(b'\n');
+  }
+}
+
+// DROPPING THE STREAM ENDS THE WALK, A FAILED WRITE TO A DISCONNECTED CLIENT DOES EXACTLY THAT
+impl Read for SearchStream {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    while self.offset >= self.buffer.len() {
+      if self.is_finished {
+        return Ok(0);
+      }
+
+      self.buffer.clear();
+      self.offset = 0;
+      self.advance();
+    }
+
+    let count = buf.len().min(self.buffer.len() - self.offset);
+    buf[..count].copy_from_slice(&self.buffer[self.offset..self.offset + count]);
+    self.offset += count;
+    Ok(count)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::Read;
+
+  use crate::library::testing::{local_vfs, TempDir};
+  use crate::parser::http_request::HttpRequest;
+
+  use super::{SearchQuery, SearchStream};
+
+  fn search(root: &TempDir, query: &str) -> Vec<String> {
+    let http_request = HttpRequest::construct(vec![format!("GET /search?{} HTTP/1.1", query)]);
+    let mut output = String::new();
+    SearchStream::new(local_vfs(root), SearchQuery::from(&http_request).unwrap()).read_to_string(&mut output).unwrap();
+    output.lines().map(String::from).collect()
+  }
+
+  #[test]
+  fn search_glob_ignore_test() {
+    let root = TempDir::new("search");
+    root.write(".gitignore", "target/\n*.tmp\n");
+    root.write("src/main.rs", "fn main() {}");
+    root.write("src/nested/lib.rs", "pub fn lib() {}");
+    root.write("src/scratch.tmp", "");
+    root.write("target/build.rs", "");
+    let lines = search(&root, "path=/&name=*.rs&type=file");
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].contains("\"path\":\"/src/main.rs\""));
+    assert!(lines[1].contains("\"path\":\"/src/nested/lib.rs\""));
+    assert!(lines[2].contains("\"matched\":2"));
+  }
+
+  #[test]
+  fn search_regex_depth_test() {
+    let root = TempDir::new("search");
+    root.write("src/nested/lib.rs", "pub fn lib() {}");
+    let lines = search(&root, "regex=^lib&max_depth=2");
+    assert_eq!(lines.len(), 1);
+    assert!(lines[0].contains("\"matched\":0"));
+  }
+}
@@ -0,0 +1,47 @@
+use std::{fs::{self, Metadata}, path::{Component, Path, PathBuf}};
+
+pub struct Storage;
+
+// THE REGULAR FILES UNDER A DIRECTORY ON DISK, WALKED LAZILY. ONLY FOR THE STORES THAT NEVER GO THROUGH A MOUNT
+pub struct StoredFiles {
+  pending: Vec<PathBuf>,
+}
+
+impl Storage {
+  pub fn files(root: &Path) -> StoredFiles {
+    StoredFiles { pending: vec![root.to_path_buf()] }
+  }
+
+  pub fn resolve_in(root: &str, path: &str) -> Option<PathBuf> {
+    let mut resolved = PathBuf::from(root);
+    for component in Path::new(path).components() {
+      match component {
+        Component::Normal(c)                    => resolved.push(c),
+        Component::RootDir | Component::CurDir  => {},
+        _                                       => return None,
+      }
+    }
+
+    Some(resolved)
+  }
+}
+
+impl Iterator for StoredFiles {
+  type Item = (PathBuf, Metadata);
+
+  // LINKS ARE SKIPPED, FOLLOWING ONE COULD LEAVE THE STORE
+  fn next(&mut self) -> Option<(PathBuf, Metadata)> {
+    loop {
+      let path = self.pending.pop()?;
+      let Ok(metadata) = fs::symlink_metadata(&path) else {
+        continue;
+      };
+
+      if metadata.is_dir() {
+        self.pending.extend(fs::read_dir(&path).into_iter().flatten().filter_map(|e| e.ok().map(|e| e.path())));
+      } else if metadata.is_file() {
+        return Some((path, metadata));
+      }
+    }
+  }
+}
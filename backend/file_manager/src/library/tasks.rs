@@ -0,0 +1,162 @@
+use std::{collections::VecDeque, io, sync::{atomic::{AtomicBool, AtomicU64, Ordering}, Arc, LazyLock, Mutex, MutexGuard, PoisonError}, thread, time::{SystemTime, UNIX_EPOCH}};
+
+use json_builder::{Json, JsonBuilder, JsonObject};
+use logger_main::Logger;
+
+use crate::config::constants::{TASK_HISTORY, TASK_MAX_RUNNING};
+use crate::config::utility::escape_json;
+use crate::enums::app_error::AppError;
+
+static TASKS: LazyLock<Mutex<VecDeque<Arc<Task>>>> = LazyLock::new(|| Mutex::new(VecDeque::new()));
+
+// RENDERED AGAIN FOR EVERY STATUS REQUEST, SO THE TASK ONLY KEEPS THE PLAIN DATA AROUND
+pub type TaskResult = Box<dyn Fn() -> JsonObject + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskState {
+  Running,
+  Completed,
+  Failed,
+  Cancelled,
+}
+
+pub struct Task {
+  pub id: String,
+  pub kind: &'static str,
+  pub subject: String,
+  processed: AtomicU64,
+  total: AtomicU64,
+  is_cancelled: AtomicBool,
+  status: Mutex<TaskStatus>,
+}
+
+struct TaskStatus {
+  state: TaskState,
+  result: Option<TaskResult>,
+  error: Option<String>,
+  started: SystemTime,
+  finished: Option<SystemTime>,
+}
+
+impl Task {
+  // AT MOST TASK_MAX_RUNNING RUN AT ONCE, FINISHED ONES ARE KEPT AROUND FOR POLLING
+  pub fn spawn<F>(id: String, kind: &'static str, subject: &str, body: F) -> Result<Arc<Task>, AppError>
+  where
+    F: FnOnce(&Task) -> io::Result<TaskResult> + Send + 'static,
+  {
+    let task = Arc::new(Task {
+      id,
+      kind,
+      subject: subject.to_owned(),
+      processed: AtomicU64::new(0),
+      total: AtomicU64::new(0),
+      is_cancelled: AtomicBool::new(false),
+      status: Mutex::new(TaskStatus { state: TaskState::Running, result: None, error: None, started: SystemTime::now(), finished: None }),
+    });
+
+    {
+      let mut tasks = TASKS.lock().unwrap_or_else(PoisonError::into_inner);
+      if tasks.iter().filter(|t| t.state() == TaskState::Running).count() >= TASK_MAX_RUNNING {
+        return Err(AppError::ServiceUnavailable(format!("{} background tasks are already running", TASK_MAX_RUNNING)));
+      }
+
+      tasks.push_back(task.clone());
+      while tasks.len() > TASK_HISTORY {
+        match tasks.iter().position(|t| t.state() != TaskState::Running) {
+          Some(index) => drop(tasks.remove(index)),
+          None => break,
+        }
+      }
+    }
+
+    let worker = task.clone();
+    thread::spawn(move || {
+      let result = body(&worker);
+      let mut status = worker.lock();
+      status.finished = Some(SystemTime::now());
+      match result {
+        _ if worker.is_cancelled() => status.state = TaskState::Cancelled,
+        Ok(result) => {
+          status.state = TaskState::Completed;
+          status.result = Some(result);
+        },
+        Err(e) => {
+          Logger::warn(format!("Task {} - {} failed: {}", worker.id, worker.kind, e));
+          status.state = TaskState::Failed;
+          status.error = Some(e.to_string());
+        },
+      }
+    });
+
+    Ok(task)
+  }
+
+  pub fn find(id: &str) -> Option<Arc<Task>> {
+    TASKS.lock().unwrap_or_else(PoisonError::into_inner).iter().find(|t| t.id == id).cloned()
+  }
+
+  pub fn state(&self) -> TaskState {
+    self.lock().state
+  }
+
+  pub fn cancel(&self) {
+    self.is_cancelled.store(true, Ordering::Relaxed);
+  }
+
+  // LONG LOOPS CHECK THIS BETWEEN UNITS OF WORK, A CANCELLED TASK ENDS AT THE NEXT CHECK
+  pub fn checkpoint(&self) -> io::Result<()> {
+    match self.is_cancelled() {
+      true => Err(io::Error::new(io::ErrorKind::Interrupted, "task was cancelled")),
+      false => Ok(()),
+    }
+  }
+
+  pub fn is_cancelled(&self) -> bool {
+    self.is_cancelled.load(Ordering::Relaxed)
+  }
+
+  pub fn set_total(&self, total: u64) {
+    self.total.store(total, Ordering::Relaxed);
+  }
+
+  pub fn advance(&self, processed: u64) {
+    self.processed.fetch_add(processed, Ordering::Relaxed);
+  }
+
+  pub fn to_json(&self) -> String {
+    let status = self.lock();
+    let seconds = |time: SystemTime| time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (processed, total) = (self.processed.load(Ordering::Relaxed), self.total.load(Ordering::Relaxed));
+
+    let mut json_object = Json::object();
+    json_object.insert("id", escape_json(&self.id));
+    json_object.insert("kind", self.kind);
+    json_object.insert("subject", escape_json(&self.subject));
+    json_object.insert("state", match status.state {
+      TaskState::Running => "running",
+      TaskState::Completed => "completed",
+      TaskState::Failed => "failed",
+      TaskState::Cancelled => "cancelled",
+    });
+    json_object.insert("processed", processed);
+    json_object.insert("total", total);
+    if total > 0 {
+      json_object.insert("progress", (processed.min(total) as f64 / total as f64 * 1000.0).round() / 10.0);
+    }
+    json_object.insert("started", seconds(status.started));
+    if let Some(finished) = status.finished {
+      json_object.insert("finished", seconds(finished));
+    }
+    if let Some(result) = status.result.as_ref() {
+      json_object.insert("result", result());
+    }
+    if let Some(error) = status.error.as_deref() {
+      json_object.insert("error", escape_json(error));
+    }
+    Json::build(json_object)
+  }
+
+  fn lock(&self) -> MutexGuard<'_, TaskStatus> {
+    self.status.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+}
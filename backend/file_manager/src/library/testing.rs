@@ -0,0 +1,112 @@
+use std::{fs, io::{Read, Write}, path::{Path, PathBuf}, sync::{atomic::{AtomicU64, Ordering}, mpsc, Arc}, time::Duration};
+
+use json_builder::{Json, JsonBuilder};
+
+use crate::library::{local_backend::LocalBackend, tasks::Task, vfs::{Backend, Vfs}};
+use crate::parser::{http_request::HttpRequest, http_response::{HttpBody, HttpResponse}};
+
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+// A DIRECTORY UNDER THE SYSTEM TEMP DIR, REMOVED WHEN THE TEST ENDS WHETHER IT PASSED OR NOT
+pub struct TempDir {
+  path: PathBuf,
+}
+
+impl TempDir {
+  pub fn new(name: &str) -> Self {
+    let path = std::env::temp_dir().join(format!("fm-{}-{}-{}", name, std::process::id(), SEQUENCE.fetch_add(1, Ordering::Relaxed)));
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(&path).unwrap();
+    TempDir { path }
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  pub fn join(&self, name: impl AsRef<Path>) -> PathBuf {
+    self.path.join(name)
+  }
+
+  pub fn mkdir(&self, name: &str) -> PathBuf {
+    let path = self.join(name);
+    fs::create_dir_all(&path).unwrap();
+    path
+  }
+
+  // MISSING PARENTS ARE CREATED TOO
+  pub fn write(&self, name: &str, content: impl AsRef<[u8]>) -> PathBuf {
+    let path = self.join(name);
+    if let Some(parent) = path.parent() {
+      fs::create_dir_all(parent).unwrap();
+    }
+    fs::write(&path, content).unwrap();
+    path
+  }
+}
+
+impl Drop for TempDir {
+  fn drop(&mut self) {
+    let _ = fs::remove_dir_all(&self.path);
+  }
+}
+
+// THE DIRECTORY ALONE, MOUNTED AT THE ROOT
+pub fn local_vfs(root: &TempDir) -> Vfs {
+  Vfs::default().mount("/", Arc::new(LocalBackend::new(root.path())))
+}
+
+// RUNS `body` AS A BACKGROUND TASK AND HANDS ITS RESULT BACK, SO ASSERTIONS STAY ON THE TEST THREAD
+pub fn in_task<T, F>(kind: &'static str, body: F) -> T
+where
+  T: Send + 'static,
+  F: FnOnce(&Task) -> T + Send + 'static,
+{
+  let (sender, receiver) = mpsc::channel();
+  let id = format!("{}-test-{}", kind, SEQUENCE.fetch_add(1, Ordering::Relaxed));
+  Task::spawn(id, kind, "/", move |task| {
+    let _ = sender.send(body(task));
+    Ok(Box::new(Json::object))
+  })
+  .unwrap();
+  receiver.recv_timeout(Duration::from_secs(10)).unwrap()
+}
+
+// BYTES THAT DO NOT REPEAT, SO CONTENT-DEFINED CHUNKING FINDS ITS USUAL NUMBER OF CUTS
+pub fn noise(length: usize) -> Vec<u8> {
+  let mut state = 0x2545_f491_4f6c_dd1du64;
+  (0..length).map(|_| {
+    state ^= state << 13;
+    state ^= state >> 7;
+    state ^= state << 17;
+    (state >> 32) as u8
+  }).collect()
+}
+
+// MISSING PARENTS ARE CREATED TOO
+pub fn put(backend: &dyn Backend, path: &str, content: &[u8]) {
+  if let Some((parent, _)) = path.rsplit_once('/') {
+    backend.mkdir(parent).unwrap();
+  }
+  let mut handle = backend.open_write(path).unwrap();
+  handle.write_all(content).unwrap();
+  handle.commit().unwrap();
+}
+
+pub fn request(line: &str, body: &[u8]) -> HttpRequest {
+  let mut http_request = HttpRequest::construct(vec![format!("{} HTTP/1.1", line)]);
+  http_request.body = body.to_vec();
+  http_request
+}
+
+// STREAMED BODIES ARE READ TO THE END
+pub fn content(http_response: HttpResponse) -> Vec<u8> {
+  match http_response.contents {
+    HttpBody::Bytes(bytes) => bytes,
+    HttpBody::Stream(mut reader) => {
+      let mut content = Vec::new();
+      reader.read_to_end(&mut content).unwrap();
+      content
+    },
+  }
+}
@@ -1,43 +1,386 @@
-use std::sync::{mpsc, Arc, Mutex};
+use std::{panic::{self, AssertUnwindSafe}, sync::{atomic::{AtomicU64, AtomicUsize, Ordering}, mpsc::TrySendError, Arc, Mutex, PoisonError}, time::Duration};
 
 use logger_main::Logger;
 
+use crate::config::constants::{
+  THREAD_POOL_AGING_MILLIS, THREAD_POOL_CLASS_LIMITS, THREAD_POOL_KEEP_ALIVE_SECS, THREAD_POOL_MAX_THREADS,
+  THREAD_POOL_MIN_THREADS, THREAD_POOL_QUEUE_DEPTH, THREAD_POOL_QUEUE_POLICY,
+};
+use crate::enums::app_error::AppError;
+
 use super::worker::Worker;
-use super::job::Job;
+use super::job::{Job, QueuedJob};
+use super::job_queue::JobQueue;
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueuePolicy {
+  Block,
+  Reject,
+  CallerRuns,
+}
+
+// DECLARATION ORDER IS SCHEDULING ORDER, THE FIRST CLASS GETS THE LONGEST HEAD START
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Priority {
+  Interactive,
+  Normal,
+  Bulk,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PoolConfig {
+  pub min_threads: usize,
+  pub max_threads: usize,
+  pub keep_alive: Duration,
+  pub queue_depth: usize,
+  pub policy: QueuePolicy,
+  pub class_limits: [usize; Priority::COUNT],
+  pub aging: Duration,
+}
 
 pub struct ThreadPool {
-  workers: Vec<Worker>,
-  sender: Option<mpsc::Sender<Job>>,
+  workers: Mutex<Vec<Worker>>,
+  queue: Arc<JobQueue>,
+  state: Arc<PoolState>,
+  config: PoolConfig,
+  next_id: AtomicUsize,
+}
+
+pub struct PoolState {
+  pub min_threads: usize,
+  pub keep_alive: Duration,
+  pub alive: AtomicUsize,
+  pub busy: AtomicUsize,
+  pub queued: AtomicUsize,
+  pub restarted: AtomicUsize,
+  pub retired: AtomicUsize,
+  pub completed: AtomicUsize,
+  pub panicked: AtomicUsize,
+  pub rejected: AtomicUsize,
+  pub wait_count: AtomicU64,
+  pub wait_total_micros: AtomicU64,
+  pub wait_max_micros: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolStats {
+  pub size: usize,
+  pub min_size: usize,
+  pub max_size: usize,
+  pub busy: usize,
+  pub idle: usize,
+  pub restarted: usize,
+  pub retired: usize,
+  pub completed: usize,
+  pub panicked: usize,
+  pub queued: usize,
+  pub queue_depth: usize,
+  pub running: [usize; Priority::COUNT],
+  pub rejected: usize,
+  pub wait_avg_micros: u64,
+  pub wait_max_micros: u64,
+}
+
+impl Default for PoolConfig {
+  fn default() -> Self {
+    PoolConfig {
+      min_threads: THREAD_POOL_MIN_THREADS,
+      max_threads: THREAD_POOL_MAX_THREADS,
+      keep_alive: Duration::from_secs(THREAD_POOL_KEEP_ALIVE_SECS),
+      queue_depth: THREAD_POOL_QUEUE_DEPTH,
+      policy: THREAD_POOL_QUEUE_POLICY,
+      class_limits: THREAD_POOL_CLASS_LIMITS,
+      aging: Duration::from_millis(THREAD_POOL_AGING_MILLIS),
+    }
+  }
+}
+
+impl Priority {
+  pub const COUNT: usize = 3;
+
+  pub fn index(self) -> usize {
+    self as usize
+  }
+
+  pub fn rank(self) -> u64 {
+    (Priority::COUNT - 1 - self.index()) as u64
+  }
+}
+
+impl PoolState {
+  pub fn new(min_threads: usize, keep_alive: Duration) -> Self {
+    PoolState {
+      min_threads,
+      keep_alive,
+      alive: AtomicUsize::new(0),
+      busy: AtomicUsize::new(0),
+      queued: AtomicUsize::new(0),
+      restarted: AtomicUsize::new(0),
+      retired: AtomicUsize::new(0),
+      completed: AtomicUsize::new(0),
+      panicked: AtomicUsize::new(0),
+      rejected: AtomicUsize::new(0),
+      wait_count: AtomicU64::new(0),
+      wait_total_micros: AtomicU64::new(0),
+      wait_max_micros: AtomicU64::new(0),
+    }
+  }
+
+  // A WORKER MAY ONLY LEAVE WHILE THE POOL STAYS AT OR ABOVE ITS MINIMUM SIZE
+  pub fn try_retire(&self) -> bool {
+    let retired = self.alive
+      .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |alive| (alive > self.min_threads).then(|| alive - 1))
+      .is_ok();
+
+    if retired {
+      self.retired.fetch_add(1, Ordering::Relaxed);
+    }
+
+    retired
+  }
 }
 
 impl ThreadPool {
-  pub fn new(capacity: usize) -> Self {
-    let (sender, receiver) = mpsc::channel();
-    let receiver = Arc::new(Mutex::new(receiver));
-    let mut workers = Vec::with_capacity(capacity);
-    for id in 0..capacity {
-      workers.push(Worker::new(id, Arc::clone(&receiver)));
+  pub fn new(config: PoolConfig) -> Self {
+    let max_threads = config.max_threads.max(config.min_threads).max(1);
+    let config = PoolConfig { max_threads, ..config };
+
+    let queue = Arc::new(JobQueue::new(config.queue_depth, config.class_limits, config.aging));
+    let state = Arc::new(PoolState::new(config.min_threads, config.keep_alive));
+    let mut workers = Vec::with_capacity(max_threads);
+    for id in 0..config.min_threads {
+      workers.push(Worker::new(id, Arc::clone(&queue), Arc::clone(&state)));
     }
 
-    ThreadPool { workers, sender: Some(sender) }
+    ThreadPool {
+      workers: Mutex::new(workers),
+      queue,
+      state,
+      config,
+      next_id: AtomicUsize::new(config.min_threads),
+    }
   }
 
-  pub fn execute<F>(&self, f: F) 
-  where 
+  pub fn submit<F>(&self, priority: Priority, f: F) -> Result<(), AppError>
+  where
     F: FnOnce() + Send + 'static
   {
-    let job = Box::new(f);
-    self.sender.as_ref().unwrap().send(job).unwrap();
+    self.heal();
+
+    let job: Job = Box::new(f);
+    self.state.queued.fetch_add(1, Ordering::Relaxed);
+    self.grow();
+
+    let result = self.queue.push(QueuedJob::new(job, priority), self.config.policy == QueuePolicy::Block);
+
+    match result {
+      Ok(_) => Ok(()),
+      Err(e) => {
+        self.state.queued.fetch_sub(1, Ordering::Relaxed);
+        match (e, self.config.policy) {
+          (TrySendError::Full(queued), QueuePolicy::CallerRuns) => {
+            Logger::warn("Thread Pool - Queue is full, running job on the caller thread");
+            if panic::catch_unwind(AssertUnwindSafe(queued.job)).is_err() {
+              self.state.panicked.fetch_add(1, Ordering::Relaxed);
+            }
+            Ok(())
+          },
+          (TrySendError::Full(_), _) => {
+            self.state.rejected.fetch_add(1, Ordering::Relaxed);
+            Logger::warn(format!("Thread Pool - Queue is full, rejecting job - {:?}", self.stats()));
+            Err(AppError::ServiceUnavailable(String::from("Server is busy, please retry later")))
+          },
+          (TrySendError::Disconnected(_), _) => {
+            Logger::error("Thread Pool - Job rejected, queue is closed", None);
+            Err(AppError::ServiceUnavailable(String::from("Thread pool is shutting down")))
+          },
+        }
+      },
+    }
+  }
+
+  pub fn heal(&self) {
+    let mut workers = self.workers.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut respawned = 0;
+    let mut index = 0;
+    while index < workers.len() {
+      if !workers[index].thread.is_finished() {
+        index += 1;
+        continue;
+      }
+
+      // A CLEAN EXIT IS A KEEP-ALIVE RETIREMENT, A PANIC MEANS THE WORKER DIED AND HAS TO BE REPLACED
+      let worker = workers.swap_remove(index);
+      if worker.thread.join().is_err() {
+        Logger::warn(format!("Thread Pool - Worker {} - Died, respawning", worker.id));
+        self.state.alive.fetch_sub(1, Ordering::SeqCst);
+        workers.push(Worker::new(worker.id, Arc::clone(&self.queue), Arc::clone(&self.state)));
+        respawned += 1;
+      }
+    }
+
+    drop(workers);
+    if respawned > 0 {
+      self.state.restarted.fetch_add(respawned, Ordering::Relaxed);
+      Logger::info(format!("Thread Pool - {:?}", self.stats()));
+    }
+  }
+
+  fn grow(&self) {
+    let alive = self.state.alive.load(Ordering::SeqCst);
+    let idle = alive.saturating_sub(self.state.busy.load(Ordering::Relaxed));
+    if alive >= self.config.max_threads || self.state.queued.load(Ordering::Relaxed) <= idle {
+      return;
+    }
+
+    let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+    Logger::info(format!("Thread Pool - Jobs are queueing up, growing to {} workers", alive + 1));
+    let mut workers = self.workers.lock().unwrap_or_else(PoisonError::into_inner);
+    workers.push(Worker::new(id, Arc::clone(&self.queue), Arc::clone(&self.state)));
+  }
+
+  pub fn stats(&self) -> PoolStats {
+    let size = self.state.alive.load(Ordering::SeqCst);
+    let busy = self.state.busy.load(Ordering::Relaxed).min(size);
+    let wait_count = self.state.wait_count.load(Ordering::Relaxed);
+
+    PoolStats {
+      size,
+      min_size: self.config.min_threads,
+      max_size: self.config.max_threads,
+      busy,
+      idle: size - busy,
+      restarted: self.state.restarted.load(Ordering::Relaxed),
+      retired: self.state.retired.load(Ordering::Relaxed),
+      completed: self.state.completed.load(Ordering::Relaxed),
+      panicked: self.state.panicked.load(Ordering::Relaxed),
+      queued: self.state.queued.load(Ordering::Relaxed),
+      queue_depth: self.config.queue_depth,
+      running: self.queue.running(),
+      rejected: self.state.rejected.load(Ordering::Relaxed),
+      wait_avg_micros: self.state.wait_total_micros.load(Ordering::Relaxed).checked_div(wait_count).unwrap_or(0),
+      wait_max_micros: self.state.wait_max_micros.load(Ordering::Relaxed),
+    }
   }
 }
 
 impl Drop for ThreadPool {
     fn drop(&mut self) {
-      drop(self.sender.take());
+      self.queue.close();
 
-      for worker in self.workers.drain(..) {
+      let workers = self.workers.get_mut().unwrap_or_else(PoisonError::into_inner);
+      for worker in workers.drain(..) {
         Logger::warn(format!("Thread Pool - Worker {} - Shutdown", worker.id).as_str());
-        worker.thread.join().unwrap();
+        if worker.thread.join().is_err() {
+          Logger::warn(format!("Thread Pool - Worker {} - Exited with a panic", worker.id).as_str());
+        }
       }
     }
 }
+
+#[cfg(test)]
+mod tests {
+  use std::{sync::{mpsc, Arc, Barrier}, thread, time::Duration};
+
+  use super::{PoolConfig, Priority, QueuePolicy, ThreadPool};
+
+  fn config(min_threads: usize, max_threads: usize, queue_depth: usize, policy: QueuePolicy) -> PoolConfig {
+    PoolConfig {
+      min_threads,
+      max_threads,
+      keep_alive: Duration::from_millis(50),
+      queue_depth,
+      policy,
+      class_limits: [max_threads; Priority::COUNT],
+      aging: Duration::from_millis(50),
+    }
+  }
+
+  #[test]
+  fn thread_pool_panic_isolation_test() {
+    let pool = ThreadPool::new(config(2, 2, 16, QueuePolicy::Block));
+    for _ in 0..4 {
+      pool.submit(Priority::Normal, || panic!("job failure")).unwrap();
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    for i in 0..4 {
+      let sender = sender.clone();
+      pool.submit(Priority::Normal, move || sender.send(i).unwrap()).unwrap();
+    }
+
+    let mut results: Vec<i32> = (0..4).map(|_| receiver.recv_timeout(Duration::from_secs(5)).unwrap()).collect();
+    results.sort();
+    for _ in 0..200 {
+      if pool.stats().panicked == 4 {
+        break;
+      }
+      thread::sleep(Duration::from_millis(1));
+    }
+
+    let stats = pool.stats();
+    assert_eq!(results, vec![0, 1, 2, 3]);
+    assert_eq!(stats.size, 2);
+    assert_eq!(stats.panicked, 4);
+  }
+
+  #[test]
+  fn thread_pool_backpressure_test() {
+    let barrier = Arc::new(Barrier::new(2));
+    let pool = ThreadPool::new(config(1, 1, 1, QueuePolicy::Reject));
+
+    let worker_barrier = Arc::clone(&barrier);
+    pool.submit(Priority::Normal, move || { worker_barrier.wait(); }).unwrap();
+    while pool.stats().busy == 0 {
+      thread::sleep(Duration::from_millis(1));
+    }
+
+    assert!(pool.submit(Priority::Normal, || {}).is_ok());
+    assert!(pool.submit(Priority::Normal, || {}).is_err());
+    assert_eq!(pool.stats().rejected, 1);
+    assert_eq!(pool.stats().queued, 1);
+    barrier.wait();
+
+    let caller_pool = ThreadPool::new(config(1, 1, 1, QueuePolicy::CallerRuns));
+    let worker_barrier = Arc::clone(&barrier);
+    caller_pool.submit(Priority::Normal, move || { worker_barrier.wait(); }).unwrap();
+    while caller_pool.stats().busy == 0 {
+      thread::sleep(Duration::from_millis(1));
+    }
+
+    // THE WORKER IS BUSY AND THE QUEUE IS FULL, SO THE LAST JOB CAN ONLY RUN ON THIS THREAD
+    let (sender, receiver) = mpsc::channel();
+    caller_pool.submit(Priority::Normal, || {}).unwrap();
+    caller_pool.submit(Priority::Normal, move || sender.send(thread::current().id()).unwrap()).unwrap();
+    assert_eq!(receiver.try_recv().unwrap(), thread::current().id());
+    assert_eq!(caller_pool.stats().rejected, 0);
+    barrier.wait();
+  }
+
+  #[test]
+  fn thread_pool_elastic_sizing_test() {
+    let barrier = Arc::new(Barrier::new(4));
+    let pool = ThreadPool::new(config(1, 3, 16, QueuePolicy::Block));
+    for _ in 0..3 {
+      let worker_barrier = Arc::clone(&barrier);
+      pool.submit(Priority::Normal, move || { worker_barrier.wait(); }).unwrap();
+    }
+
+    // EVERY JOB BLOCKS ON THE BARRIER, SO THIS ONLY RETURNS ONCE THE POOL HAS GROWN TO THREE WORKERS
+    barrier.wait();
+    assert_eq!(pool.stats().max_size, 3);
+    assert!(pool.stats().size <= 3);
+
+    for _ in 0..200 {
+      if pool.stats().size == 1 {
+        break;
+      }
+      thread::sleep(Duration::from_millis(10));
+    }
+
+    let stats = pool.stats();
+    assert_eq!(stats.size, 1);
+    assert_eq!(stats.retired, 2);
+  }
+}
@@ -0,0 +1,27 @@
+use std::{fs, io, path::{Path, PathBuf}};
+
+use crate::config::constants::TRASH_ROOT;
+use crate::library::vfs::Vfs;
+
+pub struct Trash;
+
+impl Trash {
+  // KEEPS THE PATH THE CLIENT KNEW IT BY, GROUPED BY THE BATCH THAT MOVED IT, SO A FILE CAN BE PUT BACK BY HAND
+  pub fn store(target: &Path, path: &str, batch: &str) -> io::Result<PathBuf> {
+    let relative = Vfs::normalize(path).filter(|p| !p.is_empty()).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("{} cannot be moved to the trash", path)))?;
+    let destination = Path::new(TRASH_ROOT).join(batch).join(relative);
+    if let Some(parent) = destination.parent() {
+      fs::create_dir_all(parent)?;
+    }
+
+    match fs::rename(target, &destination) {
+      Ok(_) => Ok(destination),
+      Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+        fs::copy(target, &destination)?;
+        fs::remove_file(target)?;
+        Ok(destination)
+      },
+      Err(e) => Err(e),
+    }
+  }
+}
@@ -0,0 +1,520 @@
+use std::{collections::HashMap, io::{self, Read}, path::{Component, Path}, sync::{atomic::{AtomicU64, Ordering}, Arc}};
+
+use flate2::{read::{DeflateDecoder, MultiGzDecoder}, Crc};
+use time::{Date, Month, PrimitiveDateTime, Time};
+use xz2::read::XzDecoder;
+
+use crate::config::constants::{ARCHIVE_MAX_EXTRACT_SIZE, ARCHIVE_MAX_MEMBERS, ARCHIVE_MAX_RATIO, ARCHIVE_RATIO_GRACE};
+use crate::library::vfs::Vfs;
+
+const TAR_BLOCK: u64 = 512;
+const MAX_METADATA_SIZE: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+  Zip,
+  Tar,
+  TarGz,
+  TarXz,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberKind {
+  File,
+  Directory,
+  Symlink,
+  Other,
+}
+
+#[derive(Debug, Clone)]
+pub struct Member {
+  pub name: String,
+  pub kind: MemberKind,
+  pub size: u64,
+  pub compressed: Option<u64>,
+  pub modified: u64,
+  pub mode: u32,
+  pub link: Option<String>,
+  location: u64,
+  method: u16,
+  crc: u32,
+  is_encrypted: bool,
+}
+
+// EVERY BYTE THAT COMES OUT OF A DECOMPRESSOR IS CHARGED HERE, DECLARED SIZES CAN LIE
+#[derive(Clone)]
+pub struct Budget {
+  used: Arc<AtomicU64>,
+  archive_size: u64,
+}
+
+struct Guarded<R: Read> {
+  inner: R,
+  budget: Budget,
+}
+
+struct Verified<R: Read> {
+  inner: R,
+  crc: Crc,
+  expected_crc: u32,
+  expected_size: u64,
+  read: u64,
+}
+
+pub struct ZipArchive {
+  vfs: Vfs,
+  path: String,
+  pub members: Vec<Member>,
+}
+
+pub struct TarReader {
+  inner: Box<dyn Read + Send>,
+  remaining: u64,
+  padding: u64,
+  count: usize,
+}
+
+impl ArchiveKind {
+  pub fn detect(vfs: &Vfs, path: &str) -> Option<Self> {
+    let name = Path::new(path).file_name()?.to_string_lossy().to_ascii_lowercase();
+    let by_name = match () {
+      _ if name.ends_with(".zip") => Some(ArchiveKind::Zip),
+      _ if name.ends_with(".tar") => Some(ArchiveKind::Tar),
+      _ if name.ends_with(".tar.gz") || name.ends_with(".tgz") => Some(ArchiveKind::TarGz),
+      _ if name.ends_with(".tar.xz") || name.ends_with(".txz") => Some(ArchiveKind::TarXz),
+      _ => None,
+    };
+
+    by_name.or_else(|| {
+      let mut sample = [0u8; 263];
+      let read = vfs.open_read(path, 0).and_then(|mut reader| reader.read(&mut sample)).ok()?;
+      match &sample[..read] {
+        [b'P', b'K', 3, 4, ..] | [b'P', b'K', 5, 6, ..] => Some(ArchiveKind::Zip),
+        [0x1f, 0x8b, ..] => Some(ArchiveKind::TarGz),
+        [0xfd, b'7', b'z', b'X', b'Z', 0, ..] => Some(ArchiveKind::TarXz),
+        s if s.len() >= 262 && &s[257..262] == b"ustar" => Some(ArchiveKind::Tar),
+        _ => None,
+      }
+    })
+  }
+
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      ArchiveKind::Zip => "zip",
+      ArchiveKind::Tar => "tar",
+      ArchiveKind::TarGz => "tar.gz",
+      ArchiveKind::TarXz => "tar.xz",
+    }
+  }
+}
+
+impl MemberKind {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      MemberKind::File => "file",
+      MemberKind::Directory => "directory",
+      MemberKind::Symlink => "symlink",
+      MemberKind::Other => "other",
+    }
+  }
+}
+
+impl Budget {
+  pub fn new(archive_size: u64) -> Self {
+    Budget { used: Arc::new(AtomicU64::new(0)), archive_size: archive_size.max(1) }
+  }
+
+  fn guard<R: Read>(&self, inner: R) -> Guarded<R> {
+    Guarded { inner, budget: self.clone() }
+  }
+
+  // SMALL ARCHIVES GET SOME SLACK, A FEW KB OF TEXT CAN LEGITIMATELY COMPRESS FAR BEYOND THE RATIO
+  fn charge(&self, bytes: u64) -> io::Result<()> {
+    let used = self.used.fetch_add(bytes, Ordering::Relaxed) + bytes;
+    if used > ARCHIVE_MAX_EXTRACT_SIZE {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, format!("archive expands beyond {} bytes", ARCHIVE_MAX_EXTRACT_SIZE)));
+    }
+
+    if used > ARCHIVE_RATIO_GRACE && used / self.archive_size > ARCHIVE_MAX_RATIO {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, format!("archive expands more than {}x", ARCHIVE_MAX_RATIO)));
+    }
+    Ok(())
+  }
+}
+
+impl<R: Read> Read for Guarded<R> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let read = self.inner.read(buf)?;
+    self.budget.charge(read as u64)?;
+    Ok(read)
+  }
+}
+
+impl<R: Read> Read for Verified<R> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let read = self.inner.read(buf)?;
+    self.crc.update(&buf[..read]);
+    self.read += read as u64;
+
+    let is_corrupt = match read {
+      0 => self.read != self.expected_size || self.crc.sum() != self.expected_crc,
+      _ => self.read > self.expected_size,
+    };
+
+    match is_corrupt {
+      true => Err(io::Error::new(io::ErrorKind::InvalidData, "member does not match its size or checksum")),
+      false => Ok(read),
+    }
+  }
+}
+
+// THE MEMBER RELATIVE TO THE TARGET, NONE FOR ANY NAME THAT WOULD LAND OUTSIDE IT: ABSOLUTE PATHS, DRIVE LETTERS, `..`
+pub fn member_path(name: &str) -> Option<String> {
+  let name = name.replace('\\', "/");
+  if name.starts_with('/') || name.split('/').next().is_some_and(|first| first.contains(':')) {
+    return None;
+  }
+
+  let mut names = Vec::new();
+  for component in Path::new(&name).components() {
+    match component {
+      Component::Normal(c) => names.push(c.to_string_lossy().into_owned()),
+      Component::CurDir => {},
+      _ => return None,
+    }
+  }
+
+  (!names.is_empty()).then(|| names.join("/"))
+}
+
+impl ZipArchive {
+  // EVERY READ OPENS THE ARCHIVE AT AN OFFSET, A BACKEND HAS NOTHING TO SEEK WITH
+  pub fn open(vfs: &Vfs, path: &str) -> io::Result<Self> {
+    let length = vfs.stat(path)?.size;
+    let read_at = |offset: u64, buffer: &mut [u8]| vfs.open_read(path, offset)?.read_exact(buffer);
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("invalid zip: {}", message));
+
+    let tail_length = length.min(65_557);
+    let mut tail = vec![0; tail_length as usize];
+    read_at(length - tail_length, &mut tail)?;
+
+    let eocd = (0..tail.len().saturating_sub(21)).rev().find(|&i| tail[i..i + 4] == 0x06054b50u32.to_le_bytes()).ok_or_else(|| invalid("no end of central directory"))?;
+    let mut count = u16_at(&tail, eocd + 10) as u64;
+    let mut size = u32_at(&tail, eocd + 12) as u64;
+    let mut start = u32_at(&tail, eocd + 16) as u64;
+
+    if (count == 0xFFFF || size == 0xFFFF_FFFF || start == 0xFFFF_FFFF) && eocd >= 20 && tail[eocd - 20..eocd - 16] == 0x07064b50u32.to_le_bytes() {
+      let mut record = [0u8; 56];
+      read_at(u64_at(&tail, eocd - 12), &mut record)?;
+      if record[..4] != 0x06064b50u32.to_le_bytes() {
+        return Err(invalid("bad zip64 end of central directory"));
+      }
+      count = u64_at(&record, 32);
+      size = u64_at(&record, 40);
+      start = u64_at(&record, 48);
+    }
+
+    if count as usize > ARCHIVE_MAX_MEMBERS {
+      return Err(invalid(&format!("more than {} members", ARCHIVE_MAX_MEMBERS)));
+    }
+
+    if start.checked_add(size).is_none_or(|end| end > length) || size > count.saturating_mul(3 * 65_536 + 46) {
+      return Err(invalid("central directory out of bounds"));
+    }
+
+    let mut directory = vec![0; size as usize];
+    read_at(start, &mut directory)?;
+
+    let mut members = Vec::with_capacity(count as usize);
+    let mut at = 0;
+    while members.len() < count as usize {
+      if at + 46 > directory.len() || directory[at..at + 4] != 0x02014b50u32.to_le_bytes() {
+        return Err(invalid("bad central directory entry"));
+      }
+
+      let header = &directory[at..];
+      let (name_length, extra_length, comment_length) = (u16_at(header, 28) as usize, u16_at(header, 30) as usize, u16_at(header, 32) as usize);
+      if 46 + name_length + extra_length + comment_length > header.len() {
+        return Err(invalid("truncated central directory entry"));
+      }
+
+      let name = String::from_utf8_lossy(&header[46..46 + name_length]).into_owned();
+      let extra = &header[46 + name_length..46 + name_length + extra_length];
+      let mut member = Member {
+        kind: MemberKind::File,
+        size: u32_at(header, 24) as u64,
+        compressed: Some(u32_at(header, 20) as u64),
+        modified: dos_to_unix(u16_at(header, 12), u16_at(header, 14)),
+        mode: 0o644,
+        link: None,
+        location: u32_at(header, 42) as u64,
+        method: u16_at(header, 10),
+        crc: u32_at(header, 16),
+        is_encrypted: u16_at(header, 8) & 1 == 1,
+        name: String::new(),
+      };
+      ZipArchive::apply_extra(&mut member, extra);
+
+      let is_unix = header[5] == 3;
+      let mode = u32_at(header, 38) >> 16;
+      member.kind = match (is_unix && mode != 0, mode & 0o170000) {
+        (true, 0o120000) => MemberKind::Symlink,
+        (true, 0o040000) => MemberKind::Directory,
+        _ if name.ends_with('/') => MemberKind::Directory,
+        (true, 0o100000) | (false, _) | (true, 0) => MemberKind::File,
+        _ => MemberKind::Other,
+      };
+      if is_unix && mode != 0 {
+        member.mode = mode & 0o7777;
+      } else if member.kind == MemberKind::Directory {
+        member.mode = 0o755;
+      }
+
+      member.name = name.trim_end_matches('/').to_owned();
+      members.push(member);
+      at += 46 + name_length + extra_length + comment_length;
+    }
+
+    Ok(ZipArchive { vfs: vfs.clone(), path: path.to_owned(), members })
+  }
+
+  // ZIP64 SIZES AND OFFSETS ONLY APPEAR FOR THE FIELDS THAT WERE SATURATED IN THE HEADER
+  fn apply_extra(member: &mut Member, mut extra: &[u8]) {
+    while extra.len() >= 4 {
+      let (id, length) = (u16_at(extra, 0), u16_at(extra, 2) as usize);
+      let Some(data) = extra.get(4..4 + length) else {
+        return;
+      };
+
+      match id {
+        0x0001 => {
+          let mut fields = data.chunks_exact(8).map(|c| u64_at(c, 0));
+          if member.size == 0xFFFF_FFFF {
+            member.size = fields.next().unwrap_or(member.size);
+          }
+          if member.compressed == Some(0xFFFF_FFFF) {
+            member.compressed = fields.next().or(member.compressed);
+          }
+          if member.location == 0xFFFF_FFFF {
+            member.location = fields.next().unwrap_or(member.location);
+          }
+        },
+        0x5455 if length >= 5 && data[0] & 1 == 1 => member.modified = u32_at(data, 1) as u64,
+        _ => {},
+      }
+      extra = &extra[4 + length..];
+    }
+  }
+
+  pub fn reader(&self, member: &Member, budget: &Budget) -> io::Result<Box<dyn Read + Send>> {
+    let unsupported = |message: String| io::Error::new(io::ErrorKind::Unsupported, message);
+    if member.is_encrypted {
+      return Err(unsupported(format!("{} is encrypted", member.name)));
+    }
+
+    let mut file = self.vfs.open_read(&self.path, member.location)?;
+    let mut header = [0u8; 30];
+    file.read_exact(&mut header)?;
+    if header[..4] != 0x04034b50u32.to_le_bytes() {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, format!("invalid zip: bad local header for {}", member.name)));
+    }
+
+    let skip = u16_at(&header, 26) as u64 + u16_at(&header, 28) as u64;
+    let data = self.vfs.open_read(&self.path, member.location + 30 + skip)?.take(member.compressed.unwrap_or(0));
+    let decoded: Box<dyn Read + Send> = match member.method {
+      0 => Box::new(data),
+      8 => Box::new(DeflateDecoder::new(data)),
+      method => return Err(unsupported(format!("{} uses compression method {}", member.name, method))),
+    };
+
+    let verified = Verified { inner: decoded.take(member.size + 1), crc: Crc::new(), expected_crc: member.crc, expected_size: member.size, read: 0 };
+    Ok(Box::new(budget.guard(verified)))
+  }
+}
+
+impl TarReader {
+  pub fn open(file: Box<dyn Read + Send>, kind: ArchiveKind, budget: &Budget) -> io::Result<Self> {
+    let inner: Box<dyn Read + Send> = match kind {
+      ArchiveKind::Tar => Box::new(budget.guard(file)),
+      ArchiveKind::TarGz => Box::new(budget.guard(MultiGzDecoder::new(file))),
+      ArchiveKind::TarXz => Box::new(budget.guard(XzDecoder::new(file))),
+      ArchiveKind::Zip => return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a tar archive")),
+    };
+
+    Ok(TarReader { inner, remaining: 0, padding: 0, count: 0 })
+  }
+
+  // SKIPS WHATEVER IS LEFT OF THE CURRENT MEMBER, NONE AT THE END OF THE ARCHIVE
+  pub fn next_member(&mut self) -> io::Result<Option<Member>> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("invalid tar: {}", message));
+    let mut overrides: HashMap<String, String> = HashMap::new();
+
+    loop {
+      self.skip(self.remaining + self.padding)?;
+      self.remaining = 0;
+      self.padding = 0;
+
+      let mut header = [0u8; TAR_BLOCK as usize];
+      match self.inner.read_exact(&mut header) {
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        result => result?,
+      }
+
+      if header.iter().all(|b| *b == 0) {
+        return Ok(None);
+      }
+
+      let checksum: u32 = header.iter().enumerate().map(|(i, b)| if (148..156).contains(&i) { b' ' as u32 } else { *b as u32 }).sum();
+      if parse_octal(&header[148..156]) != Some(checksum as u64) {
+        return Err(invalid("bad header checksum"));
+      }
+
+      self.count += 1;
+      if self.count > ARCHIVE_MAX_MEMBERS {
+        return Err(invalid(&format!("more than {} members", ARCHIVE_MAX_MEMBERS)));
+      }
+
+      let size = match header[124] & 0x80 {
+        0 => parse_octal(&header[124..136]).ok_or_else(|| invalid("bad size"))?,
+        _ => header[128..136].iter().fold(0u64, |acc, b| acc << 8 | *b as u64),
+      };
+      let size = overrides.get("size").and_then(|s| s.parse().ok()).unwrap_or(size);
+      let padding = size.div_ceil(TAR_BLOCK) * TAR_BLOCK - size;
+
+      match header[156] {
+        b'x' | b'L' | b'K' => {
+          if size > MAX_METADATA_SIZE {
+            return Err(invalid("oversized extended header"));
+          }
+
+          let mut data = vec![0; size as usize];
+          self.inner.read_exact(&mut data)?;
+          self.skip(padding)?;
+          match header[156] {
+            b'x' => overrides.extend(TarReader::parse_pax(&data)),
+            b'L' => drop(overrides.insert(String::from("path"), cstr(&data))),
+            _ => drop(overrides.insert(String::from("linkpath"), cstr(&data))),
+          }
+          continue;
+        },
+        b'g' => {
+          self.skip(size + padding)?;
+          continue;
+        },
+        _ => {},
+      }
+
+      let name = overrides.remove("path").unwrap_or_else(|| match cstr(&header[345..500]) {
+        prefix if prefix.is_empty() || &header[257..262] != b"ustar" => cstr(&header[0..100]),
+        prefix => format!("{}/{}", prefix, cstr(&header[0..100])),
+      });
+
+      let kind = match header[156] {
+        b'0' | 0 | b'7' => MemberKind::File,
+        b'5' => MemberKind::Directory,
+        b'2' => MemberKind::Symlink,
+        _ => MemberKind::Other,
+      };
+      let kind = if kind == MemberKind::File && name.ends_with('/') { MemberKind::Directory } else { kind };
+
+      // ONLY REGULAR FILES CARRY DATA WORTH READING, EVERYTHING ELSE IS SKIPPED WITH ITS PADDING
+      self.remaining = size;
+      self.padding = padding;
+      return Ok(Some(Member {
+        name: name.trim_end_matches('/').to_owned(),
+        kind,
+        size: if kind == MemberKind::File { size } else { 0 },
+        compressed: None,
+        modified: overrides.get("mtime").and_then(|m| m.split('.').next()?.parse().ok()).or_else(|| parse_octal(&header[136..148])).unwrap_or(0),
+        mode: parse_octal(&header[100..108]).unwrap_or(0o644) as u32 & 0o7777,
+        link: (kind == MemberKind::Symlink).then(|| overrides.remove("linkpath").unwrap_or_else(|| cstr(&header[157..257]))),
+        location: 0,
+        method: 0,
+        crc: 0,
+        is_encrypted: false,
+      }));
+    }
+  }
+
+  fn skip(&mut self, bytes: u64) -> io::Result<()> {
+    let skipped = io::copy(&mut (&mut self.inner).take(bytes), &mut io::sink())?;
+    match skipped == bytes {
+      true => Ok(()),
+      false => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "invalid tar: truncated member")),
+    }
+  }
+
+  // "<length> <key>=<value>\n" RECORDS
+  fn parse_pax(data: &[u8]) -> HashMap<String, String> {
+    let mut records = HashMap::new();
+    let mut rest = data;
+    while let Some(space) = rest.iter().position(|b| *b == b' ') {
+      let Some(length) = std::str::from_utf8(&rest[..space]).ok().and_then(|l| l.parse::<usize>().ok()).filter(|l| *l > space && *l <= rest.len()) else {
+        break;
+      };
+
+      let record = String::from_utf8_lossy(&rest[space + 1..length]);
+      if let Some((key, value)) = record.trim_end_matches('\n').split_once('=') {
+        records.insert(key.to_owned(), value.to_owned());
+      }
+      rest = &rest[length..];
+    }
+    records
+  }
+}
+
+impl Read for TarReader {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let limit = buf.len().min(self.remaining.min(usize::MAX as u64) as usize);
+    if limit == 0 {
+      return Ok(0);
+    }
+
+    let read = self.inner.read(&mut buf[..limit])?;
+    if read == 0 {
+      return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "invalid tar: truncated member"));
+    }
+    self.remaining -= read as u64;
+    Ok(read)
+  }
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+  u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+  u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+fn u64_at(bytes: &[u8], at: usize) -> u64 {
+  let mut value = [0u8; 8];
+  value.copy_from_slice(&bytes[at..at + 8]);
+  u64::from_le_bytes(value)
+}
+
+fn cstr(bytes: &[u8]) -> String {
+  let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+  String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+fn parse_octal(bytes: &[u8]) -> Option<u64> {
+  let text = cstr(bytes);
+  let text = text.trim_matches(|c: char| c == ' ' || c == '\0');
+  match text.is_empty() {
+    true => Some(0),
+    false => u64::from_str_radix(text, 8).ok(),
+  }
+}
+
+fn dos_to_unix(time: u16, date: u16) -> u64 {
+  let date = Month::try_from(((date >> 5) & 0x0f) as u8)
+    .ok()
+    .and_then(|month| Date::from_calendar_date(1980 + (date >> 9) as i32, month, (date & 0x1f) as u8).ok());
+  let time = Time::from_hms((time >> 11) as u8, ((time >> 5) & 0x3f) as u8, ((time & 0x1f) * 2) as u8).ok();
+
+  match (date, time) {
+    (Some(date), Some(time)) => PrimitiveDateTime::new(date, time).assume_utc().unix_timestamp().max(0) as u64,
+    _ => 0,
+  }
+}
@@ -0,0 +1,279 @@
+use std::{collections::{HashMap, HashSet}, io, path::{Path, PathBuf}, sync::{mpsc::Receiver, Arc, LazyLock, Mutex, MutexGuard, PoisonError}, thread, time::Duration};
+
+use json_builder::{Json, JsonBuilder, JsonObject};
+
+use crate::config::constants::{USAGE_CACHE_ENTRIES, USAGE_DRAIN_SECS, USAGE_MAX_TOP};
+use crate::config::utility::escape_json;
+use crate::library::events::{Events, FsEvent};
+use crate::library::tasks::{Task, TaskState};
+use crate::library::vfs::{EntryKind, Stat, Vfs};
+
+static CACHE: LazyLock<Mutex<UsageCache>> = LazyLock::new(|| Mutex::new(UsageCache::new()));
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Totals {
+  pub apparent: u64,
+  pub allocated: u64,
+  pub files: u64,
+  pub directories: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct UsageNode {
+  pub name: String,
+  pub is_directory: bool,
+  pub totals: Totals,
+  pub children: Vec<UsageNode>,
+  // CHILDREN PAST USAGE_MAX_TOP ARE ONLY KEPT AS A SUM TO BOUND THE MEMORY OF HUGE TREES
+  pub other: Totals,
+  pub other_count: u64,
+  pub errors: u64,
+}
+
+// EVERY EVENT IS APPLIED BEFORE THE CACHE IS READ, SO A HIT IS NEVER OLDER THAN THE LAST UPLOAD OR DELETE.
+// TREES ARE KEYED BY WHERE THEY LIVE ON DISK, SINCE THAT IS WHAT EVENTS NAME
+pub struct UsageCache {
+  entries: HashMap<PathBuf, Arc<UsageNode>>,
+  running: HashMap<PathBuf, String>,
+  events: Receiver<FsEvent>,
+  generation: u64,
+}
+
+pub struct Usage;
+
+impl Totals {
+  fn add(&mut self, other: &Totals) {
+    self.apparent += other.apparent;
+    self.allocated += other.allocated;
+    self.files += other.files;
+    self.directories += other.directories;
+  }
+}
+
+impl UsageNode {
+  // CHILDREN PAST `top` ARE FOLDED INTO A SINGLE `other` ENTRY, LEVELS BELOW `depth` ARE LEFT OUT
+  pub fn to_json(&self, top: usize, depth: usize) -> JsonObject {
+    let mut json_object = Json::object();
+    json_object.insert("name", escape_json(&self.name));
+    json_object.insert("type", if self.is_directory { "directory" } else { "file" });
+    json_object.insert("apparent_size", self.totals.apparent);
+    json_object.insert("allocated_size", self.totals.allocated);
+    if self.is_directory {
+      json_object.insert("files", self.totals.files);
+      json_object.insert("directories", self.totals.directories);
+    }
+    if self.errors > 0 {
+      json_object.insert("errors", self.errors);
+    }
+
+    if !self.is_directory || depth == 0 {
+      return json_object;
+    }
+
+    let mut json_array = Json::array();
+    for child in self.children.iter().take(top) {
+      json_array.append(child.to_json(top, depth - 1));
+    }
+    json_object.insert("children", json_array);
+
+    let (mut other, mut other_count) = (self.other, self.other_count);
+    for child in self.children.iter().skip(top) {
+      other.add(&child.totals);
+      other_count += 1;
+    }
+
+    if other_count > 0 {
+      let mut other_object = Json::object();
+      other_object.insert("count", other_count);
+      other_object.insert("apparent_size", other.apparent);
+      other_object.insert("allocated_size", other.allocated);
+      other_object.insert("files", other.files);
+      json_object.insert("other", other_object);
+    }
+    json_object
+  }
+}
+
+impl Usage {
+  // LIKE `du`, LINKS ARE NOT FOLLOWED AND A FILE WITH SEVERAL HARD LINKS IS COUNTED AT THE FIRST ONE
+  pub fn scan(vfs: &Vfs, root: &str, task: Option<&Task>) -> io::Result<UsageNode> {
+    let stat = vfs.stat(root)?;
+    let name = Vfs::normalize(root).and_then(|p| p.rsplit('/').next().map(String::from)).unwrap_or_default();
+    let mut seen = HashSet::new();
+    match stat.kind {
+      EntryKind::Directory => Usage::scan_directory(vfs, root, name, &stat, &mut seen, task),
+      _ => Ok(Usage::leaf(name, &stat, &mut seen)),
+    }
+  }
+
+  pub fn cached(root: &Path) -> Option<Arc<UsageNode>> {
+    let mut cache = Usage::cache();
+    cache.invalidate();
+    cache.entries.get(root).cloned()
+  }
+
+  // A SCAN THAT RAN WHILE SOMETHING CHANGED MIGHT HAVE MISSED IT, SO ITS RESULT IS NOT KEPT
+  pub fn store(root: &Path, node: Arc<UsageNode>, generation: u64) {
+    let mut cache = Usage::cache();
+    cache.invalidate();
+    cache.running.remove(root);
+    if cache.generation != generation {
+      return;
+    }
+
+    if cache.entries.len() >= USAGE_CACHE_ENTRIES && !cache.entries.contains_key(root) {
+      if let Some(key) = cache.entries.keys().next().cloned() {
+        cache.entries.remove(&key);
+      }
+    }
+    cache.entries.insert(root.to_path_buf(), node);
+  }
+
+  pub fn generation() -> u64 {
+    let mut cache = Usage::cache();
+    cache.invalidate();
+    cache.generation
+  }
+
+  // ONE SCAN PER DIRECTORY AT A TIME, A SECOND REQUEST IS POINTED AT THE SCAN ALREADY RUNNING
+  pub fn running(root: &Path) -> Option<Arc<Task>> {
+    Usage::cache().running.get(root).and_then(|id| Task::find(id)).filter(|t| t.state() == TaskState::Running)
+  }
+
+  pub fn set_running(root: &Path, id: &str) {
+    Usage::cache().running.insert(root.to_path_buf(), id.to_owned());
+  }
+
+  pub fn forget(root: &Path) {
+    Usage::cache().running.remove(root);
+  }
+}
+
+impl Usage {
+  fn scan_directory(vfs: &Vfs, path: &str, name: String, stat: &Stat, seen: &mut HashSet<(u64, u64)>, task: Option<&Task>) -> io::Result<UsageNode> {
+    if let Some(task) = task {
+      task.checkpoint()?;
+      task.advance(1);
+    }
+
+    let mut node = UsageNode { name, is_directory: true, ..UsageNode::default() };
+    node.totals = Totals { apparent: stat.size, allocated: stat.allocated, files: 0, directories: 0 };
+
+    for entry in vfs.list(path)? {
+      let child = match entry.stat.kind == EntryKind::Directory {
+        true => match Usage::scan_directory(vfs, &Vfs::join(path, &entry.name), entry.name, &entry.stat, seen, task) {
+          Ok(mut child) => {
+            child.totals.directories += 1;
+            child
+          },
+          Err(e) if e.kind() == io::ErrorKind::Interrupted => return Err(e),
+          Err(_) => {
+            node.errors += 1;
+            continue;
+          },
+        },
+        false => {
+          if let Some(task) = task {
+            task.advance(1);
+          }
+          Usage::leaf(entry.name, &entry.stat, seen)
+        },
+      };
+
+      node.totals.add(&child.totals);
+      node.errors += child.errors;
+      node.children.push(child);
+    }
+
+    node.children.sort_by(|a, b| b.totals.allocated.cmp(&a.totals.allocated).then(b.totals.apparent.cmp(&a.totals.apparent)).then(a.name.cmp(&b.name)));
+    for child in node.children.split_off(USAGE_MAX_TOP.min(node.children.len())) {
+      node.other.add(&child.totals);
+      node.other_count += 1;
+    }
+    Ok(node)
+  }
+
+  fn leaf(name: String, stat: &Stat, seen: &mut HashSet<(u64, u64)>) -> UsageNode {
+    let is_counted = stat.links <= 1 || seen.insert((stat.device, stat.inode));
+    let totals = match is_counted {
+      true => Totals { apparent: stat.size, allocated: stat.allocated, files: 1, directories: 0 },
+      false => Totals::default(),
+    };
+    UsageNode { name, totals, ..UsageNode::default() }
+  }
+
+  fn cache() -> MutexGuard<'static, UsageCache> {
+    CACHE.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+}
+
+impl UsageCache {
+  // READS DRAIN THE EVENTS TOO, THE THREAD KEEPS THEM FROM PILING UP WHILE NOBODY ASKS
+  fn new() -> Self {
+    thread::spawn(|| loop {
+      thread::sleep(Duration::from_secs(USAGE_DRAIN_SECS));
+      Usage::cache().invalidate();
+    });
+    UsageCache { entries: HashMap::new(), running: HashMap::new(), events: Events::subscribe(), generation: 0 }
+  }
+
+  // A CHANGE INSIDE A SCANNED TREE DROPS IT, AND SO DOES REMOVING ONE OF ITS ANCESTORS
+  fn invalidate(&mut self) {
+    while let Ok(event) = self.events.try_recv() {
+      let (FsEvent::Changed(path) | FsEvent::Removed(path)) = event;
+      self.generation += 1;
+      self.entries.retain(|root, _| !path.starts_with(root) && !root.starts_with(&path));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{fs, sync::Arc};
+
+  use json_builder::{Json, JsonBuilder};
+
+  use crate::library::events::{Events, FsEvent};
+  use crate::library::testing::{local_vfs, TempDir};
+
+  use super::Usage;
+
+  #[test]
+  fn usage_scan_test() {
+    let root = TempDir::new("usage");
+    root.write("big/nested/data.bin", vec![7; 10_000]);
+    root.write("small/note.txt", "note");
+    fs::hard_link(root.join("big/nested/data.bin"), root.join("small/link.bin")).unwrap();
+    let node = Usage::scan(&local_vfs(&root), "/", None).unwrap();
+    assert_eq!(node.totals.files, 2);
+    assert_eq!(node.totals.directories, 3);
+    assert_eq!(node.totals.apparent - node.children.iter().map(|c| c.totals.apparent).sum::<u64>(), fs::metadata(root.path()).unwrap().len());
+    assert_eq!(node.children.iter().map(|c| c.totals.files).sum::<u64>(), 2);
+
+    let apparent_files: u64 = 10_000 + 4;
+    let directory_sizes: u64 = ["", "big", "big/nested", "small"].iter().map(|d| fs::metadata(root.join(d)).unwrap().len()).sum();
+    assert_eq!(node.totals.apparent, apparent_files + directory_sizes);
+  }
+
+  #[test]
+  fn usage_json_test() {
+    let root = TempDir::new("usage");
+    root.write("big/nested/data.bin", vec![7; 10_000]);
+    root.write("small/note.txt", "note");
+    let json = Json::build(Usage::scan(&local_vfs(&root), "/", None).unwrap().to_json(1, 1));
+    assert!(json.contains("\"other\""));
+    assert!(!json.contains("nested"));
+  }
+
+  #[test]
+  fn usage_cache_test() {
+    let root = TempDir::new("usage");
+    root.write("note.txt", "note");
+    let generation = Usage::generation();
+    Usage::store(root.path(), Arc::new(Usage::scan(&local_vfs(&root), "/", None).unwrap()), generation);
+    assert!(Usage::cached(root.path()).is_some());
+
+    Events::publish(FsEvent::Changed(root.join("small/new.txt")));
+    assert!(Usage::cached(root.path()).is_none());
+  }
+}
@@ -0,0 +1,466 @@
+use std::{fs::{self, File}, io::{self, BufRead, BufReader, Read, Seek, Write}, os::unix::fs::MetadataExt, path::{Component, Path, PathBuf}, sync::{atomic::{AtomicU64, Ordering}, LazyLock, Mutex, PoisonError}, time::{SystemTime, UNIX_EPOCH}};
+
+use logger_main::Logger;
+
+use crate::config::constants::{STORAGE_ROOT, VERSION_KEEP_COUNT, VERSION_KEEP_DAYS, VERSION_MAX_FILE_SIZE, VERSION_ROOTS, VERSION_STORE};
+use crate::library::checksum::{Algorithm, Hasher};
+use crate::library::chunks::{ChunkStore, Manifest};
+use crate::library::storage::Storage;
+use crate::library::vfs::{Backend, EntryKind};
+
+static VERSIONS: LazyLock<VersionStore> = LazyLock::new(|| VersionStore::new(PathBuf::from(VERSION_STORE), PathBuf::from(STORAGE_ROOT), VERSION_ROOTS));
+static STAGED: AtomicU64 = AtomicU64::new(0);
+
+const CATALOG_HEADER: &str = "FMVERSIONS 1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionReason {
+  Overwrite,
+  Delete,
+  Restore,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+  pub id: u64,
+  pub created: u64,
+  pub modified: u64,
+  pub size: u64,
+  pub digest: String,
+  pub reason: VersionReason,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrunePolicy {
+  pub keep: Option<usize>,
+  pub max_age: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+pub struct PruneReport {
+  pub versions: u64,
+  pub objects: u64,
+  pub bytes: u64,
+}
+
+// CONTENT LIVES ONCE UNDER objects/ BY ITS SHA-256, EVERY VERSION IS A HARD LINK TO IT UNDER paths/,
+// SO AN OBJECT THAT IS DOWN TO ONE LINK IS NO LONGER USED BY ANY VERSION
+pub struct VersionStore {
+  root: PathBuf,
+  storage: PathBuf,
+  roots: Vec<String>,
+  lock: Mutex<()>,
+}
+
+impl VersionReason {
+  pub fn from(value: &str) -> Option<Self> {
+    match value {
+      "overwrite" => Some(VersionReason::Overwrite),
+      "delete" => Some(VersionReason::Delete),
+      "restore" => Some(VersionReason::Restore),
+      _ => None,
+    }
+  }
+
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      VersionReason::Overwrite => "overwrite",
+      VersionReason::Delete => "delete",
+      VersionReason::Restore => "restore",
+    }
+  }
+}
+
+impl PrunePolicy {
+  pub fn configured() -> Self {
+    PrunePolicy {
+      keep: Some(VERSION_KEEP_COUNT).filter(|k| *k > 0),
+      max_age: Some(VERSION_KEEP_DAYS * 24 * 60 * 60).filter(|a| *a > 0),
+    }
+  }
+}
+
+impl VersionStore {
+  pub fn new(root: PathBuf, storage: PathBuf, roots: &[&str]) -> Self {
+    let roots = roots.iter().map(|r| r.trim_matches('/').to_owned()).collect();
+    VersionStore { root, storage, roots, lock: Mutex::new(()) }
+  }
+
+  pub fn global() -> &'static VersionStore {
+    &VERSIONS
+  }
+
+  // THE PATH UNDER THE STORAGE ROOT THAT VERSIONS ARE KEPT FOR, NONE WHEN THE FILE IS NOT IN A VERSIONED ROOT
+  pub fn key(&self, target: &Path) -> Option<String> {
+    let relative = target.strip_prefix(&self.storage).ok()?;
+    let names: Vec<String> = relative.components().map(|c| match c {
+      Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+      _ => None,
+    }).collect::<Option<_>>()?;
+    self.key_of(&names.join("/"))
+  }
+
+  // THE SAME FOR A PATH THAT IS NOT ON DISK
+  pub fn key_of(&self, path: &str) -> Option<String> {
+    let key = path.split('/').filter(|n| !n.is_empty()).collect::<Vec<_>>().join("/");
+    let is_versioned = self.roots.iter().any(|root| root.is_empty() || key == *root || key.starts_with(&format!("{}/", root)));
+    is_versioned.then(|| format!("/{}", key))
+  }
+
+  // COPIES THE CURRENT CONTENT AWAY, A MISSING OR UNVERSIONED FILE IS NOT AN ERROR
+  pub fn snapshot(&self, target: &Path, reason: VersionReason) -> io::Result<Option<Version>> {
+    let Some(key) = self.key(target) else {
+      return Ok(None);
+    };
+
+    let metadata = match fs::symlink_metadata(target) {
+      Ok(metadata) if metadata.is_file() => metadata,
+      _ => return Ok(None),
+    };
+
+    if metadata.len() > VERSION_MAX_FILE_SIZE {
+      Logger::warn(format!("Versions - {} is too large to keep a version of", key));
+      return Ok(None);
+    }
+
+    let modified = metadata.modified().ok().and_then(|m| m.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_secs()).unwrap_or(0);
+    self.record(&key, File::open(target)?, modified, reason).map(Some)
+  }
+
+  // A SNAPSHOT OF THE CONTENT THE NEWEST VERSION ALREADY HOLDS IS NOT KEPT TWICE
+  pub fn record(&self, key: &str, content: impl Read, modified: u64, reason: VersionReason) -> io::Result<Version> {
+    let staged = self.stage(content)?;
+    let _guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut versions = self.read_catalog(key)?;
+    if let Some(newest) = versions.last().filter(|v| v.digest == staged.1) {
+      fs::remove_file(&staged.0)?;
+      return Ok(newest.clone());
+    }
+
+    let object = self.object(&staged.1);
+    match fs::symlink_metadata(&object) {
+      Ok(_) => fs::remove_file(&staged.0)?,
+      Err(_) => {
+        fs::create_dir_all(object.parent().unwrap_or(&self.root))?;
+        fs::rename(&staged.0, &object)?;
+      },
+    }
+
+    let version = Version {
+      id: versions.iter().map(|v| v.id).max().unwrap_or(0) + 1,
+      created: VersionStore::now(),
+      modified,
+      size: staged.2,
+      digest: staged.1,
+      reason,
+    };
+
+    let directory = self.directory(key);
+    fs::create_dir_all(&directory)?;
+    fs::hard_link(&object, directory.join(version.id.to_string()))?;
+    versions.push(version.clone());
+
+    self.apply(key, versions, PrunePolicy::configured(), &mut PruneReport::default())?;
+    Ok(version)
+  }
+
+  // EVERY FILE UNDER A DIRECTORY THAT IS ABOUT TO BE DELETED
+  pub fn snapshot_tree(&self, target: &Path, reason: VersionReason) -> io::Result<u64> {
+    let mut count = 0;
+    for (path, _) in Storage::files(target) {
+      count += self.snapshot(&path, reason)?.map_or(0, |_| 1);
+    }
+    Ok(count)
+  }
+
+  // NEWEST FIRST
+  pub fn list(&self, key: &str) -> io::Result<Vec<Version>> {
+    let _guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut versions = self.read_catalog(key)?;
+    versions.reverse();
+    Ok(versions)
+  }
+
+  pub fn open(&self, key: &str, id: u64) -> io::Result<(Version, File)> {
+    let _guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+    let version = self.read_catalog(key)?.into_iter().find(|v| v.id == id).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} has no version {}", key, id)))?;
+    let file = File::open(self.directory(key).join(id.to_string()))?;
+    Ok((version, file))
+  }
+
+  // A VERSION OF A CHUNKED FILE IS ITS MANIFEST, WHAT IS READ IS THE CONTENT IT STANDS FOR.
+  // ONE KEPT BEFORE ITS ROOT WAS CHUNKED IS STILL THE CONTENT ITSELF
+  pub fn content(&self, key: &str, id: u64, chunked: bool) -> io::Result<(Version, u64, Box<dyn Read + Send>)> {
+    let (version, mut file) = self.open(key, id)?;
+    if chunked {
+      let mut text = String::new();
+      let manifest = file.read_to_string(&mut text).ok().and_then(|_| Manifest::parse(&text));
+      if let Some(manifest) = manifest {
+        let chunks = ChunkStore::global();
+        return Ok((version, manifest.size, Box::new(chunks.reader(&manifest, 0, manifest.size))));
+      }
+      file.rewind()?;
+    }
+    let size = version.size;
+    Ok((version, size, Box::new(file)))
+  }
+
+  // THE CURRENT CONTENT BECOMES A VERSION OF ITS OWN FIRST, SO A RESTORE CAN BE UNDONE. A FILE ON DISK IS KEPT THE WAY IT IS STORED,
+  // THE SNAPSHOT ITS OWN WRITE TAKES THEN HOLDS THE SAME CONTENT AND IS NOT KEPT AGAIN
+  pub fn restore(&self, key: &str, id: u64, backend: &dyn Backend, path: &str, chunked: bool) -> io::Result<Version> {
+    let (version, _, mut content) = self.content(key, id, chunked)?;
+    match backend.local_path(path) {
+      Some(target) => drop(self.snapshot(&target, VersionReason::Restore)?),
+      None => match backend.stat(path) {
+        Ok(stat) if stat.kind == EntryKind::File && stat.size <= VERSION_MAX_FILE_SIZE => {
+          let modified = stat.modified.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+          self.record(key, backend.open_read(path, 0)?, modified, VersionReason::Restore)?;
+        },
+        _ => {},
+      },
+    }
+
+    if let Some((parent, _)) = path.trim_matches('/').rsplit_once('/') {
+      backend.mkdir(parent)?;
+    }
+    let mut handle = backend.open_write(path)?;
+    io::copy(&mut content, &mut handle)?;
+    handle.commit()?;
+    Ok(version)
+  }
+
+  // ONE PATH WHEN A KEY IS GIVEN, OTHERWISE EVERY PATH THAT HAS VERSIONS
+  pub fn prune(&self, key: Option<&str>, policy: PrunePolicy) -> io::Result<PruneReport> {
+    let _guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut report = PruneReport::default();
+    let keys = match key {
+      Some(key) => vec![key.to_owned()],
+      None => self.keys()?,
+    };
+
+    for key in keys {
+      let versions = self.read_catalog(&key)?;
+      self.apply(&key, versions, policy, &mut report)?;
+    }
+    Ok(report)
+  }
+}
+
+impl VersionStore {
+  fn apply(&self, key: &str, versions: Vec<Version>, policy: PrunePolicy, report: &mut PruneReport) -> io::Result<()> {
+    let (now, count) = (VersionStore::now(), versions.len());
+    let (mut kept, mut pruned) = (Vec::with_capacity(count), Vec::new());
+    for (index, version) in versions.into_iter().enumerate() {
+      let is_recent = policy.max_age.is_none_or(|age| now.saturating_sub(version.created) <= age);
+      let is_within_count = policy.keep.is_none_or(|keep| count - index <= keep);
+      match is_recent && is_within_count {
+        true => kept.push(version),
+        false => pruned.push(version),
+      }
+    }
+
+    let directory = self.directory(key);
+    for version in pruned.iter() {
+      let _ = fs::remove_file(directory.join(version.id.to_string()));
+      report.versions += 1;
+
+      let object = self.object(&version.digest);
+      if fs::symlink_metadata(&object).is_ok_and(|m| m.nlink() <= 1) {
+        fs::remove_file(&object)?;
+        report.objects += 1;
+        report.bytes += version.size;
+      }
+    }
+    self.write_catalog(key, &kept)
+  }
+
+  // COPIED AND HASHED IN ONE PASS INTO A STAGING FILE, THE OBJECT NAME IS ONLY KNOWN AT THE END
+  fn stage(&self, content: impl Read) -> io::Result<(PathBuf, String, u64)> {
+    let staging = self.root.join("staging");
+    fs::create_dir_all(&staging)?;
+    let staged = staging.join(format!("{}-{}", std::process::id(), STAGED.fetch_add(1, Ordering::Relaxed)));
+
+    match VersionStore::copy_hashed(content, &staged) {
+      Ok((digest, size)) => Ok((staged, digest, size)),
+      Err(e) => {
+        let _ = fs::remove_file(&staged);
+        Err(e)
+      },
+    }
+  }
+
+  fn copy_hashed(mut input: impl Read, staged: &Path) -> io::Result<(String, u64)> {
+    let mut output = File::create(staged)?;
+    let mut hasher = Hasher::new(Algorithm::Sha256);
+    let (mut buffer, mut size) = (vec![0; 64 * 1024], 0);
+    loop {
+      let read = match input.read(&mut buffer) {
+        Ok(0) => break,
+        Ok(read) => read,
+        Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+        Err(e) => return Err(e),
+      };
+      hasher.update(&buffer[..read]);
+      output.write_all(&buffer[..read])?;
+      size += read as u64;
+    }
+    output.sync_all()?;
+    Ok((hasher.finish(), size))
+  }
+
+  fn object(&self, digest: &str) -> PathBuf {
+    self.root.join("objects").join(&digest[..2]).join(digest)
+  }
+
+  // NAMED BY THE HASH OF THE PATH, THE CATALOG INSIDE KEEPS THE PATH ITSELF FOR `prune` OVER EVERYTHING
+  fn directory(&self, key: &str) -> PathBuf {
+    let mut hasher = Hasher::new(Algorithm::Sha256);
+    hasher.update(key.as_bytes());
+    self.root.join("paths").join(hasher.finish())
+  }
+
+  fn keys(&self) -> io::Result<Vec<String>> {
+    let mut keys = Vec::new();
+    let directories = match fs::read_dir(self.root.join("paths")) {
+      Ok(directories) => directories,
+      Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(keys),
+      Err(e) => return Err(e),
+    };
+
+    for directory in directories {
+      let catalog = directory?.path().join("catalog");
+      let Ok(file) = File::open(&catalog) else {
+        continue;
+      };
+      if let Some(Ok(line)) = BufReader::new(file).lines().nth(1) {
+        if let Some(key) = line.strip_prefix("path\t") {
+          keys.push(key.to_owned());
+        }
+      }
+    }
+    Ok(keys)
+  }
+
+  fn read_catalog(&self, key: &str) -> io::Result<Vec<Version>> {
+    let file = match File::open(self.directory(key).join("catalog")) {
+      Ok(file) => file,
+      Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+      Err(e) => return Err(e),
+    };
+
+    let mut lines = BufReader::new(file).lines();
+    if lines.next().transpose()?.as_deref() != Some(CATALOG_HEADER) {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, format!("version catalog of {} is not readable", key)));
+    }
+
+    let mut versions = Vec::new();
+    for line in lines.skip(1) {
+      let line = line?;
+      let version = VersionStore::parse_entry(&line);
+      versions.push(version.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("version catalog of {} has a bad entry", key)))?);
+    }
+    Ok(versions)
+  }
+
+  fn parse_entry(line: &str) -> Option<Version> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    let [id, created, modified, size, digest, reason] = fields.as_slice() else {
+      return None;
+    };
+
+    Some(Version {
+      id: id.parse().ok()?,
+      created: created.parse().ok()?,
+      modified: modified.parse().ok()?,
+      size: size.parse().ok()?,
+      digest: digest.to_string(),
+      reason: VersionReason::from(reason)?,
+    })
+  }
+
+  // OLDEST FIRST, REPLACED WHOLE SO A CRASH LEAVES EITHER THE OLD OR THE NEW CATALOG
+  fn write_catalog(&self, key: &str, versions: &[Version]) -> io::Result<()> {
+    let directory = self.directory(key);
+    if versions.is_empty() {
+      let _ = fs::remove_file(directory.join("catalog"));
+      let _ = fs::remove_dir(&directory);
+      return Ok(());
+    }
+
+    let mut catalog = format!("{}\npath\t{}\n", CATALOG_HEADER, key);
+    for v in versions {
+      catalog.push_str(&format!("{}\t{}\t{}\t{}\t{}\t{}\n", v.id, v.created, v.modified, v.size, v.digest, v.reason.as_str()));
+    }
+
+    let staged = directory.join("catalog.tmp");
+    fs::write(&staged, catalog)?;
+    fs::rename(&staged, directory.join("catalog"))
+  }
+
+  fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{fs, io::Read, path::PathBuf};
+
+  use crate::library::{local_backend::LocalBackend, testing::TempDir};
+
+  use super::{PrunePolicy, VersionReason, VersionStore};
+
+  // THREE SNAPSHOTS OF docs/report.txt, THE FIRST AND LAST WITH THE SAME CONTENT
+  fn store() -> (TempDir, VersionStore, PathBuf) {
+    let root = TempDir::new("versions");
+    let store = VersionStore::new(root.join("versions"), root.join("storage"), &["/docs"]);
+    let file = root.join("storage/docs/report.txt");
+    for content in ["first", "second", "first"] {
+      root.write("storage/docs/report.txt", content);
+      store.snapshot(&file, VersionReason::Overwrite).unwrap().unwrap();
+    }
+    root.write("storage/docs/report.txt", "current");
+    (root, store, file)
+  }
+
+  #[test]
+  fn version_key_test() {
+    let (root, store, file) = store();
+    assert_eq!(store.key(&file).as_deref(), Some("/docs/report.txt"));
+    assert_eq!(store.key(&root.join("storage/other.txt")), None);
+    assert_eq!(store.snapshot(&root.join("storage/docs/missing.txt"), VersionReason::Delete).unwrap(), None);
+  }
+
+  #[test]
+  fn version_snapshot_test() {
+    let (root, store, _) = store();
+    let versions = store.list("/docs/report.txt").unwrap();
+    assert_eq!(versions.iter().map(|v| v.id).collect::<Vec<_>>(), vec![3, 2, 1]);
+    assert_eq!(versions[0].digest, versions[2].digest);
+    assert_eq!(fs::read_dir(root.join("versions/objects")).unwrap().map(|d| fs::read_dir(d.unwrap().path()).unwrap().count()).sum::<usize>(), 2);
+
+    let mut content = String::new();
+    store.open("/docs/report.txt", 2).unwrap().1.read_to_string(&mut content).unwrap();
+    assert_eq!(content, "second");
+  }
+
+  #[test]
+  fn version_restore_test() {
+    let (root, store, file) = store();
+    store.restore("/docs/report.txt", 2, &LocalBackend::new(root.join("storage")), "docs/report.txt", false).unwrap();
+    assert_eq!(fs::read_to_string(&file).unwrap(), "second");
+
+    let versions = store.list("/docs/report.txt").unwrap();
+    assert_eq!(versions[0].reason, VersionReason::Restore);
+    assert_eq!(versions.len(), 4);
+  }
+
+  #[test]
+  fn version_prune_test() {
+    let (_root, store, _) = store();
+    let report = store.prune(None, PrunePolicy { keep: Some(1), max_age: None }).unwrap();
+    assert_eq!(report.versions, 2);
+    assert_eq!(report.objects, 1);
+    assert_eq!(store.list("/docs/report.txt").unwrap().len(), 1);
+  }
+}
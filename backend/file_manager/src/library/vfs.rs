@@ -0,0 +1,219 @@
+use std::{io::{self, Read, Write}, path::{Component, Path, PathBuf}, sync::{Arc, LazyLock}, time::SystemTime};
+
+use crate::config::constants::MOUNT_TABLE;
+use crate::library::local_backend::LocalBackend;
+use crate::library::memory_backend::MemoryBackend;
+
+static VFS: LazyLock<Vfs> = LazyLock::new(Vfs::configured);
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MountKind {
+  Local(&'static str),
+  Memory,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+  File,
+  Directory,
+  // LINKS AND SPECIAL FILES, ONLY SEEN IN LISTINGS SINCE `stat` FOLLOWS LINKS
+  Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stat {
+  pub kind: EntryKind,
+  pub size: u64,
+  pub modified: SystemTime,
+  pub inode: u64,
+  // PERMISSION BITS, A BACKEND THAT KEEPS NONE REPORTS 0o644 FOR FILES AND 0o755 FOR DIRECTORIES
+  pub mode: u32,
+  // HARD LINKS SHARE A DEVICE AND AN INODE, THEY ARE ONLY WORTH COMPARING WHEN `links` IS ABOVE ONE
+  pub device: u64,
+  pub links: u64,
+  pub allocated: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+  pub name: String,
+  pub stat: Stat,
+}
+
+// PATHS GIVEN TO A BACKEND ARE RELATIVE TO ITS MOUNT, NAMES JOINED BY '/', THE EMPTY PATH IS THE MOUNT ITSELF
+pub trait Backend: Send + Sync {
+  fn stat(&self, path: &str) -> io::Result<Stat>;
+  fn list(&self, path: &str) -> io::Result<Vec<DirEntry>>;
+  fn open_read(&self, path: &str, offset: u64) -> io::Result<Box<dyn Read + Send>>;
+  fn open_write(&self, path: &str) -> io::Result<Box<dyn WriteHandle + '_>>;
+  fn rename(&self, from: &str, to: &str) -> io::Result<()>;
+  // DIRECTORIES ARE REMOVED WITH EVERYTHING IN THEM
+  fn remove(&self, path: &str) -> io::Result<()>;
+  // MISSING PARENTS ARE CREATED TOO
+  fn mkdir(&self, path: &str) -> io::Result<()>;
+  // `to` BECOMES ANOTHER NAME FOR THE CONTENT OF `from`, REPLACING WHATEVER WAS THERE
+  fn link(&self, from: &str, to: &str) -> io::Result<()>;
+  // WHERE THE PATH LIVES ON DISK, FILESYSTEM EVENTS NAME FILES BY THESE PATHS
+  fn local_path(&self, path: &str) -> Option<PathBuf>;
+
+  // A BACKEND THAT KEEPS NO PERMISSIONS OR TIMES LEAVES THEM AS THEY ARE
+  fn set_mode(&self, _path: &str, _mode: u32) -> io::Result<()> {
+    Ok(())
+  }
+
+  fn set_modified(&self, _path: &str, _modified: SystemTime) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+// NOTHING SHOWS UP AT THE PATH UNTIL `commit` RETURNS, A HANDLE DROPPED BEFORE THAT THROWS ITS CONTENT AWAY
+pub trait WriteHandle: Write + Send {
+  fn commit(self: Box<Self>) -> io::Result<()>;
+}
+
+#[derive(Clone)]
+struct Mount {
+  prefix: String,
+  backend: Arc<dyn Backend>,
+}
+
+// CHEAP TO CLONE, A STREAM OR A TASK KEEPS ITS OWN COPY OF THE MOUNTS IT STARTED WITH
+#[derive(Default, Clone)]
+pub struct Vfs {
+  mounts: Vec<Mount>,
+}
+
+impl Vfs {
+  pub fn configured() -> Self {
+    MOUNT_TABLE.iter().fold(Vfs::default(), |vfs, (prefix, kind)| match kind {
+      MountKind::Local(root) => vfs.mount(prefix, Arc::new(LocalBackend::new(root))),
+      MountKind::Memory => vfs.mount(prefix, Arc::new(MemoryBackend::new())),
+    })
+  }
+
+  pub fn global() -> &'static Vfs {
+    &VFS
+  }
+
+  pub fn mount(mut self, prefix: &str, backend: Arc<dyn Backend>) -> Self {
+    let prefix = Vfs::normalize(prefix).unwrap_or_default();
+    self.mounts.retain(|m| m.prefix != prefix);
+    self.mounts.push(Mount { prefix, backend });
+    self.mounts.sort_by_key(|m| std::cmp::Reverse(m.prefix.len()));
+    self
+  }
+
+  // THE LONGEST PREFIX WINS, NONE WHEN THE PATH CLIMBS OUT WITH '..' OR NOTHING IS MOUNTED ABOVE IT
+  pub fn resolve(&self, path: &str) -> Option<(&dyn Backend, String)> {
+    let path = Vfs::normalize(path)?;
+    self.mounts.iter().find_map(|mount| {
+      let relative = match mount.prefix.is_empty() {
+        true => Some(path.as_str()),
+        false if path == mount.prefix => Some(""),
+        false => path.strip_prefix(&mount.prefix).and_then(|r| r.strip_prefix('/')),
+      };
+      relative.map(|r| (mount.backend.as_ref(), r.to_owned()))
+    })
+  }
+
+  // MOUNTS DIRECTLY BELOW A DIRECTORY, SO A LISTING SHOWS THEM EVEN WHEN THE PARENT BACKEND HAS NO SUCH ENTRY
+  pub fn mount_points(&self, path: &str) -> Vec<(String, &dyn Backend)> {
+    let Some(path) = Vfs::normalize(path) else {
+      return Vec::new();
+    };
+
+    self.mounts.iter().filter_map(|mount| {
+      let (parent, name) = mount.prefix.rsplit_once('/').unwrap_or(("", mount.prefix.as_str()));
+      (!mount.prefix.is_empty() && parent == path).then(|| (name.to_owned(), mount.backend.as_ref()))
+    }).collect()
+  }
+
+  // EVERY MOUNT WITH THE PATH IT IS SERVED AT
+  pub fn mounts(&self) -> Vec<(String, &dyn Backend)> {
+    self.mounts.iter().map(|mount| (format!("/{}", mount.prefix), mount.backend.as_ref())).collect()
+  }
+
+  pub fn stat(&self, path: &str) -> io::Result<Stat> {
+    let (backend, relative) = self.locate(path)?;
+    backend.stat(&relative)
+  }
+
+  // SORTED BY NAME, WITH THE MOUNTS DIRECTLY BELOW THE DIRECTORY
+  pub fn list(&self, path: &str) -> io::Result<Vec<DirEntry>> {
+    let (backend, relative) = self.locate(path)?;
+    let mut entries = backend.list(&relative)?;
+    for (name, mounted) in self.mount_points(path) {
+      if let (false, Ok(stat)) = (entries.iter().any(|e| e.name == name), mounted.stat("")) {
+        entries.push(DirEntry { name, stat });
+      }
+    }
+
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+  }
+
+  pub fn open_read(&self, path: &str, offset: u64) -> io::Result<Box<dyn Read + Send>> {
+    let (backend, relative) = self.locate(path)?;
+    backend.open_read(&relative, offset)
+  }
+
+  pub fn join(path: &str, name: &str) -> String {
+    format!("{}/{}", path.trim_end_matches('/'), name)
+  }
+
+  pub fn normalize(path: &str) -> Option<String> {
+    let mut names = Vec::new();
+    for component in Path::new(path).components() {
+      match component {
+        Component::Normal(c)                    => names.push(c.to_string_lossy().into_owned()),
+        Component::RootDir | Component::CurDir  => {},
+        _                                       => return None,
+      }
+    }
+    Some(names.join("/"))
+  }
+}
+
+impl Vfs {
+  fn locate(&self, path: &str) -> io::Result<(&dyn Backend, String)> {
+    self.resolve(path).ok_or_else(|| io::Error::new(io::ErrorKind::PermissionDenied, format!("{} is outside of the storage root", path)))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+
+  use crate::library::memory_backend::MemoryBackend;
+
+  use super::Vfs;
+
+  #[test]
+  fn vfs_mount_table_test() {
+    let vfs = Vfs::default()
+      .mount("/", Arc::new(MemoryBackend::new()))
+      .mount("/scratch/", Arc::new(MemoryBackend::new()))
+      .mount("/scratch/deep", Arc::new(MemoryBackend::new()));
+
+    assert_eq!(vfs.resolve("/").map(|(_, r)| r), Some(String::new()));
+    assert_eq!(vfs.resolve("/docs/a.txt").map(|(_, r)| r), Some(String::from("docs/a.txt")));
+    assert_eq!(vfs.resolve("/scratch").map(|(_, r)| r), Some(String::new()));
+    assert_eq!(vfs.resolve("/scratch/./deep/x").map(|(_, r)| r), Some(String::from("x")));
+    assert_eq!(vfs.resolve("/scratchpad").map(|(_, r)| r), Some(String::from("scratchpad")));
+    assert!(vfs.resolve("/scratch/../etc").is_none());
+
+    let (root, scratch) = (vfs.resolve("/a").unwrap().0, vfs.resolve("/scratch/a").unwrap().0);
+    root.mkdir("a").unwrap();
+    assert!(root.stat("a").is_ok());
+    assert!(scratch.stat("a").is_err());
+
+    let names: Vec<String> = vfs.mount_points("/").into_iter().map(|(name, _)| name).collect();
+    assert_eq!(names, vec![String::from("scratch")]);
+    let listed: Vec<String> = vfs.list("/").unwrap().into_iter().map(|entry| entry.name).collect();
+    assert_eq!(listed, vec![String::from("a"), String::from("scratch")]);
+    assert_eq!(Vfs::join("/", "a"), "/a");
+    assert_eq!(Vfs::join("/scratch/", "a"), "/scratch/a");
+    assert!(Vfs::default().resolve("/a").is_none());
+  }
+}
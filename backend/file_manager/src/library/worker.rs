@@ -1,8 +1,9 @@
-use std::{sync::{mpsc, Arc, Mutex}, thread::{self, JoinHandle}};
+use std::{panic::{self, AssertUnwindSafe}, sync::{atomic::Ordering, mpsc, Arc}, thread::{self, JoinHandle}};
 
 use logger_main::Logger;
 
-use super::job::Job;
+use super::job_queue::JobQueue;
+use super::tp::PoolState;
 
 pub struct Worker {
   pub id: usize,
@@ -10,18 +11,49 @@ pub struct Worker {
 }
 
 impl Worker {
-  pub fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<Job>>>) -> Self {
+  pub fn new(id: usize, queue: Arc<JobQueue>, state: Arc<PoolState>) -> Self {
     Logger::info(format!("Thread Pool - Worker: {}", id).as_str());
+    state.alive.fetch_add(1, Ordering::SeqCst);
     let thread = thread::spawn(move || loop {
-      let lock = receiver.lock().unwrap().recv();
-      match lock {
-        Err(_) => {
+      match queue.pop(state.keep_alive) {
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+          if state.try_retire() {
+            Logger::info(format!("Thread Pool - Worker {id} - Idle past keep-alive, retiring").as_str());
+            break;
+          }
+        },
+        Err(mpsc::RecvTimeoutError::Disconnected) => {
           Logger::warn("Thread Pool - Worker - Disconnected!");
+          state.alive.fetch_sub(1, Ordering::SeqCst);
           break;
         },
-        Ok(job) => {
-          Logger::info(format!("Thread Pool - Worker {id} - Executing a job").as_str()); 
-          job();
+        Ok(queued) => {
+          let waited = queued.enqueued_at.elapsed().as_micros() as u64;
+          state.queued.fetch_sub(1, Ordering::Relaxed);
+          state.wait_count.fetch_add(1, Ordering::Relaxed);
+          state.wait_total_micros.fetch_add(waited, Ordering::Relaxed);
+          state.wait_max_micros.fetch_max(waited, Ordering::Relaxed);
+
+          Logger::info(format!("Thread Pool - Worker {id} - Executing a job (queued {waited}µs)").as_str());
+          state.busy.fetch_add(1, Ordering::Relaxed);
+          let result = panic::catch_unwind(AssertUnwindSafe(queued.job));
+          state.busy.fetch_sub(1, Ordering::Relaxed);
+          queue.finish(queued.priority);
+
+          match result {
+            Ok(_) => {
+              state.completed.fetch_add(1, Ordering::Relaxed);
+            },
+            Err(cause) => {
+              state.panicked.fetch_add(1, Ordering::Relaxed);
+              let reason = cause
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| cause.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+              Logger::warn(format!("Thread Pool - Worker {id} - Job panicked: {}", reason).as_str());
+            },
+          }
         },
       }
     });
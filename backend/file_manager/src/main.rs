@@ -1,4 +1,5 @@
 use global::tcp_handler::TcpHandler;
+use library::indexer::Indexer;
 
 mod enums;
 mod parser;
@@ -9,5 +10,6 @@ mod router;
 
 // MAIN
 fn main() {
+  Indexer::start();
   TcpHandler::new().listen();
 }
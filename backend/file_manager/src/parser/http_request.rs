@@ -1,10 +1,17 @@
+use std::{collections::HashMap, net::IpAddr};
+
+use crate::config::utility::{decode_base64, parse_query, parse_query_pairs};
 use crate::enums::app_enums::HttpMethod;
 
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct HttpRequest {
   pub method: HttpMethod,
+  // AS THE CLIENT SENT THEM, `method` IS NONE FOR A METHOD THE ROUTER DOES NOT KNOW
+  pub method_token: String,
+  pub request_line: String,
   pub path: String,
+  pub target: String,
   pub http_version: String,
   pub host: String,
   pub user_agent: String,
@@ -17,75 +24,104 @@ pub struct HttpRequest {
   pub sec_fetch_mode: String,
   pub sec_fetch_site: String,
   pub priority: String,
+  pub headers: HashMap<String, String>,
+  pub query: HashMap<String, String>,
+  pub body: Vec<u8>,
+  pub remote_addr: Option<IpAddr>,
 }
 
 struct HttpRequestParser {
   request: Vec<String>,
-  temp: String,
+  headers: HashMap<String, String>,
 }
 
 impl HttpRequest {
   pub fn construct(request: Vec<String>) -> Self {
-    let mut parser = HttpRequestParser::new(request);
+    let request_line = request.first().map(|line| line.trim_end().to_owned()).unwrap_or_default();
+    let parser = HttpRequestParser::new(request);
+    let target = parser.parse_line(0, 1).1;
+    let (path, query) = target.split_once('?').unwrap_or((target.as_str(), ""));
     HttpRequest {
       method: HttpMethod::from(parser.parse_line(0, 0).1),
-      path: parser.parse_line(0, 1).1,
+      method_token: parser.parse_line(0, 0).1,
+      request_line,
+      path: path.to_owned(),
+      target: target.clone(),
       http_version: parser.parse_line(0, 2).1,
-      host: parser.parse_colon( 1).1,
-      user_agent: parser.parse_colon( 2).1,
-      accept: parser.parse_colon(3).1,
-      accept_language: parser.parse_colon(4).1,
-      accept_encoding: parser.parse_colon(5).0.separate_with(','),
-      connection: parser.parse_colon(6).1,
-      upgrade_insecure_requests: parser.parse_colon(7).1,
-      sec_fetch_dest: parser.parse_colon(8).1,
-      sec_fetch_mode: parser.parse_colon(9).1,
-      sec_fetch_site: parser.parse_colon(10).1,
-      priority: parser.parse_colon(11).1,
+      host: parser.parse_header("host"),
+      user_agent: parser.parse_header("user-agent"),
+      accept: parser.parse_header("accept"),
+      accept_language: parser.parse_header("accept-language"),
+      accept_encoding: parser.separate_with("accept-encoding", ','),
+      connection: parser.parse_header("connection"),
+      upgrade_insecure_requests: parser.parse_header("upgrade-insecure-requests"),
+      sec_fetch_dest: parser.parse_header("sec-fetch-dest"),
+      sec_fetch_mode: parser.parse_header("sec-fetch-mode"),
+      sec_fetch_site: parser.parse_header("sec-fetch-site"),
+      priority: parser.parse_header("priority"),
+      headers: parser.headers,
+      query: parse_query(query),
+      body: Vec::new(),
+      remote_addr: None,
+    }
+  }
+
+  pub fn header(&self, name: &str) -> Option<&String> {
+    self.headers.get(&name.to_ascii_lowercase())
+  }
+
+  // THE USER NAME OF `Authorization: Basic`, THE PASSWORD IS NOT CHECKED HERE
+  pub fn username(&self) -> Option<String> {
+    let (scheme, credentials) = self.header("authorization")?.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("basic") {
+      return None;
     }
+
+    let credentials = String::from_utf8(decode_base64(credentials.trim())?).ok()?;
+    let (user, _) = credentials.split_once(':')?;
+    (!user.is_empty()).then(|| user.to_owned())
+  }
+
+  pub fn param(&self, name: &str) -> Option<&String> {
+    self.query.get(name)
+  }
+
+  pub fn params(&self, name: &str) -> Vec<String> {
+    let query = self.target.split_once('?').map(|(_, q)| q).unwrap_or_default();
+    parse_query_pairs(query).into_iter().filter(|(k, _)| k == name).map(|(_, v)| v).collect()
   }
 }
 
 impl HttpRequestParser {
   fn new(request: Vec<String>) -> Self {
-    Self { request, temp: String::new() }
+    let mut headers = HashMap::new();
+    for line in request.iter().skip(1) {
+      if let Some((name, value)) = line.split_once(':') {
+        headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_owned());
+      }
+    }
+
+    Self { request, headers }
   }
 
-  fn separate_with(&self, c: char) -> Vec<String> {
-    if self.temp.is_empty() {
-      panic!("Value not found at temp! Separation failed");
+  fn separate_with(&self, name: &str, c: char) -> Vec<String> {
+    let value = self.parse_header(name);
+    if value.is_empty() {
+      return Vec::new();
     }
 
-    self.temp.split(c).map(|x| String::from(x.trim())).collect()
+    value.split(c).map(|x| String::from(x.trim())).filter(|x| !x.is_empty()).collect()
   }
 
   fn parse_line(&self, index: usize, at: usize) -> (&Self, String) {
     let value = self.request.get(index);
     return match value {
-      Some(r_value) => (self, r_value.split_whitespace().nth(at).unwrap().to_owned()),
+      Some(r_value) => (self, r_value.split_whitespace().nth(at).unwrap_or_default().to_owned()),
       None => (self, String::new()), 
     };
   }
 
-  fn parse_colon(&mut self, index: usize) -> (&Self, String) {
-    let value = self.request.get(index);
-    return match value {
-      Some(v_result) => {
-        let mut result = String::new();
-        let mut is_concat = false;
-        for c in v_result.chars() {
-          if c != ':' && !is_concat {
-            is_concat = true;
-            continue;
-          }
-
-          result.push(c);
-        }
-
-        self.temp = result.clone();
-        (self, result)
-      },
-      None => (self, String::new()),
-    }
+  fn parse_header(&self, name: &str) -> String {
+    self.headers.get(name).cloned().unwrap_or_default()
   }
-}
\ No newline at end of file
+}
@@ -1,10 +1,22 @@
+use std::io::{self, Cursor, Read, Write};
+
+use crate::enums::app_enums::ContentEncoding;
+use crate::library::compression;
+
+pub enum HttpBody {
+  Bytes(Vec<u8>),
+  Stream(Box<dyn Read + Send>),
+}
+
 pub struct HttpResponse {
   pub version: String,
   pub status: String,
   pub message: String,
   pub content_type: String,
-  pub content_length: usize,
-  pub contents: String,
+  pub content_length: Option<usize>,
+  pub headers: Vec<(String, String)>,
+  pub encoding: ContentEncoding,
+  pub contents: HttpBody,
 }
 
 impl HttpResponse {
@@ -13,35 +25,140 @@ impl HttpResponse {
   }
 
   pub fn init(
-    version: impl Into<String>, 
-    status: impl Into<String>, 
-    message: impl Into<String>, 
-    content_type: impl Into<String>, 
+    version: impl Into<String>,
+    status: impl Into<String>,
+    message: impl Into<String>,
+    content_type: impl Into<String>,
     contents: impl Into<String>
   ) -> Self {
-    let c: String = contents.into().clone();
-
-    Self { 
-      version: version.into(), 
-      status: status.into(), 
-      message: message.into(), 
-      content_type: content_type.into(), 
-      content_length: c.len(),
-      contents: c.into(),
+    let c: Vec<u8> = contents.into().into_bytes();
+
+    Self {
+      version: version.into(),
+      status: status.into(),
+      message: message.into(),
+      content_type: content_type.into(),
+      content_length: Some(c.len()),
+      headers: Vec::new(),
+      encoding: ContentEncoding::IDENTITY,
+      contents: HttpBody::Bytes(c),
+    }
+  }
+
+  pub fn stream(
+    status: impl Into<String>,
+    message: impl Into<String>,
+    content_type: impl Into<String>,
+    content_length: Option<usize>,
+    reader: impl Read + Send + 'static
+  ) -> Self {
+    Self {
+      version: String::from("HTTP/1.1"),
+      status: status.into(),
+      message: message.into(),
+      content_type: content_type.into(),
+      content_length,
+      headers: Vec::new(),
+      encoding: ContentEncoding::IDENTITY,
+      contents: HttpBody::Stream(Box::new(reader)),
     }
   }
 }
 
+impl HttpResponse {
+  pub fn header(&mut self, key: impl Into<String>, value: impl Into<String>) -> &mut Self {
+    self.headers.push((key.into(), value.into()));
+    self
+  }
+
+  pub fn get_header(&self, key: &str) -> Option<&String> {
+    self.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, v)| v)
+  }
+
+  fn is_chunked(&self) -> bool {
+    self.has_body() && (self.encoding != ContentEncoding::IDENTITY || self.content_length.is_none())
+  }
+
+  fn has_body(&self) -> bool {
+    !matches!(self.status.as_str(), "204" | "304") && !self.status.starts_with('1')
+  }
+}
+
 impl HttpResponse {
   pub fn construct(&self) -> String {
-      format!(
-        "{} {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n{}", 
-        self.version,
-        self.status,
-        self.message,
-        self.content_type, 
-        self.content_length, 
-        self.contents
-      )
-  }
-}
\ No newline at end of file
+    let mut head = format!(
+      "{} {} {}\r\nContent-Type: {}\r\n",
+      self.version,
+      self.status,
+      self.message,
+      self.content_type,
+    );
+
+    match self.content_length {
+      _ if !self.has_body() => {},
+      Some(length) if !self.is_chunked() => head.push_str(format!("Content-Length: {}\r\n", length).as_str()),
+      _ => head.push_str("Transfer-Encoding: chunked\r\n"),
+    }
+
+    for (key, value) in self.headers.iter() {
+      head.push_str(format!("{}: {}\r\n", key, value).as_str());
+    }
+
+    head.push_str("\r\n");
+    head
+  }
+
+  pub fn write_to(self, stream: &mut impl Write) -> io::Result<()> {
+    stream.write_all(self.construct().as_bytes())?;
+
+    if !self.has_body() {
+      return stream.flush();
+    }
+
+    let is_chunked = self.is_chunked();
+    let mut reader: Box<dyn Read + Send> = match self.contents {
+      HttpBody::Bytes(bytes) => Box::new(Cursor::new(bytes)),
+      HttpBody::Stream(reader) => reader,
+    };
+
+    if !is_chunked {
+      io::copy(&mut reader, stream)?;
+      return stream.flush();
+    }
+
+    let chunked = compression::encode(&self.encoding, &mut reader, ChunkedWriter::new(stream))?;
+    chunked.finish()?.flush()
+  }
+}
+
+pub struct ChunkedWriter<W: Write> {
+  inner: W,
+}
+
+impl<W: Write> ChunkedWriter<W> {
+  pub fn new(inner: W) -> Self {
+    Self { inner }
+  }
+
+  pub fn finish(mut self) -> io::Result<W> {
+    self.inner.write_all(b"0\r\n\r\n")?;
+    Ok(self.inner)
+  }
+}
+
+impl<W: Write> Write for ChunkedWriter<W> {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    if buf.is_empty() {
+      return Ok(0);
+    }
+
+    self.inner.write_all(format!("{:X}\r\n", buf.len()).as_bytes())?;
+    self.inner.write_all(buf)?;
+    self.inner.write_all(b"\r\n")?;
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    self.inner.flush()
+  }
+}
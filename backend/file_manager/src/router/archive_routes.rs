@@ -0,0 +1,255 @@
+use std::{io, path::Path};
+
+use json_builder::{Json, JsonBuilder};
+
+use crate::config::constants::ARCHIVE_MAX_SELECTION;
+use crate::config::utility::escape_json;
+use crate::enums::app_error::AppError;
+use crate::library::archive::{ArchiveEntries, ArchiveFormat};
+use crate::library::extraction::{ExtractJob, ExtractRequest};
+use crate::library::mime::Mime;
+use crate::library::unpack::{ArchiveKind, Budget, Member, MemberKind, TarReader, ZipArchive};
+use crate::library::vfs::{EntryKind, Stat, Vfs};
+use crate::parser::{http_request::HttpRequest, http_response::HttpResponse};
+use crate::router::middleware::RequestId;
+
+pub struct Archive;
+
+impl Archive {
+  pub fn download(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Archive::download_in(Vfs::global(), http_request)
+  }
+
+  pub fn list(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Archive::list_in(Vfs::global(), http_request)
+  }
+
+  pub fn member(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Archive::member_in(Vfs::global(), http_request)
+  }
+
+  pub fn extract(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Archive::extract_in(Vfs::global(), http_request)
+  }
+
+  pub fn job(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let id = http_request.param("id").ok_or_else(|| AppError::BadRequest(String::from("Query parameter `id` is required")))?;
+    let job = ExtractJob::find(id).ok_or_else(|| AppError::NotFound(format!("No extraction job {}", id)))?;
+
+    let mut http_response = HttpResponse::new("200", "OK", job.to_json());
+    http_response.header("Cache-Control", "no-store");
+    Ok(http_response)
+  }
+}
+
+impl Archive {
+  // GET /archive?path=/a&path=/b&format=zip|tar.gz, EVERY `path` BECOMES A TOP LEVEL ENTRY
+  fn download_in(vfs: &Vfs, http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let format = match http_request.param("format") {
+      Some(format) => ArchiveFormat::from(format).ok_or_else(|| AppError::BadRequest(format!("Unsupported archive format {}", format)))?,
+      None => ArchiveFormat::Zip,
+    };
+
+    let paths: Vec<String> = http_request.params("path").into_iter().filter(|p| !p.trim().is_empty()).collect();
+    if paths.is_empty() {
+      return Err(AppError::BadRequest(String::from("Query parameter `path` is required")));
+    }
+
+    if paths.len() > ARCHIVE_MAX_SELECTION {
+      return Err(AppError::BadRequest(format!("At most {} paths can be archived at once", ARCHIVE_MAX_SELECTION)));
+    }
+
+    let mut selection = Vec::with_capacity(paths.len());
+    for path in paths.iter() {
+      Archive::stat(vfs, path)?;
+      let name = Vfs::normalize(path).and_then(|p| p.rsplit('/').next().map(String::from)).unwrap_or_default();
+      selection.push((path.clone(), name));
+    }
+
+    let file_name = match (http_request.param("name"), selection.as_slice()) {
+      (Some(name), _) => name.clone(),
+      (None, [(_, name)]) if !name.is_empty() => name.clone(),
+      (None, [_]) => String::from("storage"),
+      _ => String::from("archive"),
+    };
+
+    let file_name = format!("{}.{}", file_name.replace(['"', '\\', '/', '\r', '\n'], "_"), format.extension());
+    let mut http_response = HttpResponse::stream("200", "OK", format.content_type(), None, format.stream(ArchiveEntries::new(vfs.clone(), selection)));
+    http_response
+      .header("Content-Disposition", format!("attachment; filename=\"{}\"", file_name))
+      .header("Cache-Control", "no-store")
+      .header("X-Content-Type-Options", "nosniff");
+    Ok(http_response)
+  }
+
+  fn list_in(vfs: &Vfs, http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let (path, stat, kind) = Archive::open(vfs, http_request)?;
+    let members = match kind {
+      ArchiveKind::Zip => ZipArchive::open(vfs, &path).map_err(Archive::unreadable)?.members,
+      kind => {
+        let mut reader = TarReader::open(vfs.open_read(&path, 0)?, kind, &Budget::new(stat.size))?;
+        let mut members = Vec::new();
+        while let Some(member) = reader.next_member().map_err(Archive::unreadable)? {
+          members.push(member);
+        }
+        members
+      },
+    };
+
+    let mut json_array = Json::array();
+    for member in members.iter() {
+      let mut json_object = Json::object();
+      json_object.insert("name", escape_json(&member.name));
+      json_object.insert("type", member.kind.as_str());
+      json_object.insert("size", member.size);
+      if let Some(compressed) = member.compressed {
+        json_object.insert("compressed", compressed);
+      }
+      json_object.insert("modified", member.modified);
+      json_object.insert("mode", escape_json(&format!("{:04o}", member.mode)));
+      if let Some(link) = member.link.as_deref() {
+        json_object.insert("link", escape_json(link));
+      }
+      json_array.append(json_object);
+    }
+
+    let mut json_object = Json::object();
+    json_object.insert("path", escape_json(&path));
+    json_object.insert("format", kind.as_str());
+    json_object.insert("count", members.len() as u64);
+    json_object.insert("total_size", members.iter().map(|m| m.size).sum::<u64>());
+    json_object.insert("members", json_array);
+    Ok(HttpResponse::new("200", "OK", Json::build(json_object)))
+  }
+
+  fn member_in(vfs: &Vfs, http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let (path, stat, kind) = Archive::open(vfs, http_request)?;
+    let name = http_request
+      .param("name")
+      .map(|n| n.trim_matches('/'))
+      .filter(|n| !n.is_empty())
+      .ok_or_else(|| AppError::BadRequest(String::from("Query parameter `name` is required")))?;
+
+    let not_found = || AppError::NotFound(format!("{} has no file named {}", path, name));
+    let budget = Budget::new(stat.size);
+    let (member, reader): (Member, Box<dyn io::Read + Send>) = match kind {
+      ArchiveKind::Zip => {
+        let zip = ZipArchive::open(vfs, &path).map_err(Archive::unreadable)?;
+        let member = zip.members.iter().find(|m| m.name == name && m.kind == MemberKind::File).ok_or_else(not_found)?;
+        (member.clone(), zip.reader(member, &budget).map_err(Archive::unreadable)?)
+      },
+      kind => {
+        let mut reader = TarReader::open(vfs.open_read(&path, 0)?, kind, &budget)?;
+        loop {
+          match reader.next_member().map_err(Archive::unreadable)? {
+            Some(member) if member.name == name && member.kind == MemberKind::File => break (member, Box::new(reader)),
+            Some(_) => continue,
+            None => return Err(not_found()),
+          }
+        }
+      },
+    };
+
+    let file_name = Path::new(&member.name).file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let content_type = Mime::from_extension(Path::new(&file_name)).unwrap_or("application/octet-stream");
+    let mut http_response = HttpResponse::stream("200", "OK", content_type, Some(member.size as usize), reader);
+    http_response
+      .header("Content-Disposition", format!("attachment; filename=\"{}\"", file_name.replace(['"', '\\', '\r', '\n'], "_")))
+      .header("X-Content-Type-Options", "nosniff");
+    Ok(http_response)
+  }
+
+  // RUNS IN THE BACKGROUND, THE CLIENT POLLS THE JOB IT GETS BACK
+  fn extract_in(vfs: &Vfs, http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let (path, _, kind) = Archive::open(vfs, http_request)?;
+    let target_path = match http_request.param("target").map(|t| t.trim()).filter(|t| !t.is_empty()) {
+      Some(target) => target.to_owned(),
+      None => {
+        let name = path.trim_end_matches('/');
+        let stem = [".tar.gz", ".tar.xz", ".tgz", ".txz", ".tar", ".zip"]
+          .iter()
+          .find_map(|suffix| name.len().checked_sub(suffix.len()).filter(|at| name[*at..].eq_ignore_ascii_case(suffix)).map(|at| &name[..at]))
+          .unwrap_or(name);
+        format!("{}.extracted", stem)
+      },
+    };
+
+    // A MOUNT POINT IS NEVER A TARGET, EXTRACTING OVER A WHOLE MOUNT IS TOO EASY TO DO BY ACCIDENT
+    let (backend, relative) = vfs.resolve(&target_path).ok_or_else(|| AppError::Forbidden(format!("{} is outside of the storage root", target_path)))?;
+    if relative.is_empty() {
+      return Err(AppError::Forbidden(format!("{} cannot be used as an extraction target", target_path)));
+    }
+
+    if backend.stat(&relative).is_ok_and(|s| s.kind != EntryKind::Directory) {
+      return Err(AppError::Conflict(format!("{} is not a directory", target_path)));
+    }
+
+    let request = ExtractRequest {
+      vfs: vfs.clone(),
+      archive: path.clone(),
+      kind,
+      target: target_path.clone(),
+      members: http_request.params("member").into_iter().filter(|m| !m.trim_matches('/').is_empty()).collect(),
+      overwrite: http_request.param("overwrite").is_some_and(|o| o == "true"),
+    };
+
+    let job = ExtractJob::spawn(RequestId::generate(), &path, &target_path, request)?;
+    let mut http_response = HttpResponse::new("202", "Accepted", job.to_json());
+    http_response.header("Location", format!("/archive/jobs?id={}", job.id));
+    Ok(http_response)
+  }
+
+  fn stat(vfs: &Vfs, path: &str) -> Result<Stat, AppError> {
+    let (backend, relative) = vfs.resolve(path).ok_or_else(|| AppError::Forbidden(format!("{} is outside of the storage root", path)))?;
+    backend.stat(&relative).map_err(|_| AppError::NotFound(format!("{} does not exist", path)))
+  }
+
+  fn open(vfs: &Vfs, http_request: &HttpRequest) -> Result<(String, Stat, ArchiveKind), AppError> {
+    let path = http_request
+      .param("path")
+      .filter(|p| !p.trim_matches('/').is_empty())
+      .ok_or_else(|| AppError::BadRequest(String::from("Query parameter `path` is required")))?;
+
+    let stat = Archive::stat(vfs, path)?;
+    if stat.kind != EntryKind::File {
+      return Err(AppError::BadRequest(format!("{} is not a file", path)));
+    }
+
+    let kind = ArchiveKind::detect(vfs, path).ok_or_else(|| AppError::BadRequest(format!("{} is not a zip, tar, tar.gz or tar.xz archive", path)))?;
+    Ok((path.clone(), stat, kind))
+  }
+
+  fn unreadable(e: io::Error) -> AppError {
+    match e.kind() {
+      io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof | io::ErrorKind::Unsupported => AppError::BadRequest(e.to_string()),
+      _ => AppError::Io(e),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{io::Read, sync::Arc};
+
+  use crate::library::{memory_backend::MemoryBackend, testing::{content, put, request}, unpack::{Budget, ZipArchive}, vfs::Vfs};
+
+  use super::Archive;
+
+  #[test]
+  fn archive_memory_test() {
+    let backend = Arc::new(MemoryBackend::new());
+    put(backend.as_ref(), "docs/a.txt", b"hello");
+    put(backend.as_ref(), "docs/sub/b.txt", b"world");
+    let vfs = Vfs::default().mount("/", backend.clone());
+
+    let zip = content(Archive::download_in(&vfs, &request("GET /archive?path=/docs", b"")).unwrap());
+    put(backend.as_ref(), "docs.zip", &zip);
+    let archive = ZipArchive::open(&vfs, "/docs.zip").unwrap();
+    let member = archive.members.iter().find(|m| m.name == "docs/sub/b.txt").unwrap();
+
+    let mut text = String::new();
+    archive.reader(member, &Budget::new(zip.len() as u64)).unwrap().read_to_string(&mut text).unwrap();
+    assert_eq!(text, "world");
+    assert!(archive.members.iter().any(|m| m.name == "docs/a.txt"));
+  }
+}
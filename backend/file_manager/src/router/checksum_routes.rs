@@ -0,0 +1,187 @@
+use json_builder::{Json, JsonBuilder, JsonObject};
+
+use crate::config::constants::CHECKSUM_INLINE_LIMIT;
+use crate::config::utility::escape_json;
+use crate::enums::app_error::AppError;
+use crate::library::checksum::{hash, parse_manifest, verify, Algorithm, ManifestStream};
+use crate::library::tasks::{Task, TaskResult};
+use crate::library::vfs::{EntryKind, Stat, Vfs};
+use crate::parser::{http_request::HttpRequest, http_response::HttpResponse};
+use crate::router::middleware::RequestId;
+
+pub struct Checksum;
+
+impl Checksum {
+  pub fn file(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Checksum::file_in(Vfs::global(), http_request)
+  }
+
+  pub fn manifest(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Checksum::manifest_in(Vfs::global(), http_request)
+  }
+
+  pub fn verify(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Checksum::verify_in(Vfs::global(), http_request)
+  }
+}
+
+impl Checksum {
+  // SMALL FILES ARE ANSWERED RIGHT AWAY, ANYTHING LARGER (OR `async=true`) BECOMES A TASK
+  fn file_in(vfs: &Vfs, http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let algorithm = Checksum::algorithm(http_request)?.unwrap_or(Algorithm::Sha256);
+    let (path, stat) = Checksum::resolve(vfs, http_request)?;
+    if stat.kind != EntryKind::File {
+      return Err(AppError::BadRequest(format!("{} is not a file", path)));
+    }
+
+    let is_async = http_request.param("async").is_some_and(|a| a == "true");
+    if !is_async && stat.size <= CHECKSUM_INLINE_LIMIT {
+      let digest = hash(vfs.open_read(&path, 0)?, algorithm, None)?;
+      let mut http_response = HttpResponse::new("200", "OK", Json::build(Checksum::digest(&path, algorithm, stat.size, &digest)));
+      http_response.header("Cache-Control", "no-store");
+      return Ok(http_response);
+    }
+
+    let (vfs, subject) = (vfs.clone(), path.clone());
+    let task = Task::spawn(RequestId::generate(), "checksum", &path, move |task| {
+      task.set_total(stat.size);
+      let digest = hash(vfs.open_read(&subject, 0)?, algorithm, Some(task))?;
+      Ok(Box::new(move || Checksum::digest(&subject, algorithm, stat.size, &digest)) as TaskResult)
+    })?;
+    Ok(Checksum::accepted(&task))
+  }
+
+  // `sha256sum`-COMPATIBLE LISTING OF EVERY FILE UNDER `path`, RELATIVE TO IT
+  fn manifest_in(vfs: &Vfs, http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let algorithm = Checksum::algorithm(http_request)?.unwrap_or(Algorithm::Sha256);
+    let (path, stat) = Checksum::resolve(vfs, http_request)?;
+    if stat.kind != EntryKind::Directory {
+      return Err(AppError::BadRequest(format!("{} is not a directory", path)));
+    }
+
+    let stream = ManifestStream::new(vfs.clone(), path, algorithm);
+    let mut http_response = HttpResponse::stream("200", "OK", "text/plain; charset=utf-8", None, Box::new(stream));
+    http_response
+      .header("Cache-Control", "no-store")
+      .header("X-Checksum-Algorithm", algorithm.as_str());
+    Ok(http_response)
+  }
+
+  // THE MANIFEST IS THE REQUEST BODY, THE ALGORITHM FOLLOWS FROM THE DIGEST LENGTH UNLESS `algo` IS GIVEN
+  fn verify_in(vfs: &Vfs, http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let (path, stat) = Checksum::resolve(vfs, http_request)?;
+    if stat.kind != EntryKind::Directory {
+      return Err(AppError::BadRequest(format!("{} is not a directory", path)));
+    }
+
+    let text = String::from_utf8(http_request.body.clone()).map_err(|_| AppError::BadRequest(String::from("Manifest is not valid UTF-8")))?;
+    let entries = parse_manifest(&text).map_err(AppError::BadRequest)?;
+    let algorithm = match (Checksum::algorithm(http_request)?, entries.first()) {
+      (Some(algorithm), _) => algorithm,
+      (None, Some((digest, _))) => Algorithm::from_digest_length(digest.len()).ok_or_else(|| AppError::BadRequest(String::from("Cannot tell the algorithm from the digests, pass `algo`")))?,
+      (None, None) => return Err(AppError::BadRequest(String::from("Manifest is empty"))),
+    };
+
+    let (vfs, root) = (vfs.clone(), path.clone());
+    let task = Task::spawn(RequestId::generate(), "verify", &path, move |task| {
+      let report = verify(&vfs, &root, algorithm, &entries, task)?;
+      Ok(Box::new(move || {
+        let list = |names: &[String]| {
+          let mut json_array = Json::array();
+          for name in names {
+            let mut json_object = Json::object();
+            json_object.insert("path", escape_json(name));
+            json_array.append(json_object);
+          }
+          json_array
+        };
+
+        let mut json_object = Json::object();
+        json_object.insert("algorithm", algorithm.as_str());
+        json_object.insert("ok", report.ok);
+        json_object.insert("is_valid", report.mismatched.is_empty() && report.missing.is_empty() && report.extra.is_empty() && report.unreadable.is_empty());
+        json_object.insert("mismatched", list(&report.mismatched));
+        json_object.insert("missing", list(&report.missing));
+        json_object.insert("extra", list(&report.extra));
+        json_object.insert("unreadable", list(&report.unreadable));
+        json_object
+      }) as TaskResult)
+    })?;
+    Ok(Checksum::accepted(&task))
+  }
+}
+
+impl Checksum {
+  fn algorithm(http_request: &HttpRequest) -> Result<Option<Algorithm>, AppError> {
+    match http_request.param("algo") {
+      Some(algo) => Algorithm::from(algo).map(Some).ok_or_else(|| AppError::BadRequest(format!("Unsupported checksum algorithm {}, use crc32, md5, sha1, sha256 or blake3", algo))),
+      None => Ok(None),
+    }
+  }
+
+  fn resolve(vfs: &Vfs, http_request: &HttpRequest) -> Result<(String, Stat), AppError> {
+    let path = http_request.param("path").ok_or_else(|| AppError::BadRequest(String::from("Query parameter `path` is required")))?;
+    let (backend, relative) = vfs.resolve(path).ok_or_else(|| AppError::Forbidden(format!("{} is outside of the storage root", path)))?;
+    let stat = backend.stat(&relative).map_err(|_| AppError::NotFound(format!("{} does not exist", path)))?;
+    Ok((path.clone(), stat))
+  }
+
+  fn digest(path: &str, algorithm: Algorithm, size: u64, digest: &str) -> JsonObject {
+    let mut json_object = Json::object();
+    json_object.insert("path", escape_json(path));
+    json_object.insert("algorithm", algorithm.as_str());
+    json_object.insert("size", size);
+    json_object.insert("digest", digest);
+    json_object
+  }
+
+  fn accepted(task: &Task) -> HttpResponse {
+    let mut http_response = HttpResponse::new("202", "Accepted", task.to_json());
+    http_response.header("Location", format!("/tasks?id={}", task.id));
+    http_response
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{fs, sync::Arc};
+
+  use crate::library::{checksum::{hash, Algorithm}, chunks::ChunkStore, local_backend::LocalBackend, memory_backend::MemoryBackend, vfs::Vfs};
+  use crate::library::testing::{content, noise, put, request, TempDir};
+
+  use super::Checksum;
+
+  #[test]
+  fn checksum_memory_test() {
+    let backend = Arc::new(MemoryBackend::new());
+    put(backend.as_ref(), "docs/a.txt", b"hello");
+    put(backend.as_ref(), "docs/sub/b.txt", b"world");
+    let vfs = Vfs::default().mount("/", backend);
+    let digest = |bytes: &[u8]| hash(bytes, Algorithm::Sha256, None).unwrap();
+
+    let file = String::from_utf8(content(Checksum::file_in(&vfs, &request("GET /files/checksum?path=/docs/a.txt", b"")).unwrap())).unwrap();
+    assert!(file.contains(&digest(b"hello")));
+
+    let manifest = String::from_utf8(content(Checksum::manifest_in(&vfs, &request("GET /files/manifest?path=/docs", b"")).unwrap())).unwrap();
+    assert_eq!(manifest, format!("{}  a.txt\n{}  sub/b.txt\n", digest(b"hello"), digest(b"world")));
+  }
+
+  // THE MANIFEST ON DISK IS NOT WHAT GETS HASHED, THE CONTENT IT STANDS FOR IS
+  #[test]
+  fn checksum_chunked_test() {
+    let root = TempDir::new("checksum");
+    let chunks = Box::leak(Box::new(ChunkStore::new(root.join("chunks"), root.join("storage"), &["/backups"])));
+    let backend = LocalBackend::new(root.mkdir("storage")).with_chunks(chunks);
+    let original = noise(200 * 1024);
+    put(&backend, "backups/a.bin", &original);
+    assert_ne!(fs::read(root.join("storage/backups/a.bin")).unwrap(), original);
+
+    let vfs = Vfs::default().mount("/", Arc::new(backend));
+    let digest = hash(&original[..], Algorithm::Sha256, None).unwrap();
+    let file = String::from_utf8(content(Checksum::file_in(&vfs, &request("GET /files/checksum?path=/backups/a.bin", b"")).unwrap())).unwrap();
+    assert!(file.contains(&digest) && file.contains("\"size\":204800"));
+
+    let manifest = String::from_utf8(content(Checksum::manifest_in(&vfs, &request("GET /files/manifest?path=/backups", b"")).unwrap())).unwrap();
+    assert_eq!(manifest, format!("{}  a.bin\n", digest));
+  }
+}
@@ -0,0 +1,29 @@
+use json_builder::{Json, JsonBuilder};
+
+use crate::enums::app_error::AppError;
+use crate::library::chunks::ChunkStore;
+use crate::library::tasks::{Task, TaskResult};
+use crate::parser::{http_request::HttpRequest, http_response::HttpResponse};
+use crate::router::middleware::RequestId;
+
+pub struct Chunks;
+
+impl Chunks {
+  // POST /chunks/gc, SWEEPS CHUNKS NO MANIFEST NAMES ANY MORE AS A BACKGROUND TASK
+  pub fn collect(_: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let task = Task::spawn(RequestId::generate(), "chunk-gc", "/", |task| {
+      let report = ChunkStore::global().collect(&ChunkStore::sources(), task)?;
+      Ok(Box::new(move || {
+        let mut json_object = Json::object();
+        json_object.insert("referenced", report.referenced);
+        json_object.insert("removed", report.removed);
+        json_object.insert("reclaimed", report.bytes);
+        json_object
+      }) as TaskResult)
+    })?;
+
+    let mut http_response = HttpResponse::new("202", "Accepted", task.to_json());
+    http_response.header("Location", format!("/tasks?id={}", task.id));
+    Ok(http_response)
+  }
+}
@@ -0,0 +1,157 @@
+use json_builder::{Json, JsonBuilder};
+
+use crate::config::constants::{DUPLICATE_MAX_RESOLVE, DUPLICATE_MAX_SETS};
+use crate::config::utility::escape_json;
+use crate::enums::app_error::AppError;
+use crate::library::duplicates::{find, resolve, DedupeAction};
+use crate::library::tasks::{Task, TaskResult};
+use crate::library::vfs::{EntryKind, Stat, Vfs};
+use crate::parser::{http_request::HttpRequest, http_response::HttpResponse};
+use crate::router::middleware::RequestId;
+
+pub struct Duplicates;
+
+impl Duplicates {
+  pub fn find(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Duplicates::find_in(Vfs::global(), http_request)
+  }
+
+  pub fn resolve(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Duplicates::resolve_in(Vfs::global(), http_request)
+  }
+}
+
+impl Duplicates {
+  // GET /files/duplicates?path=&min_size=, THE SETS ARE IN THE RESULT OF THE TASK IT STARTS
+  fn find_in(vfs: &Vfs, http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let path = http_request.param("path").cloned().unwrap_or_else(|| String::from("/"));
+    if Duplicates::stat(vfs, &path)?.kind != EntryKind::Directory {
+      return Err(AppError::BadRequest(format!("{} is not a directory", path)));
+    }
+
+    let min_size = match http_request.param("min_size") {
+      Some(size) => size.parse::<u64>().map_err(|_| AppError::BadRequest(String::from("Query parameter `min_size` is invalid")))?,
+      None => 1,
+    };
+
+    let (vfs, base) = (vfs.clone(), format!("/{}", Vfs::normalize(&path).unwrap_or_default()));
+    let task = Task::spawn(RequestId::generate(), "duplicates", &path, move |task| {
+      let sets = find(&vfs, &base, min_size, task)?;
+      Ok(Box::new(move || {
+        let mut json_array = Json::array();
+        for set in sets.iter().take(DUPLICATE_MAX_SETS) {
+          let mut paths = Json::array();
+          for path in set.paths.iter() {
+            let mut json_object = Json::object();
+            json_object.insert("path", escape_json(path));
+            paths.append(json_object);
+          }
+
+          let mut json_object = Json::object();
+          json_object.insert("size", set.size);
+          json_object.insert("digest", set.digest.as_str());
+          json_object.insert("wasted", set.wasted());
+          json_object.insert("paths", paths);
+          json_array.append(json_object);
+        }
+
+        let mut json_object = Json::object();
+        json_object.insert("path", escape_json(&base));
+        json_object.insert("sets", sets.len() as u64);
+        json_object.insert("wasted", sets.iter().map(|s| s.wasted()).sum::<u64>());
+        json_object.insert("is_truncated", sets.len() > DUPLICATE_MAX_SETS);
+        json_object.insert("duplicates", json_array);
+        json_object
+      }) as TaskResult)
+    })?;
+    Ok(Duplicates::accepted(&task))
+  }
+
+  // POST /files/duplicates/resolve?keep=/a&path=/b&path=/c&action=link|trash
+  fn resolve_in(vfs: &Vfs, http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let action = match http_request.param("action") {
+      Some(action) => DedupeAction::from(action).ok_or_else(|| AppError::BadRequest(format!("Unsupported action {}, use link or trash", action)))?,
+      None => return Err(AppError::BadRequest(String::from("Query parameter `action` is required"))),
+    };
+
+    let keep_path = http_request.param("keep").ok_or_else(|| AppError::BadRequest(String::from("Query parameter `keep` is required")))?;
+    Duplicates::resolve_file(vfs, keep_path)?;
+
+    let paths: Vec<String> = http_request.params("path").into_iter().filter(|p| !p.trim_matches('/').is_empty()).collect();
+    if paths.is_empty() {
+      return Err(AppError::BadRequest(String::from("Query parameter `path` is required")));
+    }
+
+    if paths.len() > DUPLICATE_MAX_RESOLVE {
+      return Err(AppError::BadRequest(format!("At most {} duplicates can be resolved at once", DUPLICATE_MAX_RESOLVE)));
+    }
+
+    let mut duplicates = Vec::with_capacity(paths.len());
+    for path in paths {
+      let stat = Duplicates::resolve_file(vfs, &path)?;
+      if Vfs::normalize(&path) == Vfs::normalize(keep_path) {
+        return Err(AppError::BadRequest(format!("{} is the file being kept", path)));
+      }
+      duplicates.push((path, stat.size));
+    }
+
+    let (vfs, subject) = (vfs.clone(), keep_path.clone());
+    let task = Task::spawn(RequestId::generate(), "dedupe", keep_path, move |task| {
+      task.set_total(duplicates.iter().map(|(_, size)| size * 2).sum());
+      let mut outcomes = Vec::with_capacity(duplicates.len());
+      for (path, _) in duplicates {
+        task.checkpoint()?;
+        let outcome = resolve(&vfs, &subject, &path, action, &task.id, task);
+        outcomes.push((path, outcome));
+      }
+
+      Ok(Box::new(move || {
+        let (mut resolved, mut failed) = (Json::array(), Json::array());
+        for (path, outcome) in outcomes.iter() {
+          let mut json_object = Json::object();
+          json_object.insert("path", escape_json(path));
+          match outcome {
+            Ok(reclaimed) => {
+              json_object.insert("reclaimed", *reclaimed);
+              resolved.append(json_object);
+            },
+            Err(e) => {
+              json_object.insert("error", escape_json(&e.to_string()));
+              failed.append(json_object);
+            },
+          }
+        }
+
+        let mut json_object = Json::object();
+        json_object.insert("action", action.as_str());
+        json_object.insert("keep", escape_json(&subject));
+        json_object.insert("reclaimed", outcomes.iter().filter_map(|(_, o)| o.as_ref().ok()).sum::<u64>());
+        json_object.insert("resolved", resolved);
+        json_object.insert("failed", failed);
+        json_object
+      }) as TaskResult)
+    })?;
+    Ok(Duplicates::accepted(&task))
+  }
+}
+
+impl Duplicates {
+  fn stat(vfs: &Vfs, path: &str) -> Result<Stat, AppError> {
+    let (backend, relative) = vfs.resolve(path).ok_or_else(|| AppError::Forbidden(format!("{} is outside of the storage root", path)))?;
+    backend.stat(&relative).map_err(|_| AppError::NotFound(format!("{} does not exist", path)))
+  }
+
+  fn resolve_file(vfs: &Vfs, path: &str) -> Result<Stat, AppError> {
+    let stat = Duplicates::stat(vfs, path)?;
+    if stat.kind != EntryKind::File {
+      return Err(AppError::BadRequest(format!("{} is not a regular file", path)));
+    }
+    Ok(stat)
+  }
+
+  fn accepted(task: &Task) -> HttpResponse {
+    let mut http_response = HttpResponse::new("202", "Accepted", task.to_json());
+    http_response.header("Location", format!("/tasks?id={}", task.id));
+    http_response
+  }
+}
@@ -1,17 +1,17 @@
-use crate::parser::http_response::HttpResponse;
+use crate::enums::{app_enums::HttpMethod, app_error::AppError};
+use crate::parser::{http_request::HttpRequest, http_response::HttpResponse};
 
 pub struct Extra;
 
 impl Extra {
-  pub fn not_found() -> String {
-    let contents = r#"{ "test": "Not Found" }"#;
-    let http_response = HttpResponse::new("404", "Not Found", contents);
-    http_response.construct()
+  pub fn not_found(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Err(AppError::NotFound(format!("No route for {}", http_request.path)))
   }
 
-  pub fn method_not_allowed() -> String {
-    let contents = r#"{ "test": "Method Not Allowed" }"#;
-    let http_response = HttpResponse::new("405", "Not Allowed", contents);
-    http_response.construct()
+  pub fn method_not_allowed(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Err(AppError::MethodNotAllowed(match http_request.method {
+      HttpMethod::NONE => String::from("Unsupported request method"),
+      _ => format!("Method {} is not allowed on {}", http_request.method.as_string(), http_request.path),
+    }))
   } 
 }
\ No newline at end of file
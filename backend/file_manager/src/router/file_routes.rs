@@ -0,0 +1,274 @@
+use std::{io::{self, Read, Write}, path::Path, time::UNIX_EPOCH};
+
+use json_builder::{Json, JsonBuilder};
+
+use crate::config::utility::escape_json;
+use crate::enums::app_error::AppError;
+use crate::library::{conditional::{self, Validators}, mime::Mime, range::ByteRange, vfs::{Backend, DirEntry, EntryKind, Stat, Vfs}};
+use crate::parser::{http_request::HttpRequest, http_response::HttpResponse};
+
+pub struct Files;
+
+impl Files {
+  pub fn read(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Files::read_in(Vfs::global(), http_request)
+  }
+
+  pub fn upload(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Files::upload_in(Vfs::global(), http_request)
+  }
+
+  pub fn rename(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Files::rename_in(Vfs::global(), http_request)
+  }
+
+  pub fn delete(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Files::delete_in(Vfs::global(), http_request)
+  }
+}
+
+impl Files {
+  fn read_in(vfs: &Vfs, http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let path = http_request.param("path").map(|p| p.as_str()).unwrap_or("/");
+    let (backend, relative) = Files::resolve(vfs, path)?;
+    let stat = backend.stat(&relative).map_err(|_| AppError::NotFound(format!("{} does not exist", path)))?;
+
+    let validators = Validators::from_stat(&stat);
+    if validators.is_not_modified(http_request) {
+      let mut http_response = HttpResponse::new("304", "Not Modified", "");
+      validators.apply(&mut http_response);
+      return Ok(http_response);
+    }
+
+    let mut http_response = match stat.kind {
+      EntryKind::Directory => HttpResponse::new("200", "OK", Files::list(vfs, path, backend, &relative)?),
+      _ => Files::content(http_request, backend, &relative, &stat, &validators)?,
+    };
+
+    validators.apply(&mut http_response);
+    Ok(http_response)
+  }
+
+  fn upload_in(vfs: &Vfs, http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let path = Files::required_path(http_request, "path")?;
+    let (backend, relative) = Files::resolve(vfs, path)?;
+
+    let current = backend.stat(&relative).ok();
+    if current.as_ref().is_some_and(|s| s.kind == EntryKind::Directory) {
+      return Err(AppError::Conflict(format!("{} is a directory", path)));
+    }
+
+    if conditional::is_precondition_failed(http_request, current.as_ref().map(Validators::from_stat).as_ref()) {
+      return Err(AppError::PreconditionFailed(format!("{} has been modified", path)));
+    }
+
+    backend.mkdir(Files::parent(&relative))?;
+    let mut writer = backend.open_write(&relative)?;
+    writer.write_all(&http_request.body)?;
+    writer.commit()?;
+    let stat = backend.stat(&relative)?;
+
+    let (status, message) = if current.is_some() { ("200", "OK") } else { ("201", "Created") };
+    let mut json_object = Json::object();
+    json_object.insert("path", escape_json(path));
+    json_object.insert("size", http_request.body.len() as u64);
+
+    let mut http_response = HttpResponse::new(status, message, Json::build(json_object));
+    Validators::from_stat(&stat).apply(&mut http_response);
+    Ok(http_response)
+  }
+
+  // BOTH PATHS HAVE TO BE ON THE SAME MOUNT, A MOVE ACROSS BACKENDS WOULD BE A COPY
+  fn rename_in(vfs: &Vfs, http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let (path, to) = (Files::required_path(http_request, "path")?, Files::required_path(http_request, "to")?);
+    let ((backend, from), (destination, relative)) = (Files::resolve(vfs, path)?, Files::resolve(vfs, to)?);
+    if !std::ptr::addr_eq(backend, destination) || from.is_empty() || relative.is_empty() {
+      return Err(AppError::BadRequest(format!("{} cannot be moved to {}", path, to)));
+    }
+
+    let stat = backend.stat(&from).map_err(|_| AppError::NotFound(format!("{} does not exist", path)))?;
+    if conditional::is_precondition_failed(http_request, Some(&Validators::from_stat(&stat))) {
+      return Err(AppError::PreconditionFailed(format!("{} has been modified", path)));
+    }
+
+    if backend.stat(&relative).is_ok_and(|s| s.kind == EntryKind::Directory) {
+      return Err(AppError::Conflict(format!("{} is a directory", to)));
+    }
+
+    backend.mkdir(Files::parent(&relative))?;
+    backend.rename(&from, &relative)?;
+
+    let mut json_object = Json::object();
+    json_object.insert("path", escape_json(path));
+    json_object.insert("to", escape_json(to));
+    Ok(HttpResponse::new("200", "OK", Json::build(json_object)))
+  }
+
+  fn delete_in(vfs: &Vfs, http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let path = Files::required_path(http_request, "path")?;
+    let (backend, relative) = Files::resolve(vfs, path)?;
+    if relative.is_empty() {
+      return Err(AppError::BadRequest(format!("{} is a mount point", path)));
+    }
+
+    let stat = backend.stat(&relative).map_err(|_| AppError::NotFound(format!("{} does not exist", path)))?;
+    if conditional::is_precondition_failed(http_request, Some(&Validators::from_stat(&stat))) {
+      return Err(AppError::PreconditionFailed(format!("{} has been modified", path)));
+    }
+
+    backend.remove(&relative)?;
+
+    let mut json_object = Json::object();
+    json_object.insert("deleted", escape_json(path));
+    Ok(HttpResponse::new("200", "OK", Json::build(json_object)))
+  }
+
+  fn required_path<'a>(http_request: &'a HttpRequest, name: &str) -> Result<&'a str, AppError> {
+    http_request
+      .param(name)
+      .map(|p| p.as_str())
+      .filter(|p| !p.trim_matches('/').is_empty())
+      .ok_or_else(|| AppError::BadRequest(format!("Query parameter `{}` is required", name)))
+  }
+
+  fn resolve<'a>(vfs: &'a Vfs, path: &str) -> Result<(&'a dyn Backend, String), AppError> {
+    vfs.resolve(path).ok_or_else(|| AppError::Forbidden(format!("{} is outside of the storage root", path)))
+  }
+
+  fn parent(relative: &str) -> &str {
+    relative.rsplit_once('/').map_or("", |(parent, _)| parent)
+  }
+
+  // RANGES ARE READ FROM AN OFFSET, THE BACKEND DECIDES HOW TO GET THERE
+  fn content(http_request: &HttpRequest, backend: &dyn Backend, relative: &str, stat: &Stat, validators: &Validators) -> Result<HttpResponse, AppError> {
+    let size = stat.size;
+    let (start, length) = match ByteRange::from_request(http_request, size, &validators.etag) {
+      ByteRange::Full => (0, size),
+      ByteRange::Partial(start, end) => (start, end - start + 1),
+      ByteRange::Unsatisfiable => {
+        let mut http_response = HttpResponse::new("416", "Range Not Satisfiable", "");
+        http_response.header("Content-Range", format!("bytes */{}", size));
+        return Ok(http_response);
+      },
+    };
+
+    let content_type = Mime::detect_from(Path::new(relative), backend.open_read(relative, 0)?);
+    let is_active = Mime::is_active(&content_type);
+    let reader = backend.open_read(relative, start)?.take(length);
+
+    let (status, message) = if length == size { ("200", "OK") } else { ("206", "Partial Content") };
+    let mut http_response = HttpResponse::stream(status, message, content_type, Some(length as usize), reader);
+    http_response
+      .header("Accept-Ranges", "bytes")
+      .header("X-Content-Type-Options", "nosniff");
+    if is_active {
+      http_response.header("Content-Security-Policy", "sandbox");
+    }
+    if status == "206" {
+      http_response.header("Content-Range", format!("bytes {}-{}/{}", start, start + length - 1, size));
+    }
+    Ok(http_response)
+  }
+
+  fn list(vfs: &Vfs, path: &str, backend: &dyn Backend, relative: &str) -> Result<String, AppError> {
+    let mut entries = backend.list(relative)?;
+    for (name, mounted) in vfs.mount_points(path) {
+      if let (false, Ok(stat)) = (entries.iter().any(|e| e.name == name), mounted.stat("")) {
+        entries.push(DirEntry { name, stat });
+      }
+    }
+
+    let mut json_array = Json::array();
+    for entry in entries {
+      let child = if relative.is_empty() { entry.name.clone() } else { format!("{}/{}", relative, entry.name) };
+      let modified = entry.stat.modified.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+
+      let mut json_object = Json::object();
+      json_object.insert("name", escape_json(&entry.name));
+      json_object.insert("type", if entry.stat.kind == EntryKind::Directory { "directory" } else { "file" });
+      json_object.insert("size", entry.stat.size);
+      json_object.insert("modified", modified);
+      json_object.insert("etag", escape_json(&Validators::from_stat(&entry.stat).etag));
+      if entry.stat.kind == EntryKind::File {
+        let mime = match backend.open_read(&child, 0) {
+          Ok(reader) => Mime::detect_from(Path::new(&child), reader),
+          Err(_) => Mime::detect_from(Path::new(&child), io::empty()),
+        };
+        json_object.insert("mime", mime);
+      }
+      json_array.append(json_object);
+    }
+
+    let mut json_object = Json::object();
+    json_object.insert("path", escape_json(path));
+    json_object.insert("entries", json_array);
+    Ok(Json::build(json_object))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{io::Read, sync::Arc};
+
+  use crate::enums::app_error::AppError;
+  use crate::library::{memory_backend::MemoryBackend, vfs::Vfs};
+  use crate::parser::{http_request::HttpRequest, http_response::{HttpBody, HttpResponse}};
+
+  use super::Files;
+
+  fn request(line: &str, headers: &[&str], body: &[u8]) -> HttpRequest {
+    let mut lines = vec![format!("{} HTTP/1.1", line)];
+    lines.extend(headers.iter().map(|h| h.to_string()));
+    let mut http_request = HttpRequest::construct(lines);
+    http_request.body = body.to_vec();
+    http_request
+  }
+
+  fn body(http_response: HttpResponse) -> String {
+    let mut content = Vec::new();
+    match http_response.contents {
+      HttpBody::Bytes(bytes) => content = bytes,
+      HttpBody::Stream(mut reader) => drop(reader.read_to_end(&mut content)),
+    }
+    String::from_utf8(content).unwrap()
+  }
+
+  fn header<'a>(http_response: &'a HttpResponse, name: &str) -> Option<&'a str> {
+    http_response.headers.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+  }
+
+  #[test]
+  fn file_routes_memory_test() {
+    let vfs = Vfs::default().mount("/", Arc::new(MemoryBackend::new())).mount("/scratch", Arc::new(MemoryBackend::new()));
+
+    let created = Files::upload_in(&vfs, &request("POST /files?path=/docs/a.txt", &[], b"hello world")).unwrap();
+    assert_eq!(created.status, "201");
+    let etag = header(&created, "ETag").unwrap().to_owned();
+    assert_eq!(Files::upload_in(&vfs, &request("POST /files?path=/docs/a.txt", &[], b"hello there")).unwrap().status, "200");
+    let stale = Files::upload_in(&vfs, &request("POST /files?path=/docs/a.txt", &[&format!("If-Match: {}", etag)], b"lost"));
+    assert!(matches!(stale, Err(AppError::PreconditionFailed(_))));
+
+    let partial = Files::read_in(&vfs, &request("GET /files?path=/docs/a.txt", &["Range: bytes=6-"], b"")).unwrap();
+    assert_eq!(partial.status, "206");
+    assert_eq!(header(&partial, "Content-Range"), Some("bytes 6-10/11"));
+    assert_eq!(body(partial), "there");
+
+    Files::upload_in(&vfs, &request("POST /files?path=/scratch/b.txt", &[], b"b")).unwrap();
+    let listing = body(Files::read_in(&vfs, &request("GET /files?path=/", &[], b"")).unwrap());
+    assert!(listing.contains("\"docs\"") && listing.contains("\"scratch\""));
+    assert!(body(Files::read_in(&vfs, &request("GET /files?path=/docs", &[], b"")).unwrap()).contains("text/plain"));
+
+    Files::upload_in(&vfs, &request("POST /files?path=/docs/page.svg", &[], b"<svg onload=\"alert(1)\"/>")).unwrap();
+    assert_eq!(header(&Files::read_in(&vfs, &request("GET /files?path=/docs/page.svg", &[], b"")).unwrap(), "Content-Security-Policy"), Some("sandbox"));
+    assert_eq!(header(&Files::read_in(&vfs, &request("GET /files?path=/docs/a.txt", &[], b"")).unwrap(), "Content-Security-Policy"), None);
+
+    assert!(matches!(Files::rename_in(&vfs, &request("POST /files/rename?path=/docs/a.txt&to=/scratch/a.txt", &[], b"")), Err(AppError::BadRequest(_))));
+    Files::rename_in(&vfs, &request("POST /files/rename?path=/docs/a.txt&to=/moved/a.txt", &[], b"")).unwrap();
+    assert_eq!(body(Files::read_in(&vfs, &request("GET /files?path=/moved/a.txt", &[], b"")).unwrap()), "hello there");
+
+    Files::delete_in(&vfs, &request("DELETE /files?path=/moved", &[], b"")).unwrap();
+    assert!(matches!(Files::read_in(&vfs, &request("GET /files?path=/moved/a.txt", &[], b"")), Err(AppError::NotFound(_))));
+    assert!(matches!(Files::delete_in(&vfs, &request("DELETE /files?path=/scratch", &[], b"")), Err(AppError::BadRequest(_))));
+    assert!(matches!(Files::read_in(&vfs, &request("GET /files?path=/../etc", &[], b"")), Err(AppError::Forbidden(_))));
+  }
+}
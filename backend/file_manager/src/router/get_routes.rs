@@ -1,11 +1,13 @@
 use json_builder::{Json, JsonBuilder, JsonNull};
 
-use crate::parser::http_response::HttpResponse;
+use crate::enums::app_error::AppError;
+use crate::library::metrics::Metrics;
+use crate::parser::{http_request::HttpRequest, http_response::HttpResponse};
 
 pub struct Get;
 
 impl Get {
-  pub fn home() -> String {
+  pub fn home(_: &HttpRequest) -> Result<HttpResponse, AppError> {
     let mut json_object = Json::object();
     json_object.insert("path", "home");
     json_object.insert("method", "get");
@@ -14,7 +16,11 @@ impl Get {
     json_object.insert("is_null", JsonNull::new());
 
     let contents = Json::build(json_object);
-    let http_response = HttpResponse::new("200", "Ok", contents);
-    http_response.construct()
+    Ok(HttpResponse::new("200", "Ok", contents))
   } 
+
+  pub fn metrics(_: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let contents = Metrics::global().render();
+    Ok(HttpResponse::init("HTTP/1.1", "200", "OK", "text/plain; version=0.0.4; charset=utf-8", contents))
+  }
 }
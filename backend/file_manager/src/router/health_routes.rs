@@ -0,0 +1,218 @@
+use std::{fs, io::{self, Write}, path::{Path, PathBuf}};
+
+use json_builder::{Json, JsonBuilder};
+
+use crate::config::constants::{
+  CHUNK_STORE, INDEX_PATH, READINESS_MAX_QUEUE_PERCENT, READINESS_MIN_FREE_BYTES, STORAGE_ROOT, TRASH_ROOT, VERSION_STORE,
+};
+use crate::config::utility::escape_json;
+use crate::enums::app_error::AppError;
+use crate::library::metrics::Metrics;
+use crate::library::vfs::{Backend, EntryKind, Vfs};
+use crate::parser::{http_request::HttpRequest, http_response::HttpResponse};
+
+pub struct Health;
+
+struct Check {
+  name: String,
+  passed: bool,
+  details: String,
+}
+
+// THE STORES BESIDE THE MOUNTS ARE CREATED ON FIRST USE AND ONLY NEED A WRITABLE PARENT UNTIL THEN
+struct Store {
+  name: String,
+  path: PathBuf,
+  is_lazy: bool,
+}
+
+impl Health {
+  pub fn live(_: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let mut json_object = Json::object();
+    json_object.insert("status", "pass");
+
+    Ok(HttpResponse::new("200", "OK", Json::build(json_object)))
+  }
+
+  pub fn ready(_: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let mut checks: Vec<Check> = Vfs::global().mounts().into_iter().map(|(prefix, backend)| Health::mount(&prefix, backend)).collect();
+    checks.extend(Health::stores().iter().map(Health::storage));
+    checks.extend([Health::disk_space(STORAGE_ROOT), Health::thread_pool()]);
+    Ok(Health::respond(&checks))
+  }
+}
+
+impl Health {
+  fn respond(checks: &[Check]) -> HttpResponse {
+    let is_ready = checks.iter().all(|check| check.passed);
+
+    let mut json_array = Json::array();
+    for check in checks.iter() {
+      let mut json_object = Json::object();
+      json_object.insert("name", escape_json(&check.name));
+      json_object.insert("status", if check.passed { "pass" } else { "fail" });
+      json_object.insert("details", escape_json(&check.details));
+      json_array.append(json_object);
+    }
+
+    let mut json_object = Json::object();
+    json_object.insert("status", if is_ready { "pass" } else { "fail" });
+    json_object.insert("checks", json_array);
+
+    match is_ready {
+      true => HttpResponse::new("200", "OK", Json::build(json_object)),
+      false => HttpResponse::new("503", "Service Unavailable", Json::build(json_object)),
+    }
+  }
+
+  fn stores() -> Vec<Store> {
+    let store = |name: &str, path: &Path| Store { name: name.to_owned(), path: path.to_path_buf(), is_lazy: true };
+    vec![
+      store("index", Path::new(INDEX_PATH).parent().unwrap_or(Path::new("."))),
+      store("versions", Path::new(VERSION_STORE)),
+      store("chunks", Path::new(CHUNK_STORE)),
+      store("trash", Path::new(TRASH_ROOT)),
+    ]
+  }
+
+  // A MOUNT HAS TO BE THERE. ONE ON DISK IS PROBED ON DISK, THROUGH THE BACKEND THE PROBE WOULD LEAVE A VERSION BEHIND
+  fn mount(prefix: &str, backend: &dyn Backend) -> Check {
+    let name = match prefix {
+      "/" => String::from("storage"),
+      _ => format!("mount:{}", prefix),
+    };
+
+    if let Some(root) = backend.local_path("") {
+      return Health::storage(&Store { name, path: root, is_lazy: false });
+    }
+
+    let probe = format!(".readyz-{}", std::process::id());
+    let result = match backend.stat("") {
+      Ok(stat) if stat.kind == EntryKind::Directory => backend.open_write(&probe).and_then(|mut handle| {
+        handle.write_all(b"ok")?;
+        handle.commit()
+      }).and_then(|_| backend.remove(&probe)),
+      Ok(_) => Err(io::Error::other("not a directory")),
+      Err(e) => Err(e),
+    };
+
+    Check {
+      name,
+      passed: result.is_ok(),
+      details: match result {
+        Ok(_) => format!("{} is writable", prefix),
+        Err(e) => format!("{} is not writable: {}", prefix, e),
+      },
+    }
+  }
+
+  // A PROBE FILE PROVES THE DIRECTORY IS BOTH MOUNTED AND WRITABLE, PERMISSION BITS ALONE DO NOT
+  fn storage(store: &Store) -> Check {
+    let target = match store.is_lazy {
+      true => store.path.ancestors().find(|path| path.exists()).unwrap_or(Path::new(".")),
+      false => store.path.as_path(),
+    };
+
+    let probe = target.join(format!(".readyz-{}", std::process::id()));
+    let result = match fs::metadata(target) {
+      Ok(metadata) if metadata.is_dir() => fs::write(&probe, b"ok").and_then(|_| fs::remove_file(&probe)),
+      Ok(_) => Err(io::Error::other("not a directory")),
+      Err(e) => Err(e),
+    };
+
+    Check {
+      name: store.name.clone(),
+      passed: result.is_ok(),
+      details: match result {
+        Ok(_) if target == store.path => format!("{} is writable", target.display()),
+        Ok(_) => format!("{} will be created in {}, which is writable", store.path.display(), target.display()),
+        Err(e) => format!("{} is not writable: {}", target.display(), e),
+      },
+    }
+  }
+
+  fn disk_space(root: &str) -> Check {
+    match fs2::available_space(root) {
+      Ok(available) => Check {
+        name: String::from("disk_space"),
+        passed: available >= READINESS_MIN_FREE_BYTES,
+        details: format!("{} bytes available, {} required", available, READINESS_MIN_FREE_BYTES),
+      },
+      Err(e) => Check { name: String::from("disk_space"), passed: false, details: format!("Failed to query free space: {}", e) },
+    }
+  }
+
+  fn thread_pool() -> Check {
+    let Some(stats) = Metrics::global().pool() else {
+      return Check { name: String::from("thread_pool"), passed: true, details: String::from("No jobs submitted yet") };
+    };
+
+    let is_saturated = stats.queued * 100 >= stats.queue_depth * READINESS_MAX_QUEUE_PERCENT
+      && stats.busy >= stats.max_size;
+    Check {
+      name: String::from("thread_pool"),
+      passed: !is_saturated,
+      details: format!("{}/{} workers busy, {}/{} jobs queued", stats.busy, stats.max_size, stats.queued, stats.queue_depth),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{path::Path, sync::Arc};
+
+  use crate::library::{local_backend::LocalBackend, memory_backend::MemoryBackend, testing::TempDir, vfs::{Backend, Vfs}};
+
+  use super::{Check, Health, Store};
+
+  fn store(name: &str, path: &Path, is_lazy: bool) -> Store {
+    Store { name: name.to_owned(), path: path.to_path_buf(), is_lazy }
+  }
+
+  #[test]
+  fn health_stores_test() {
+    let names: Vec<String> = Health::stores().into_iter().map(|store| store.name).collect();
+    for name in ["index", "versions", "chunks", "trash"] {
+      assert!(names.iter().any(|n| n == name), "{} is not checked", name);
+    }
+  }
+
+  #[test]
+  fn health_mounts_test() {
+    let root = TempDir::new("readyz");
+    let storage = root.mkdir("storage");
+    let memory = Arc::new(MemoryBackend::new());
+    let vfs = Vfs::default().mount("/", Arc::new(LocalBackend::new(&storage))).mount("/scratch", memory.clone()).mount("/gone", Arc::new(LocalBackend::new(root.join("gone"))));
+    let checks: Vec<Check> = vfs.mounts().into_iter().map(|(prefix, backend)| Health::mount(&prefix, backend)).collect();
+
+    let passed: Vec<(&str, bool)> = checks.iter().map(|check| (check.name.as_str(), check.passed)).collect();
+    assert_eq!(passed, vec![("mount:/scratch", true), ("mount:/gone", false), ("storage", true)]);
+    assert_eq!(Health::respond(&checks).status, "503");
+    assert_eq!(std::fs::read_dir(&storage).unwrap().count(), 0);
+    assert!(memory.list("").unwrap().is_empty());
+  }
+
+  #[test]
+  fn health_ready_test() {
+    let root = TempDir::new("readyz");
+    let storage = root.mkdir("storage");
+    let stores = [store("storage", &storage, false), store("versions", &root.join("versions"), true)];
+    let checks: Vec<Check> = stores.iter().map(Health::storage).collect();
+
+    assert!(checks.iter().all(|check| check.passed));
+    assert_eq!(Health::respond(&checks).status, "200");
+    assert!(!root.join("versions").exists());
+    assert_eq!(std::fs::read_dir(&storage).unwrap().count(), 0);
+  }
+
+  #[test]
+  fn health_not_ready_test() {
+    let root = TempDir::new("readyz");
+    root.write("blocked", "");
+    let stores = [store("storage", &root.join("missing"), false), store("chunks", &root.join("blocked/chunks"), true)];
+    let checks: Vec<Check> = stores.iter().map(Health::storage).collect();
+
+    assert!(checks.iter().all(|check| !check.passed));
+    assert_eq!(Health::respond(&checks).status, "503");
+  }
+}
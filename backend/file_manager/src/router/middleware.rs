@@ -0,0 +1,276 @@
+use std::{collections::HashMap, sync::{atomic::{AtomicU64, Ordering}, Arc}, time::{Instant, SystemTime, UNIX_EPOCH}};
+
+use crate::enums::app_error::AppError;
+use crate::library::metrics::Metrics;
+use crate::parser::{http_request::HttpRequest, http_response::HttpResponse};
+
+use logger_main::Logger;
+
+pub type Layer = Arc<dyn Middleware>;
+pub type Endpoint<'a> = &'a dyn Fn(&HttpRequest) -> Result<HttpResponse, AppError>;
+
+pub struct Context {
+  pub http_request: HttpRequest,
+  pub attributes: HashMap<&'static str, String>,
+}
+
+impl Context {
+  pub fn new(http_request: HttpRequest) -> Self {
+    Context { http_request, attributes: HashMap::new() }
+  }
+
+  pub fn request_id(&self) -> String {
+    self.attributes.get("request_id").cloned().unwrap_or_else(RequestId::generate)
+  }
+}
+
+pub trait Middleware: Send + Sync {
+  fn handle(&self, context: &mut Context, next: Next) -> HttpResponse;
+}
+
+pub struct Next<'a> {
+  layers: &'a [Layer],
+  groups: &'a [MiddlewareGroup],
+  endpoint: Endpoint<'a>,
+}
+
+impl<'a> Next<'a> {
+  pub fn new(layers: &'a [Layer], endpoint: Endpoint<'a>) -> Self {
+    Next { layers, groups: &[], endpoint }
+  }
+
+  pub fn with_groups(self, groups: &'a [MiddlewareGroup]) -> Self {
+    Next { groups, ..self }
+  }
+
+  // GROUP LAYERS ARE PICKED ONCE THE GLOBAL ONES HAVE RUN AND THE ROUTE ONLY AFTER THAT,
+  // SO BOTH SEE THE REQUEST AS ANY LAYER REWROTE IT
+  pub fn run(self, context: &mut Context) -> HttpResponse {
+    if let Some((layer, rest)) = self.layers.split_first() {
+      return layer.handle(context, Next { layers: rest, ..self });
+    }
+
+    if !self.groups.is_empty() {
+      let layers: Vec<Layer> = self
+        .groups
+        .iter()
+        .filter(|group| group.matches(&context.http_request.path))
+        .flat_map(|group| group.layers.iter().cloned())
+        .collect();
+      return Next::new(&layers, self.endpoint).run(context);
+    }
+
+    match (self.endpoint)(&context.http_request) {
+      Ok(http_response) => http_response,
+      Err(e) => {
+        if let AppError::Io(io) = &e {
+          Metrics::global().observe_fs_error(io);
+        }
+        Logger::warn(format!("Request failed [PATH: {} | {}]", context.http_request.path, e));
+        e.into_response(&context.request_id())
+      },
+    }
+  }
+}
+
+pub struct MiddlewareGroup {
+  pub prefix: &'static str,
+  pub layers: Vec<Layer>,
+}
+
+impl MiddlewareGroup {
+  pub fn new(prefix: &'static str, layers: Vec<Layer>) -> Self {
+    MiddlewareGroup { prefix, layers }
+  }
+
+  pub fn matches(&self, path: &str) -> bool {
+    let prefix = self.prefix.trim_end_matches('/');
+    path == prefix || path.starts_with(format!("{}/", prefix).as_str())
+  }
+}
+
+pub struct RequestId;
+
+impl RequestId {
+  pub fn generate() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0);
+    format!("{:x}-{:04x}", millis, COUNTER.fetch_add(1, Ordering::Relaxed) & 0xffff)
+  }
+
+  fn is_valid(value: &str) -> bool {
+    !value.is_empty() && value.len() <= 64 && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+  }
+}
+
+impl Middleware for RequestId {
+  fn handle(&self, context: &mut Context, next: Next) -> HttpResponse {
+    let request_id = match context.http_request.header("x-request-id") {
+      Some(value) if RequestId::is_valid(value) => value.clone(),
+      _ => RequestId::generate(),
+    };
+
+    context.attributes.insert("request_id", request_id.clone());
+    context.http_request.headers.insert(String::from("x-request-id"), request_id.clone());
+
+    let mut http_response = next.run(context);
+    http_response.header("X-Request-Id", request_id);
+    http_response
+  }
+}
+
+pub struct Logging;
+
+impl Middleware for Logging {
+  fn handle(&self, context: &mut Context, next: Next) -> HttpResponse {
+    let started = Instant::now();
+    let (method, path) = (context.http_request.method.as_string(), context.http_request.path.clone());
+    let http_response = next.run(context);
+    Logger::debug(format!(
+      "Handled [ID: {} | METHOD: {} | PATH: {} | STATUS: {} | TIME: {:?}]",
+      context.request_id(), method, path, http_response.status, started.elapsed()
+    ));
+    http_response
+  }
+}
+
+// FOR ANSWERS THAT ARE STALE AS SOON AS THEY ARE SENT, LIKE TASK PROGRESS OR HEALTH
+pub struct NoStore;
+
+impl Middleware for NoStore {
+  fn handle(&self, context: &mut Context, next: Next) -> HttpResponse {
+    let mut http_response = next.run(context);
+    if http_response.get_header("Cache-Control").is_none() {
+      http_response.header("Cache-Control", "no-store");
+    }
+    http_response
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+
+  use crate::enums::app_error::AppError;
+  use crate::parser::{http_request::HttpRequest, http_response::HttpResponse};
+
+  use super::{Context, Layer, Middleware, MiddlewareGroup, Next, NoStore, RequestId};
+
+  struct Tag(&'static str);
+
+  impl Middleware for Tag {
+    fn handle(&self, context: &mut Context, next: Next) -> HttpResponse {
+      context.http_request.headers.insert(String::from("x-trace"), self.0.to_owned());
+      let mut http_response = next.run(context);
+      http_response.header("X-Layer", self.0);
+      http_response
+    }
+  }
+
+  struct Deny;
+
+  impl Middleware for Deny {
+    fn handle(&self, _: &mut Context, _: Next) -> HttpResponse {
+      HttpResponse::new("403", "Forbidden", "")
+    }
+  }
+
+  fn echo(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::new("200", "OK", http_request.header("x-trace").cloned().unwrap_or_default()))
+  }
+
+  fn fail(_: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Err(AppError::Conflict(String::from("already exists")))
+  }
+
+  fn context() -> Context {
+    Context::new(HttpRequest::construct(vec![String::from("GET / HTTP/1.1")]))
+  }
+
+  #[test]
+  fn middleware_order_test() {
+    let layers: Vec<Layer> = vec![Arc::new(Tag("outer")), Arc::new(Tag("inner"))];
+    let http_response = Next::new(&layers, &echo).run(&mut context());
+
+    let tags: Vec<&String> = http_response.headers.iter().map(|(_, v)| v).collect();
+    assert_eq!(tags, vec!["inner", "outer"]);
+    assert_eq!(http_response.content_length, Some("inner".len()));
+  }
+
+  #[test]
+  fn middleware_short_circuit_test() {
+    let layers: Vec<Layer> = vec![Arc::new(Tag("outer")), Arc::new(Deny), Arc::new(Tag("inner"))];
+    let http_response = Next::new(&layers, &echo).run(&mut context());
+
+    assert_eq!(http_response.status, "403");
+    assert_eq!(http_response.headers.len(), 1);
+  }
+
+  #[test]
+  fn middleware_error_response_test() {
+    let layers: Vec<Layer> = vec![Arc::new(RequestId)];
+    let mut context = context();
+    context.http_request.headers.insert(String::from("x-request-id"), String::from("abc-123"));
+    let http_response = Next::new(&layers, &fail).run(&mut context);
+
+    assert_eq!(http_response.status, "409");
+    assert_eq!(http_response.get_header("X-Request-Id"), Some(&String::from("abc-123")));
+  }
+
+  struct Rewrite(&'static str);
+
+  impl Middleware for Rewrite {
+    fn handle(&self, context: &mut Context, next: Next) -> HttpResponse {
+      context.http_request.path = self.0.to_owned();
+      next.run(context)
+    }
+  }
+
+  fn path(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::new("200", "OK", http_request.path.clone()))
+  }
+
+  fn groups() -> Vec<MiddlewareGroup> {
+    vec![
+      MiddlewareGroup::new("/files", vec![Arc::new(Tag("files")), Arc::new(NoStore)]),
+      MiddlewareGroup::new("/tasks/", vec![Arc::new(Tag("tasks"))]),
+    ]
+  }
+
+  #[test]
+  fn middleware_group_matches_test() {
+    let groups = groups();
+    assert!(groups[0].matches("/files"));
+    assert!(groups[0].matches("/files/versions"));
+    assert!(!groups[0].matches("/filesystem"));
+    assert!(groups[1].matches("/tasks"));
+    assert!(groups[1].matches("/tasks/1"));
+    assert!(!groups[1].matches("/"));
+  }
+
+  #[test]
+  fn middleware_group_order_test() {
+    let (layers, groups): (Vec<Layer>, _) = (vec![Arc::new(Tag("global"))], groups());
+    let http_response = Next::new(&layers, &echo).with_groups(&groups).run(&mut context());
+    let tags: Vec<&String> = http_response.headers.iter().map(|(_, v)| v).collect();
+    assert_eq!(tags, vec!["global"]);
+
+    let mut context = context();
+    context.http_request.path = String::from("/files/versions");
+    let http_response = Next::new(&layers, &echo).with_groups(&groups).run(&mut context);
+
+    // GROUP LAYERS RUN INSIDE THE GLOBAL ONES, SO THE ROUTE SEES THE GROUP'S TAG AND THE GLOBAL HEADER IS ADDED LAST
+    let tags: Vec<&String> = http_response.headers.iter().map(|(_, v)| v).collect();
+    assert_eq!(tags, vec!["no-store", "files", "global"]);
+    assert_eq!(http_response.content_length, Some("files".len()));
+  }
+
+  #[test]
+  fn middleware_rewrite_test() {
+    let (layers, groups): (Vec<Layer>, _) = (vec![Arc::new(Rewrite("/tasks/7"))], groups());
+    let http_response = Next::new(&layers, &path).with_groups(&groups).run(&mut context());
+
+    assert_eq!(http_response.get_header("X-Layer"), Some(&String::from("tasks")));
+    assert_eq!(http_response.content_length, Some("/tasks/7".len()));
+  }
+}
@@ -1,4 +1,16 @@
 mod get_routes;
+mod archive_routes;
+mod checksum_routes;
+mod chunk_routes;
+mod duplicate_routes;
 mod extra_routes;
+mod file_routes;
+mod health_routes;
+mod search_routes;
+mod static_routes;
+mod task_routes;
+mod usage_routes;
+mod version_routes;
 
+pub mod middleware;
 pub mod router_handler;
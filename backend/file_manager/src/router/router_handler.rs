@@ -1,32 +1,177 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, panic::{self, AssertUnwindSafe}, sync::Arc, time::Instant};
 
-use crate::enums::app_enums::HttpMethod;
+use crate::enums::{app_enums::HttpMethod, app_error::AppError};
+use crate::router::archive_routes::Archive;
+use crate::router::checksum_routes::Checksum;
+use crate::router::chunk_routes::Chunks;
+use crate::router::duplicate_routes::Duplicates;
 use crate::router::extra_routes::Extra;
+use crate::router::file_routes::Files;
 use crate::router::get_routes::Get;
+use crate::router::health_routes::Health;
+use crate::router::search_routes::Search;
+use crate::router::static_routes::Static;
+use crate::router::task_routes::Tasks;
+use crate::router::usage_routes::DiskUsage;
+use crate::router::version_routes::Versions;
+use crate::config::constants::{BULK_ROUTING_TABLE, STATIC_ASSETS_PREFIX};
 use crate::hashmap;
+use crate::library::{compression::Compressor, cors::CorsPolicy, metrics::Metrics, rate_limit::RateLimiter, tp::Priority};
+use crate::parser::{http_request::HttpRequest, http_response::HttpResponse};
+use crate::router::middleware::{Context, Layer, Logging, MiddlewareGroup, Next, NoStore, RequestId};
 
 use logger_main::Logger;
 
+pub type Route = fn(&HttpRequest) -> Result<HttpResponse, AppError>;
+
 pub struct RouterHandler {
-  pub map: HashMap<HttpMethod, HashMap<&'static str, fn() -> String>>,
+  pub map: HashMap<HttpMethod, HashMap<&'static str, Route>>,
+  pub prefix_map: HashMap<HttpMethod, Vec<(&'static str, Route)>>,
+  pub middleware: Vec<Layer>,
+  pub middleware_groups: Vec<MiddlewareGroup>,
 }
 
 impl RouterHandler {
   pub fn new() -> Self {
-    RouterHandler { map: RouterHandler::route_map() }
+    RouterHandler { 
+      map: RouterHandler::route_map(), 
+      prefix_map: RouterHandler::prefix_route_map(),
+      middleware: RouterHandler::middleware(),
+      middleware_groups: RouterHandler::middleware_groups(),
+    }
   }
 }
 
 impl RouterHandler {
-  fn route_map() -> HashMap<HttpMethod, HashMap<&'static str, fn() -> String>> {
+  fn route_map() -> HashMap<HttpMethod, HashMap<&'static str, Route>> {
     hashmap! {
-      HttpMethod::GET => hashmap! { "/" => Get::home as fn() -> String }
+      HttpMethod::GET => hashmap! { 
+        "/" => Get::home as Route, 
+        "/files" => Files::read as Route,
+        "/metrics" => Get::metrics as Route,
+        "/healthz" => Health::live as Route,
+        "/readyz" => Health::ready as Route,
+        "/search" => Search::find as Route,
+        "/search/content" => Search::content as Route,
+        "/archive" => Archive::download as Route,
+        "/archive/list" => Archive::list as Route,
+        "/archive/member" => Archive::member as Route,
+        "/archive/jobs" => Archive::job as Route,
+        "/files/checksum" => Checksum::file as Route,
+        "/files/manifest" => Checksum::manifest as Route,
+        "/files/usage" => DiskUsage::analyze as Route,
+        "/files/duplicates" => Duplicates::find as Route,
+        "/files/versions" => Versions::list as Route,
+        "/files/versions/content" => Versions::content as Route,
+        "/tasks" => Tasks::status as Route
+      },
+      HttpMethod::POST => hashmap! {
+        "/files" => Files::upload as Route,
+        "/archive/extract" => Archive::extract as Route,
+        "/files/manifest/verify" => Checksum::verify as Route,
+        "/files/rename" => Files::rename as Route,
+        "/files/duplicates/resolve" => Duplicates::resolve as Route,
+        "/files/versions/restore" => Versions::restore as Route,
+        "/chunks/gc" => Chunks::collect as Route
+      },
+      HttpMethod::DELETE => hashmap! {
+        "/files" => Files::delete as Route,
+        "/tasks" => Tasks::cancel as Route,
+        "/files/versions" => Versions::prune as Route
+      }
+    }
+  }
+
+  fn prefix_route_map() -> HashMap<HttpMethod, Vec<(&'static str, Route)>> {
+    hashmap! {
+      HttpMethod::GET => vec![(STATIC_ASSETS_PREFIX, Static::serve as Route)]
+    }
+  }
+
+  // OUTERMOST LAYER FIRST, GROUP LAYERS RUN INSIDE THE GLOBAL ONES
+  fn middleware() -> Vec<Layer> {
+    vec![
+      Arc::new(RequestId),
+      Arc::new(Logging),
+      Arc::new(Compressor),
+      Arc::new(CorsPolicy::new()),
+      Arc::new(RateLimiter::new()),
+    ]
+  }
+
+  fn middleware_groups() -> Vec<MiddlewareGroup> {
+    let no_store: Layer = Arc::new(NoStore);
+    ["/tasks", "/healthz", "/readyz", "/metrics"]
+      .into_iter()
+      .map(|prefix| MiddlewareGroup::new(prefix, vec![no_store.clone()]))
+      .collect()
+  }
+
+  fn lookup(&self, method: &HttpMethod, path: &str) -> Option<&Route> {
+    self.find(method, path).map(|(_, route)| route)
+  }
+
+  // THE MATCHED PATTERN RATHER THAN THE RAW PATH KEEPS METRIC LABELS BOUNDED
+  fn pattern(&self, method: &HttpMethod, path: &str) -> &'static str {
+    self.find(method, path).map(|(pattern, _)| pattern).unwrap_or("unmatched")
+  }
+
+  fn find(&self, method: &HttpMethod, path: &str) -> Option<(&'static str, &Route)> {
+    if let Some((pattern, route)) = self.map.get(method).and_then(|routes| routes.get_key_value(path)) {
+      return Some((pattern, route));
     }
+
+    self.prefix_map.get(method)?.iter().find_map(|(prefix, route)| {
+      let is_match = path == *prefix || path.starts_with(format!("{}/", prefix.trim_end_matches('/')).as_str());
+      is_match.then_some((*prefix, route))
+    })
   }
 }
 
 impl RouterHandler {
-  pub fn exec(&self, method: &HttpMethod, path: &str) -> &fn() -> String {
+  pub fn dispatch(&self, http_request: HttpRequest) -> HttpResponse {
+    let started = Instant::now();
+    let method = http_request.method.as_string();
+    let pattern = self.pattern(&http_request.method, &http_request.path);
+    let bytes_in = http_request.body.len();
+    let http_response = self.handle(http_request);
+
+    Metrics::global().observe_request(pattern, &method, &http_response.status, started.elapsed(), bytes_in);
+    http_response
+  }
+
+  fn handle(&self, http_request: HttpRequest) -> HttpResponse {
+    let endpoint = |http_request: &HttpRequest| (self.exec(&http_request.method, &http_request.path))(http_request);
+    let mut context = Context::new(http_request);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+      Next::new(&self.middleware, &endpoint).with_groups(&self.middleware_groups).run(&mut context)
+    }));
+    result.unwrap_or_else(|cause| {
+      let reason = cause
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| cause.downcast_ref::<String>().cloned())
+        .unwrap_or_default();
+
+      Logger::warn(format!("Route panicked [PATH: {} | REASON: {}]", context.http_request.path, reason));
+      AppError::Internal(String::from("Unexpected error while handling the request")).into_response(&context.request_id())
+    })
+  }
+
+  // READS KEEP THE UI RESPONSIVE, LONG RUNNING TREE OPERATIONS ARE LIMITED TO THE BULK CLASS
+  pub fn priority(&self, http_request: &HttpRequest) -> Priority {
+    let is_bulk = BULK_ROUTING_TABLE
+      .iter()
+      .any(|(method, path)| *method == http_request.method && *path == http_request.path);
+
+    match (is_bulk, &http_request.method) {
+      (true, _) => Priority::Bulk,
+      (false, HttpMethod::GET | HttpMethod::OPTIONS) => Priority::Interactive,
+      (false, _) => Priority::Normal,
+    }
+  }
+
+  pub fn exec(&self, method: &HttpMethod, path: &str) -> &Route {
     Logger::debug(format!("Route to [METHOD: {} | PATH: {}]", method.as_string(), path));
     match method {
         HttpMethod::DELETE      => self.delete(path),
@@ -39,41 +184,41 @@ impl RouterHandler {
 }
 
 trait HttpMethodTrait {
-  fn get(&self, path: &str)     -> &fn() -> String;
-  fn post(&self, path: &str)    -> &fn() -> String;
-  fn update(&self, path: &str)  -> &fn() -> String;
-  fn delete(&self, path: &str)  -> &fn() -> String;
+  fn get(&self, path: &str)     -> &Route;
+  fn post(&self, path: &str)    -> &Route;
+  fn update(&self, path: &str)  -> &Route;
+  fn delete(&self, path: &str)  -> &Route;
 }
 
 impl HttpMethodTrait for RouterHandler {
-  fn get(&self, path: &str) -> &fn() -> String {
-    self.map.get(&HttpMethod::GET).unwrap().get(path).unwrap_or(self.not_found())
+  fn get(&self, path: &str) -> &Route {
+    self.lookup(&HttpMethod::GET, path).unwrap_or(self.not_found())
   }
 
-  fn post(&self, path: &str) -> &fn() -> String {
-    self.map.get(&HttpMethod::POST).unwrap().get(path).unwrap_or(self.not_found())
+  fn post(&self, path: &str) -> &Route {
+    self.lookup(&HttpMethod::POST, path).unwrap_or(self.not_found())
   }
 
-  fn update(&self, path: &str) -> &fn() -> String {
-    self.map.get(&HttpMethod::UPDATE).unwrap().get(path).unwrap_or(self.not_found())
+  fn update(&self, path: &str) -> &Route {
+    self.lookup(&HttpMethod::UPDATE, path).unwrap_or(self.not_found())
   }
 
-  fn delete(&self, path: &str) -> &fn() -> String {
-    self.map.get(&HttpMethod::DELETE).unwrap().get(path).unwrap_or(self.not_found())
+  fn delete(&self, path: &str) -> &Route {
+    self.lookup(&HttpMethod::DELETE, path).unwrap_or(self.not_found())
   }
 }
 
 trait ExtraHttpMethodTrait {
-  fn not_found(&self) -> &fn() -> String;
-  fn method_not_allowed(&self) -> &fn() -> String;
+  fn not_found(&self) -> &Route;
+  fn method_not_allowed(&self) -> &Route;
 }
 
 impl ExtraHttpMethodTrait for RouterHandler {
-  fn method_not_allowed(&self) -> &fn() -> String {
-    &(Extra::method_not_allowed as fn() -> String)
+  fn method_not_allowed(&self) -> &Route {
+    &(Extra::method_not_allowed as Route)
   }
   
-  fn not_found(&self) -> &fn() -> String {
-    &(Extra::not_found as fn() -> String)
+  fn not_found(&self) -> &Route {
+    &(Extra::not_found as Route)
   }
 }
\ No newline at end of file
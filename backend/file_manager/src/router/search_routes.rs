@@ -0,0 +1,93 @@
+use std::{collections::HashMap, io::Read};
+
+use json_builder::{Json, JsonBuilder};
+
+use crate::config::constants::{INDEX_DEFAULT_LIMIT, INDEX_MAX_LIMIT, INDEX_SNIPPET_LENGTH};
+use crate::config::utility::escape_json;
+use crate::enums::app_error::AppError;
+use crate::library::{index::Clause, indexer::Indexer, search::{SearchQuery, SearchStream}, vfs::{EntryKind, Vfs}};
+use crate::parser::{http_request::HttpRequest, http_response::HttpResponse};
+
+pub struct Search;
+
+impl Search {
+  pub fn find(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Search::find_in(Vfs::global(), http_request)
+  }
+
+  pub fn content(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Search::content_in(Vfs::global(), http_request)
+  }
+}
+
+impl Search {
+  fn find_in(vfs: &Vfs, http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let query = SearchQuery::from(http_request)?;
+    let (backend, relative) = vfs.resolve(&query.path).ok_or_else(|| AppError::Forbidden(format!("{} is outside of the storage root", query.path)))?;
+    let stat = backend.stat(&relative).map_err(|_| AppError::NotFound(format!("{} does not exist", query.path)))?;
+    if stat.kind != EntryKind::Directory {
+      return Err(AppError::BadRequest(format!("{} is not a directory", query.path)));
+    }
+
+    let mut http_response = HttpResponse::stream("200", "OK", "application/x-ndjson", None, SearchStream::new(vfs.clone(), query));
+    http_response
+      .header("Cache-Control", "no-store")
+      .header("X-Content-Type-Options", "nosniff");
+    Ok(http_response)
+  }
+
+  fn content_in(vfs: &Vfs, http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let query = http_request
+      .param("q")
+      .map(|q| q.trim())
+      .filter(|q| !q.is_empty())
+      .ok_or_else(|| AppError::BadRequest(String::from("Query parameter `q` is required")))?;
+
+    let limit = match http_request.param("limit") {
+      Some(limit) => limit.parse::<usize>().map_err(|_| AppError::BadRequest(String::from("Query parameter `limit` is invalid")))?,
+      None => INDEX_DEFAULT_LIMIT,
+    }
+    .min(INDEX_MAX_LIMIT);
+
+    let clauses = Clause::parse(query);
+    if clauses.is_empty() {
+      return Err(AppError::BadRequest(String::from("Query parameter `q` has no searchable terms")));
+    }
+
+    let indexer = Indexer::global().ok_or_else(|| AppError::ServiceUnavailable(String::from("Content index is not running")))?;
+    let mut hits = indexer.search(&clauses, limit + 1);
+    let is_truncated = hits.len() > limit;
+    hits.truncate(limit);
+
+    // EACH FILE IS READ ONCE NO MATTER HOW MANY OF ITS LINES MATCHED
+    let mut files: HashMap<String, Vec<String>> = HashMap::new();
+    let mut json_array = Json::array();
+    for hit in hits.iter() {
+      let lines = files.entry(hit.path.clone()).or_insert_with(|| {
+        let mut contents = Vec::new();
+        if vfs.open_read(&hit.path, 0).and_then(|mut reader| reader.read_to_end(&mut contents)).is_err() {
+          contents.clear();
+        }
+        String::from_utf8_lossy(&contents).lines().map(String::from).collect()
+      });
+
+      let snippet: String = lines.get(hit.line as usize - 1).map(|l| l.trim().chars().take(INDEX_SNIPPET_LENGTH).collect()).unwrap_or_default();
+      let mut json_object = Json::object();
+      json_object.insert("path", escape_json(&hit.path));
+      json_object.insert("line", hit.line);
+      json_object.insert("snippet", escape_json(&snippet));
+      json_array.append(json_object);
+    }
+
+    let mut json_object = Json::object();
+    json_object.insert("query", escape_json(query));
+    json_object.insert("hits", json_array);
+    json_object.insert("count", hits.len() as u64);
+    json_object.insert("truncated", is_truncated);
+    json_object.insert("indexed_files", indexer.len() as u64);
+
+    let mut http_response = HttpResponse::new("200", "OK", Json::build(json_object));
+    http_response.header("Cache-Control", "no-store");
+    Ok(http_response)
+  }
+}
@@ -0,0 +1,162 @@
+use std::{fs::{self, File}, path::{Path, PathBuf}};
+
+use crate::config::constants::{
+  STATIC_ASSETS_MAX_AGE, STATIC_ASSETS_PREFIX, STATIC_ASSETS_ROOT, STATIC_ASSETS_SPA_FALLBACK, STATIC_INDEX_FILES,
+};
+use crate::config::utility::decode_uri_component;
+use crate::enums::app_error::AppError;
+use crate::library::{conditional::Validators, mime::Mime, storage::Storage};
+use crate::parser::{http_request::HttpRequest, http_response::HttpResponse};
+
+pub struct Static;
+
+impl Static {
+  pub fn serve(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Static::serve_from(STATIC_ASSETS_ROOT, http_request)
+  }
+}
+
+impl Static {
+  fn serve_from(root: &str, http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let path = decode_uri_component(http_request.path.strip_prefix(STATIC_ASSETS_PREFIX).unwrap_or_default());
+    let target = Storage::resolve_in(root, &path)
+      .ok_or_else(|| AppError::Forbidden(format!("{} is outside of the assets root", path)))?;
+
+    let asset = match Static::find(target) {
+      Some(asset) => Some(asset),
+      None if STATIC_ASSETS_SPA_FALLBACK && Static::is_navigation(http_request, &path) => {
+        Static::find(PathBuf::from(root))
+      },
+      None => None,
+    };
+
+    match asset {
+      Some(asset) => Static::send(http_request, &asset),
+      None => Err(AppError::NotFound(format!("Asset {} does not exist", path))),
+    }
+  }
+
+  fn find(target: PathBuf) -> Option<PathBuf> {
+    let metadata = fs::metadata(&target).ok()?;
+    if metadata.is_file() {
+      return Some(target);
+    }
+
+    STATIC_INDEX_FILES.iter().map(|index| target.join(index)).find(|index| index.is_file())
+  }
+
+  fn send(http_request: &HttpRequest, asset: &Path) -> Result<HttpResponse, AppError> {
+    let metadata = fs::metadata(asset)?;
+
+    let validators = Validators::from(&metadata);
+    let cache_control = Static::cache_control(asset);
+    if validators.is_not_modified(http_request) {
+      let mut http_response = HttpResponse::new("304", "Not Modified", "");
+      validators.apply(&mut http_response);
+      http_response.header("Cache-Control", cache_control);
+      return Ok(http_response);
+    }
+
+    let file = File::open(asset)?;
+    let mut http_response = HttpResponse::stream("200", "OK", Mime::detect(asset), Some(metadata.len() as usize), file);
+    validators.apply(&mut http_response);
+    http_response
+      .header("Cache-Control", cache_control)
+      .header("X-Content-Type-Options", "nosniff");
+    Ok(http_response)
+  }
+
+  fn cache_control(asset: &Path) -> String {
+    let name = asset.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    if STATIC_INDEX_FILES.contains(&name.as_str()) {
+      return String::from("no-cache");
+    }
+
+    // BUNDLER OUTPUT LIKE `main.3f9a1c2b.js` IS CONTENT ADDRESSED AND NEVER CHANGES IN PLACE
+    let is_fingerprinted = name
+      .split('.')
+      .any(|part| part.len() >= 8 && part.chars().all(|c| c.is_ascii_hexdigit()));
+
+    match is_fingerprinted {
+      true => String::from("public, max-age=31536000, immutable"),
+      false => format!("public, max-age={}", STATIC_ASSETS_MAX_AGE),
+    }
+  }
+
+  fn is_navigation(http_request: &HttpRequest, path: &str) -> bool {
+    let has_extension = Path::new(path).extension().is_some();
+    !has_extension || http_request.accept.contains("text/html")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{io::Read, path::Path};
+
+  use crate::config::constants::STATIC_ASSETS_MAX_AGE;
+  use crate::enums::app_error::AppError;
+  use crate::library::testing::TempDir;
+  use crate::parser::{http_request::HttpRequest, http_response::{HttpBody, HttpResponse}};
+
+  use super::Static;
+
+  fn assets() -> TempDir {
+    let root = TempDir::new("static");
+    root.write("index.html", "<html>app</html>");
+    root.write("assets/main.3f9a1c2b.js", "console.log(1)");
+    root.write("assets/logo.svg", "<svg/>");
+    root.write("docs/index.html", "<html>docs</html>");
+    root
+  }
+
+  fn request(line: &str, accept: &str) -> HttpRequest {
+    HttpRequest::construct(vec![format!("GET {} HTTP/1.1", line), format!("Accept: {}", accept)])
+  }
+
+  fn serve(root: &TempDir, http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Static::serve_from(root.path().to_str().unwrap(), http_request)
+  }
+
+  fn body(http_response: HttpResponse) -> String {
+    let mut content = Vec::new();
+    match http_response.contents {
+      HttpBody::Bytes(bytes) => content = bytes,
+      HttpBody::Stream(mut reader) => drop(reader.read_to_end(&mut content)),
+    }
+    String::from_utf8(content).unwrap()
+  }
+
+  #[test]
+  fn static_serve_test() {
+    let root = assets();
+    assert_eq!(body(serve(&root, &request("/ui/assets/logo.svg", "*/*")).unwrap()), "<svg/>");
+    assert_eq!(body(serve(&root, &request("/ui/docs/", "*/*")).unwrap()), "<html>docs</html>");
+    assert_eq!(body(serve(&root, &request("/ui", "*/*")).unwrap()), "<html>app</html>");
+    assert!(matches!(serve(&root, &request("/ui/../secret", "*/*")), Err(AppError::Forbidden(_))));
+  }
+
+  #[test]
+  fn static_spa_fallback_test() {
+    let root = assets();
+    assert_eq!(body(serve(&root, &request("/ui/settings/profile", "*/*")).unwrap()), "<html>app</html>");
+    assert_eq!(body(serve(&root, &request("/ui/report.pdf", "text/html,*/*")).unwrap()), "<html>app</html>");
+    assert!(matches!(serve(&root, &request("/ui/assets/missing.js", "*/*")), Err(AppError::NotFound(_))));
+  }
+
+  #[test]
+  fn static_navigation_test() {
+    assert!(Static::is_navigation(&request("/ui/settings", "*/*"), "/settings"));
+    assert!(Static::is_navigation(&request("/ui/a.png", "text/html,application/xhtml+xml"), "/a.png"));
+    assert!(!Static::is_navigation(&request("/ui/a.png", "image/*"), "/a.png"));
+  }
+
+  #[test]
+  fn static_cache_control_test() {
+    let revalidated = format!("public, max-age={}", STATIC_ASSETS_MAX_AGE);
+    assert_eq!(Static::cache_control(Path::new("dist/index.html")), "no-cache");
+    assert_eq!(Static::cache_control(Path::new("dist/main.3f9a1c2b.js")), "public, max-age=31536000, immutable");
+    assert_eq!(Static::cache_control(Path::new("dist/chunk-0123abcd.css")), revalidated);
+    assert_eq!(Static::cache_control(Path::new("dist/deadbeef.woff2")), "public, max-age=31536000, immutable");
+    assert_eq!(Static::cache_control(Path::new("dist/logo.svg")), revalidated);
+  }
+}
@@ -0,0 +1,26 @@
+use crate::enums::app_error::AppError;
+use crate::library::tasks::{Task, TaskState};
+use crate::parser::{http_request::HttpRequest, http_response::HttpResponse};
+
+pub struct Tasks;
+
+impl Tasks {
+  pub fn status(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let id = http_request.param("id").ok_or_else(|| AppError::BadRequest(String::from("Query parameter `id` is required")))?;
+    let task = Task::find(id).ok_or_else(|| AppError::NotFound(format!("No task {}", id)))?;
+
+    Ok(HttpResponse::new("200", "OK", task.to_json()))
+  }
+
+  // THE TASK STOPS AT ITS NEXT CHECKPOINT, THE RESPONSE MAY STILL SHOW IT RUNNING
+  pub fn cancel(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let id = http_request.param("id").ok_or_else(|| AppError::BadRequest(String::from("Query parameter `id` is required")))?;
+    let task = Task::find(id).ok_or_else(|| AppError::NotFound(format!("No task {}", id)))?;
+    if task.state() != TaskState::Running {
+      return Err(AppError::Conflict(format!("Task {} has already finished", id)));
+    }
+
+    task.cancel();
+    Ok(HttpResponse::new("202", "Accepted", task.to_json()))
+  }
+}
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use json_builder::{Json, JsonBuilder, JsonObject};
+
+use crate::config::constants::{USAGE_DEFAULT_DEPTH, USAGE_DEFAULT_TOP, USAGE_MAX_DEPTH, USAGE_MAX_TOP};
+use crate::config::utility::escape_json;
+use crate::enums::app_error::AppError;
+use crate::library::tasks::{Task, TaskResult};
+use crate::library::usage::{Usage, UsageNode};
+use crate::library::vfs::Vfs;
+use crate::parser::{http_request::HttpRequest, http_response::HttpResponse};
+use crate::router::middleware::RequestId;
+
+pub struct DiskUsage;
+
+impl DiskUsage {
+  pub fn analyze(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    DiskUsage::analyze_in(Vfs::global(), http_request)
+  }
+}
+
+impl DiskUsage {
+  // GET /files/usage?path=&top=&depth=, A CACHED SCAN IS ANSWERED RIGHT AWAY, OTHERWISE THE CLIENT POLLS THE TASK.
+  // ONLY TREES ON DISK ARE CACHED, NOTHING WOULD TELL THE CACHE WHEN ANOTHER BACKEND CHANGES
+  fn analyze_in(vfs: &Vfs, http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let path = http_request.param("path").cloned().unwrap_or_else(|| String::from("/"));
+    let (backend, relative) = vfs.resolve(&path).ok_or_else(|| AppError::Forbidden(format!("{} is outside of the storage root", path)))?;
+    backend.stat(&relative).map_err(|_| AppError::NotFound(format!("{} does not exist", path)))?;
+    let key = backend.local_path(&relative);
+
+    let top = DiskUsage::number(http_request, "top", USAGE_DEFAULT_TOP)?.clamp(1, USAGE_MAX_TOP);
+    let depth = DiskUsage::number(http_request, "depth", USAGE_DEFAULT_DEPTH)?.min(USAGE_MAX_DEPTH);
+    let is_refresh = http_request.param("refresh").is_some_and(|r| r == "true");
+
+    if let Some(node) = key.as_deref().and_then(Usage::cached).filter(|_| !is_refresh) {
+      let mut http_response = HttpResponse::new("200", "OK", Json::build(DiskUsage::report(&path, &node, top, depth)));
+      http_response.header("Cache-Control", "no-store");
+      return Ok(http_response);
+    }
+
+    let task = match key.as_deref().and_then(Usage::running) {
+      Some(task) => task,
+      None => {
+        let (generation, scanned, vfs) = (Usage::generation(), key.clone(), vfs.clone());
+        let subject = path.clone();
+        let task = Task::spawn(RequestId::generate(), "usage", &path, move |task| {
+          let node = match Usage::scan(&vfs, &subject, Some(task)) {
+            Ok(node) => Arc::new(node),
+            Err(e) => {
+              if let Some(scanned) = scanned.as_deref() {
+                Usage::forget(scanned);
+              }
+              return Err(e);
+            },
+          };
+
+          if let Some(scanned) = scanned.as_deref() {
+            Usage::store(scanned, node.clone(), generation);
+          }
+          Ok(Box::new(move || DiskUsage::report(&subject, &node, top, depth)) as TaskResult)
+        })?;
+        if let Some(key) = key.as_deref() {
+          Usage::set_running(key, &task.id);
+        }
+        task
+      },
+    };
+
+    let mut http_response = HttpResponse::new("202", "Accepted", task.to_json());
+    http_response.header("Location", format!("/tasks?id={}", task.id));
+    Ok(http_response)
+  }
+
+  fn number(http_request: &HttpRequest, name: &str, default: usize) -> Result<usize, AppError> {
+    match http_request.param(name) {
+      Some(value) => value.parse::<usize>().map_err(|_| AppError::BadRequest(format!("Query parameter `{}` is invalid", name))),
+      None => Ok(default),
+    }
+  }
+
+  fn report(path: &str, node: &UsageNode, top: usize, depth: usize) -> JsonObject {
+    let mut json_object = Json::object();
+    json_object.insert("path", escape_json(path));
+    json_object.insert("top", top as u64);
+    json_object.insert("depth", depth as u64);
+    json_object.insert("tree", node.to_json(top, depth));
+    json_object
+  }
+}
@@ -0,0 +1,185 @@
+use std::{io, path::Path};
+
+use json_builder::{Json, JsonBuilder};
+
+use crate::config::utility::escape_json;
+use crate::enums::app_error::AppError;
+use crate::library::chunks::ChunkStore;
+use crate::library::mime::Mime;
+use crate::library::versions::{PrunePolicy, VersionStore};
+use crate::library::vfs::{Backend, EntryKind, Vfs};
+use crate::parser::{http_request::HttpRequest, http_response::HttpResponse};
+
+pub struct Versions;
+
+impl Versions {
+  pub fn list(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Versions::list_in(Vfs::global(), VersionStore::global(), http_request)
+  }
+
+  pub fn content(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Versions::content_in(Vfs::global(), VersionStore::global(), http_request)
+  }
+
+  pub fn restore(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Versions::restore_in(Vfs::global(), VersionStore::global(), http_request)
+  }
+
+  pub fn prune(http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    Versions::prune_in(Vfs::global(), VersionStore::global(), http_request)
+  }
+}
+
+impl Versions {
+  // NEWEST FIRST, A DELETED FILE KEEPS ITS VERSIONS UNDER THE PATH IT HAD
+  fn list_in(vfs: &Vfs, store: &VersionStore, http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let (path, key, _, _) = Versions::resolve(vfs, store, http_request)?;
+    let versions = store.list(&key)?;
+
+    let mut json_array = Json::array();
+    for version in versions.iter() {
+      let mut json_object = Json::object();
+      json_object.insert("id", version.id);
+      json_object.insert("created", version.created);
+      json_object.insert("modified", version.modified);
+      json_object.insert("size", version.size);
+      json_object.insert("digest", version.digest.as_str());
+      json_object.insert("reason", version.reason.as_str());
+      json_array.append(json_object);
+    }
+
+    let mut json_object = Json::object();
+    json_object.insert("path", escape_json(&path));
+    json_object.insert("count", versions.len() as u64);
+    json_object.insert("versions", json_array);
+
+    let mut http_response = HttpResponse::new("200", "OK", Json::build(json_object));
+    http_response.header("Cache-Control", "no-store");
+    Ok(http_response)
+  }
+
+  fn content_in(vfs: &Vfs, store: &VersionStore, http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let (path, key, backend, relative) = Versions::resolve(vfs, store, http_request)?;
+    let id = Versions::id(http_request)?;
+    let chunked = Versions::is_chunked(backend, &relative);
+    let (version, size, sniffed) = store.content(&key, id, chunked).map_err(|e| Versions::missing(e, &path, id))?;
+    let content_type = Mime::detect_from(Path::new(&path), sniffed);
+    let (_, _, reader) = store.content(&key, id, chunked)?;
+
+    let file_name = path.rsplit('/').next().unwrap_or_default();
+    let mut http_response = HttpResponse::stream("200", "OK", content_type, Some(size as usize), reader);
+    http_response
+      .header("Content-Disposition", format!("attachment; filename=\"{}\"", file_name.replace(['"', '\\', '\r', '\n'], "_")))
+      .header("X-Content-Type-Options", "nosniff")
+      .header("X-Version-Id", version.id.to_string());
+    Ok(http_response)
+  }
+
+  fn restore_in(vfs: &Vfs, store: &VersionStore, http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let (path, key, backend, relative) = Versions::resolve(vfs, store, http_request)?;
+    let id = Versions::id(http_request)?;
+    if backend.stat(&relative).is_ok_and(|s| s.kind != EntryKind::File) {
+      return Err(AppError::Conflict(format!("{} is not a regular file", path)));
+    }
+
+    let chunked = Versions::is_chunked(backend, &relative);
+    let version = store.restore(&key, id, backend, &relative, chunked).map_err(|e| Versions::missing(e, &path, id))?;
+
+    let mut json_object = Json::object();
+    json_object.insert("path", escape_json(&path));
+    json_object.insert("restored", version.id);
+    json_object.insert("size", version.size);
+    Ok(HttpResponse::new("200", "OK", Json::build(json_object)))
+  }
+
+  // DELETE /files/versions?path=&keep=&days=, WITHOUT `path` EVERY VERSIONED FILE IS PRUNED
+  fn prune_in(vfs: &Vfs, store: &VersionStore, http_request: &HttpRequest) -> Result<HttpResponse, AppError> {
+    let key = match http_request.param("path") {
+      Some(_) => Some(Versions::resolve(vfs, store, http_request)?.1),
+      None => None,
+    };
+
+    let number = |name: &str| match http_request.param(name) {
+      Some(value) => value.parse::<u64>().map(Some).map_err(|_| AppError::BadRequest(format!("Query parameter `{}` is invalid", name))),
+      None => Ok(None),
+    };
+
+    let policy = match (number("keep")?, number("days")?) {
+      (None, None) => PrunePolicy::configured(),
+      (keep, days) => PrunePolicy { keep: keep.map(|k| k as usize), max_age: days.map(|d| d * 24 * 60 * 60) },
+    };
+
+    let report = store.prune(key.as_deref(), policy)?;
+    let mut json_object = Json::object();
+    json_object.insert("versions", report.versions);
+    json_object.insert("objects", report.objects);
+    json_object.insert("reclaimed", report.bytes);
+    Ok(HttpResponse::new("200", "OK", Json::build(json_object)))
+  }
+
+  // A FILE ON DISK IS KEPT UNDER ITS PATH BELOW THE STORAGE ROOT, WHERE EVER IT IS MOUNTED. ANY OTHER UNDER ITS PATH IN THE VFS
+  fn resolve<'a>(vfs: &'a Vfs, store: &VersionStore, http_request: &HttpRequest) -> Result<(String, String, &'a dyn Backend, String), AppError> {
+    let path = http_request
+      .param("path")
+      .filter(|p| !p.trim_matches('/').is_empty())
+      .ok_or_else(|| AppError::BadRequest(String::from("Query parameter `path` is required")))?;
+
+    let (backend, relative) = vfs.resolve(path).ok_or_else(|| AppError::Forbidden(format!("{} is outside of the storage root", path)))?;
+    let key = match backend.local_path(&relative) {
+      Some(target) => store.key(&target),
+      None => store.key_of(path),
+    };
+    let key = key.ok_or_else(|| AppError::BadRequest(format!("{} is not under a versioned root", path)))?;
+    Ok((path.clone(), key, backend, relative))
+  }
+
+  fn is_chunked(backend: &dyn Backend, relative: &str) -> bool {
+    backend.local_path(relative).is_some_and(|target| ChunkStore::global().is_chunked(&target))
+  }
+
+  fn id(http_request: &HttpRequest) -> Result<u64, AppError> {
+    http_request
+      .param("id")
+      .ok_or_else(|| AppError::BadRequest(String::from("Query parameter `id` is required")))?
+      .parse::<u64>()
+      .map_err(|_| AppError::BadRequest(String::from("Query parameter `id` is invalid")))
+  }
+
+  fn missing(e: io::Error, path: &str, id: u64) -> AppError {
+    match e.kind() {
+      io::ErrorKind::NotFound => AppError::NotFound(format!("{} has no version {}", path, id)),
+      _ => AppError::Io(e),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{io::Read, sync::Arc};
+
+  use crate::enums::app_error::AppError;
+  use crate::library::{memory_backend::MemoryBackend, testing::{content, put, request, TempDir}, versions::{VersionReason, VersionStore}, vfs::{Backend, Vfs}};
+
+  use super::Versions;
+
+  #[test]
+  fn version_memory_test() {
+    let root = TempDir::new("versions");
+    let store = VersionStore::new(root.join("versions"), root.join("storage"), &["/docs"]);
+    let backend = Arc::new(MemoryBackend::new());
+    let vfs = Vfs::default().mount("/", backend.clone());
+    store.record("/docs/a.txt", &b"first"[..], 0, VersionReason::Overwrite).unwrap();
+    put(backend.as_ref(), "docs/a.txt", b"second");
+
+    let restored = Versions::restore_in(&vfs, &store, &request("POST /files/versions/restore?path=/docs/a.txt&id=1", b"")).unwrap();
+    assert_eq!(restored.status, "200");
+    let mut text = String::new();
+    backend.open_read("docs/a.txt", 0).unwrap().read_to_string(&mut text).unwrap();
+    assert_eq!(text, "first");
+
+    let listing = String::from_utf8(content(Versions::list_in(&vfs, &store, &request("GET /files/versions?path=/docs/a.txt", b"")).unwrap())).unwrap();
+    assert!(listing.contains("\"count\":2") && listing.contains("\"restore\""));
+    assert_eq!(content(Versions::content_in(&vfs, &store, &request("GET /files/versions/content?path=/docs/a.txt&id=2", b"")).unwrap()), b"second");
+    assert!(matches!(Versions::list_in(&vfs, &store, &request("GET /files/versions?path=/other.txt", b"")), Err(AppError::BadRequest(_))));
+  }
+}